@@ -0,0 +1,219 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+const PKG_NAME: &str = env!("CARGO_PKG_NAME");
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// Overrides the location of the config file
+const CONFIG_PATH_ENV: &str = "KAKISUTE_CONFIG";
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_workspace: Option<String>,
+
+    /// Workspace name to data directory
+    #[serde(default)]
+    workspaces: BTreeMap<String, String>,
+
+    /// Mode name to action name to key sequences, overriding the default TUI keybindings
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    keymap: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+
+    /// Built-in theme name and style overrides of the TUI
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    theme: BTreeMap<String, String>,
+
+    /// Columns of the TUI list in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    list_columns: Vec<String>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    fn path() -> PathBuf {
+        if let Ok(path) = env::var(CONFIG_PATH_ENV) {
+            return PathBuf::from(path);
+        }
+        let project_dirs = ProjectDirs::from("", "", PKG_NAME).unwrap();
+        project_dirs.config_dir().join(CONFIG_FILE_NAME)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config {}", path.display()))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to save config {}", path.display()))
+    }
+
+    pub fn default_workspace(&self) -> Option<&str> {
+        self.default_workspace.as_deref()
+    }
+
+    /// Pairs of workspace name and data directory, sorted by name
+    pub fn workspaces(&self) -> Vec<(&str, &str)> {
+        self.workspaces
+            .iter()
+            .map(|(name, data_dir)| (name.as_str(), data_dir.as_str()))
+            .collect()
+    }
+
+    pub fn workspace_names(&self) -> Vec<String> {
+        self.workspaces.keys().cloned().collect()
+    }
+
+    pub fn add_workspace(&mut self, name: &str, data_dir: &str) -> Result<()> {
+        if self.workspaces.contains_key(name) {
+            return Err(anyhow!("Workspace already exists: {}", name));
+        }
+        self.workspaces
+            .insert(name.to_string(), data_dir.to_string());
+        Ok(())
+    }
+
+    pub fn remove_workspace(&mut self, name: &str) -> Result<()> {
+        if self.workspaces.remove(name).is_none() {
+            return Err(anyhow!("Workspace not found: {}", name));
+        }
+        if self.default_workspace() == Some(name) {
+            self.default_workspace = None;
+        }
+        Ok(())
+    }
+
+    /// None resets the default workspace
+    pub fn set_default_workspace(&mut self, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name {
+            if !self.workspaces.contains_key(name) {
+                return Err(anyhow!("Workspace not found: {}", name));
+            }
+        }
+        self.default_workspace = name.map(|name| name.to_string());
+        Ok(())
+    }
+
+    /// Resolve the workspace name to use. The default workspace is used when not specified.
+    pub fn resolve_workspace(&self, name: Option<&str>) -> Option<String> {
+        name.or_else(|| self.default_workspace())
+            .map(|name| name.to_string())
+    }
+
+    pub fn keymap(&self) -> &BTreeMap<String, BTreeMap<String, Vec<String>>> {
+        &self.keymap
+    }
+
+    pub fn theme(&self) -> &BTreeMap<String, String> {
+        &self.theme
+    }
+
+    pub fn list_columns(&self) -> &[String] {
+        &self.list_columns
+    }
+
+    /// Data directory of the workspace, which is passed to `DataDir::setup`
+    pub fn workspace_data_dir(&self, name: &str) -> Result<String> {
+        self.workspaces
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Workspace not found: {}", name))
+    }
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "workspaces" {
+        before {
+            let mut config = Config::default();
+            config.add_workspace("work", "~/work").unwrap();
+            config.add_workspace("personal", "~/personal").unwrap();
+        }
+
+        it "are sorted by name" {
+            assert_eq!(config.workspaces(), vec![("personal", "~/personal"), ("work", "~/work")]);
+        }
+
+        it "can not be added twice" {
+            assert!(config.add_workspace("work", "~/other").is_err());
+        }
+
+        it "use the default workspace when not specified" {
+            assert_eq!(config.resolve_workspace(None), None);
+            config.set_default_workspace(Some("work")).unwrap();
+            assert_eq!(config.resolve_workspace(None), Some("work".to_string()));
+            assert_eq!(config.resolve_workspace(Some("personal")), Some("personal".to_string()));
+        }
+
+        it "reset the default workspace when it is removed" {
+            config.set_default_workspace(Some("work")).unwrap();
+            config.remove_workspace("work").unwrap();
+            assert_eq!(config.default_workspace(), None);
+            assert!(config.workspace_data_dir("work").is_err());
+        }
+
+        it "reject unknown default workspace" {
+            assert!(config.set_default_workspace(Some("unknown")).is_err());
+        }
+    }
+
+    describe "config file" {
+        it "round trips" {
+            let mut config = Config::default();
+            config.add_workspace("work", "~/work").unwrap();
+            config.set_default_workspace(Some("work")).unwrap();
+            let content = toml::to_string(&config).unwrap();
+            let parsed: Config = toml::from_str(&content).unwrap();
+            assert_eq!(parsed, config);
+        }
+
+        it "accepts empty file" {
+            let parsed: Config = toml::from_str("").unwrap();
+            assert_eq!(parsed, Config::default());
+        }
+
+        it "reads keymap" {
+            let parsed: Config = toml::from_str("[keymap.normal]\ntop = [\"g g\", \"home\"]\n").unwrap();
+            assert_eq!(parsed.keymap()["normal"]["top"], vec!["g g", "home"]);
+        }
+
+        it "reads theme" {
+            let parsed: Config = toml::from_str("[theme]\nname = \"light\"\nhighlight = \"black on cyan\"\n").unwrap();
+            assert_eq!(parsed.theme()["name"], "light");
+            assert_eq!(parsed.theme()["highlight"], "black on cyan");
+        }
+
+        it "reads list columns" {
+            let parsed: Config = toml::from_str("list_columns = [\"date\", \"name\"]\n").unwrap();
+            assert_eq!(parsed.list_columns(), ["date", "name"]);
+        }
+    }
+}
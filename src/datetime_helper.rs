@@ -1,7 +1,10 @@
-use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
 
 const DATE_FORMAT: &str = "%Y_%m_%d_%H_%M_%S";
 const DATE_FORMAT_LENGTH: usize = 19;
+const USER_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Datetimes older than this are shown as the date
+const HUMANIZED_DAYS: i64 = 30;
 
 pub fn datetime_to_string(datetime: DateTime<Local>) -> String {
     datetime.format(DATE_FORMAT).to_string()
@@ -22,6 +25,36 @@ pub fn string_to_datetime(datetime: &str) -> LocalResult<DateTime<Local>> {
     }
 }
 
+/// Name given by the user after the datetime prefix, e.g. "test.sql" of "2022_01_10_16_30_15_test.sql"
+pub fn strip_datetime(file_name: &str) -> &str {
+    if string_to_datetime(file_name).single().is_none() {
+        return file_name;
+    }
+    let name = &file_name[DATE_FORMAT_LENGTH..];
+    name.strip_prefix('_').unwrap_or(name)
+}
+
+/// Short elapsed time such as "3h ago". Older or future datetimes are shown as the date.
+pub fn humanize_elapsed(datetime: DateTime<Local>, now: DateTime<Local>) -> String {
+    let elapsed = now.signed_duration_since(datetime);
+    if elapsed.num_seconds() < 0 || elapsed.num_days() >= HUMANIZED_DAYS {
+        datetime.format(USER_DATE_FORMAT).to_string()
+    } else if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        format!("{}m ago", elapsed.num_minutes())
+    } else if elapsed.num_days() < 1 {
+        format!("{}h ago", elapsed.num_hours())
+    } else {
+        format!("{}d ago", elapsed.num_days())
+    }
+}
+
+/// Parse date given by users like "2022-01-10"
+pub fn string_to_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, USER_DATE_FORMAT).ok()
+}
+
 #[cfg(test)]
 extern crate speculate;
 #[cfg(test)]
@@ -65,4 +98,43 @@ speculate! {
             assert_eq!(actual.single(),None);
         }
     }
+
+    describe "strip_datetime" {
+        it "returns the name after the datetime" {
+            assert_eq!(strip_datetime("2022_01_10_16_30_15_test.sql"), "test.sql");
+            assert_eq!(strip_datetime("2022_01_10_16_30_15.txt"), ".txt");
+        }
+
+        it "returns the whole name without datetime" {
+            assert_eq!(strip_datetime("test.sql"), "test.sql");
+        }
+    }
+
+    describe "humanize_elapsed" {
+        before {
+            let now = Local.ymd(2022,1,10).and_hms(16,30,15);
+        }
+
+        it "shows the elapsed time in the largest unit" {
+            assert_eq!(humanize_elapsed(now, now), "just now");
+            assert_eq!(humanize_elapsed(now - chrono::Duration::minutes(5), now), "5m ago");
+            assert_eq!(humanize_elapsed(now - chrono::Duration::hours(3), now), "3h ago");
+            assert_eq!(humanize_elapsed(now - chrono::Duration::days(2), now), "2d ago");
+        }
+
+        it "shows the date of old and future datetimes" {
+            assert_eq!(humanize_elapsed(now - chrono::Duration::days(40), now), "2021-12-01");
+            assert_eq!(humanize_elapsed(now + chrono::Duration::hours(1), now), "2022-01-10");
+        }
+    }
+
+    describe "string_to_date" {
+        it "return date when format is correct" {
+            assert_eq!(string_to_date("2022-01-10"), Some(NaiveDate::from_ymd(2022,1,10)));
+        }
+
+        it "return none when format is wrong" {
+            assert_eq!(string_to_date("2022_01_10"), None);
+        }
+    }
 }
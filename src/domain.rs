@@ -1 +1,5 @@
 pub mod kakisute;
+pub mod metadata;
+pub mod notebook;
+pub mod searches;
+pub mod template;
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Information about kakisute which can not be expressed by the file itself
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    #[serde(default)]
+    pinned: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, Vec<String>>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pinned(&self) -> &[String] {
+        &self.pinned
+    }
+
+    pub fn is_pinned(&self, file_name: &str) -> bool {
+        self.pinned.iter().any(|pinned| pinned == file_name)
+    }
+
+    /// Returns false if the file is already pinned
+    pub fn pin(&mut self, file_name: &str) -> bool {
+        if self.is_pinned(file_name) {
+            return false;
+        }
+        self.pinned.push(file_name.to_string());
+        self.pinned.sort();
+        true
+    }
+
+    /// Returns false if the file is not pinned
+    pub fn unpin(&mut self, file_name: &str) -> bool {
+        let len = self.pinned.len();
+        self.pinned.retain(|pinned| pinned != file_name);
+        len != self.pinned.len()
+    }
+
+    pub fn tags(&self, file_name: &str) -> &[String] {
+        self.tags
+            .get(file_name)
+            .map(|tags| tags.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// File name to tags of every tagged file
+    pub fn tagged(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, file_name: &str, tag: &str) -> bool {
+        self.tags(file_name).iter().any(|t| t == tag)
+    }
+
+    /// Every tag used by any file, sorted and deduplicated
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut all_tags = self
+            .tags
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<String>>();
+        all_tags.sort();
+        all_tags.dedup();
+        all_tags
+    }
+
+    /// Returns false if the file already has the tag
+    pub fn add_tag(&mut self, file_name: &str, tag: &str) -> bool {
+        if self.has_tag(file_name, tag) {
+            return false;
+        }
+        let tags = self.tags.entry(file_name.to_string()).or_default();
+        tags.push(tag.to_string());
+        tags.sort();
+        true
+    }
+
+    /// Returns false if the file does not have the tag
+    pub fn remove_tag(&mut self, file_name: &str, tag: &str) -> bool {
+        let removed = match self.tags.get_mut(file_name) {
+            Some(tags) => {
+                let len = tags.len();
+                tags.retain(|t| t != tag);
+                len != tags.len()
+            }
+            None => false,
+        };
+        if self.tags(file_name).is_empty() {
+            self.tags.remove(file_name);
+        }
+        removed
+    }
+
+    /// Copy information about `from` in other metadata as `to`. Returns true if something is changed
+    pub fn copy_from(&mut self, other: &Metadata, from: &str, to: &str) -> bool {
+        let mut changed = false;
+        if other.is_pinned(from) {
+            changed |= self.pin(to);
+        }
+        for tag in other.tags(from) {
+            changed |= self.add_tag(to, tag);
+        }
+        changed
+    }
+
+    /// Metadata only about the given files
+    pub fn subset(&self, file_names: &[String]) -> Metadata {
+        let mut subset = Metadata::new();
+        for file_name in file_names {
+            subset.copy_from(self, file_name, file_name);
+        }
+        subset
+    }
+
+    /// Carry information over to the new name. Returns true if something is changed
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        let old = self.clone();
+        if self.remove(from) {
+            self.copy_from(&old, from, to);
+            return true;
+        }
+        false
+    }
+
+    /// Forget everything about the file. Returns true if something is removed
+    pub fn remove(&mut self, file_name: &str) -> bool {
+        let unpinned = self.unpin(file_name);
+        let untagged = self.tags.remove(file_name).is_some();
+        unpinned || untagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+
+    #[test]
+    fn test_pin_and_unpin() {
+        let mut metadata = Metadata::new();
+        assert!(!metadata.is_pinned("a"));
+        assert!(metadata.pin("b"));
+        assert!(metadata.pin("a"));
+        assert!(!metadata.pin("a"));
+        assert_eq!(metadata.pinned(), ["a", "b"]);
+        assert!(metadata.unpin("a"));
+        assert!(!metadata.unpin("a"));
+        assert_eq!(metadata.pinned(), ["b"]);
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut metadata = Metadata::new();
+        metadata.pin("a");
+        assert!(metadata.rename("a", "work/a"));
+        assert!(!metadata.rename("b", "work/b"));
+        assert_eq!(metadata.pinned(), ["work/a"]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut metadata = Metadata::new();
+        assert!(metadata.add_tag("a", "sql"));
+        assert!(metadata.add_tag("a", "prod"));
+        assert!(!metadata.add_tag("a", "sql"));
+        assert!(metadata.add_tag("b", "sql"));
+        assert_eq!(metadata.tags("a"), ["prod", "sql"]);
+        assert_eq!(metadata.all_tags(), ["prod", "sql"]);
+        assert!(metadata.remove_tag("a", "prod"));
+        assert!(!metadata.remove_tag("a", "prod"));
+        assert!(metadata.remove_tag("b", "sql"));
+        assert!(metadata.tags("b").is_empty());
+        assert_eq!(metadata.all_tags(), ["sql"]);
+    }
+
+    #[test]
+    fn test_rename_and_subset_keep_tags() {
+        let mut metadata = Metadata::new();
+        metadata.add_tag("a", "sql");
+        metadata.pin("b");
+        assert!(metadata.rename("a", "c"));
+        assert_eq!(metadata.tags("c"), ["sql"]);
+        assert!(metadata.tags("a").is_empty());
+
+        let subset = metadata.subset(&["c".to_string()]);
+        assert_eq!(subset.tags("c"), ["sql"]);
+        assert!(!subset.is_pinned("b"));
+    }
+
+    #[test]
+    fn test_deserialize_empty_object() {
+        let metadata: Metadata = serde_json::from_str("{}").unwrap();
+        assert_eq!(metadata, Metadata::new());
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+
+const SEPARATOR: char = '/';
+
+/// Normalize notebook name given by users.
+/// Returns None for the top level of the data directory (empty string or "/").
+pub fn normalize(notebook: &str) -> Result<Option<String>> {
+    let components = notebook
+        .split(SEPARATOR)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<&str>>();
+
+    if let Some(component) = components
+        .iter()
+        .find(|component| component.starts_with('.'))
+    {
+        return Err(anyhow!("Invalid notebook name: {}", component));
+    }
+
+    if components.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(components.join("/")))
+    }
+}
+
+/// File name given by users must stay in its notebook
+pub fn validate_file_name(file_name: &str) -> Result<()> {
+    if file_name.contains([SEPARATOR, '\\']) || file_name.starts_with('.') {
+        return Err(anyhow!("Invalid file name: {}", file_name));
+    }
+    Ok(())
+}
+
+/// Join notebook and file name into the path relative to the data directory
+pub fn join(notebook: Option<&str>, file_name: &str) -> String {
+    match notebook {
+        Some(notebook) => format!("{}{}{}", notebook, SEPARATOR, file_name),
+        None => file_name.to_string(),
+    }
+}
+
+/// Split the path relative to the data directory into notebook and file name
+pub fn split(path_name: &str) -> (Option<&str>, &str) {
+    match path_name.rsplit_once(SEPARATOR) {
+        Some((notebook, file_name)) => (Some(notebook), file_name),
+        None => (None, path_name),
+    }
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "normalize" {
+        it "returns None for the top level" {
+            assert_eq!(normalize("").unwrap(), None);
+            assert_eq!(normalize("/").unwrap(), None);
+        }
+
+        it "trims separators" {
+            assert_eq!(normalize("/work/db/").unwrap(), Some("work/db".to_string()));
+        }
+
+        it "rejects hidden and parent directories" {
+            assert!(normalize("../work").is_err());
+            assert!(normalize(".templates").is_err());
+        }
+    }
+
+    describe "join and split" {
+        it "are reversible" {
+            let path_name = join(Some("work"), "2022_01_10_16_30_15.txt");
+            assert_eq!(path_name, "work/2022_01_10_16_30_15.txt");
+            assert_eq!(split(&path_name), (Some("work"), "2022_01_10_16_30_15.txt"));
+        }
+
+        it "keeps top level file as is" {
+            assert_eq!(join(None, "a.txt"), "a.txt");
+            assert_eq!(split("a.txt"), (None, "a.txt"));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Older queries are forgotten beyond this
+const MAX_HISTORY: usize = 100;
+
+/// Queries searched so far and the ones saved by name
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Searches {
+    /// Oldest first
+    #[serde(default)]
+    history: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    saved: BTreeMap<String, String>,
+}
+
+impl Searches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// The query is moved to the newest if it was searched before. Returns false for an empty query.
+    pub fn add_history(&mut self, query: &str) -> bool {
+        if query.is_empty() {
+            return false;
+        }
+        self.history.retain(|searched| searched != query);
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(query.to_string());
+        true
+    }
+
+    /// Name to query, sorted by name
+    pub fn saved(&self) -> &BTreeMap<String, String> {
+        &self.saved
+    }
+
+    /// Overwrites the query saved with the same name
+    pub fn save(&mut self, name: &str, query: &str) {
+        self.saved.insert(name.to_string(), query.to_string());
+    }
+
+    /// Returns false if no query is saved with the name
+    pub fn forget(&mut self, name: &str) -> bool {
+        self.saved.remove(name).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_history() {
+        let mut searches = Searches::new();
+        assert!(searches.add_history("a"));
+        assert!(searches.add_history("b"));
+        assert!(searches.add_history("a"));
+        assert!(!searches.add_history(""));
+        assert_eq!(searches.history(), ["b", "a"]);
+        for i in 0..MAX_HISTORY {
+            searches.add_history(&i.to_string());
+        }
+        assert_eq!(searches.history().len(), MAX_HISTORY);
+        assert_eq!(searches.history()[0], "0");
+    }
+
+    #[test]
+    fn test_save() {
+        let mut searches = Searches::new();
+        searches.save("prod", "SELECT.*prod");
+        searches.save("prod", "prod");
+        assert_eq!(searches.saved().get("prod").unwrap(), "prod");
+        assert!(searches.forget("prod"));
+        assert!(!searches.forget("prod"));
+    }
+
+    #[test]
+    fn test_deserialize_without_saved() {
+        let searches: Searches = serde_json::from_str(r#"{"history":["a"]}"#).unwrap();
+        assert_eq!(searches.history(), ["a"]);
+        assert!(searches.saved().is_empty());
+    }
+}
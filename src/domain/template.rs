@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+const ENV_PREFIX: &str = "env:";
+
+/// Content used to pre-fill a new kakisute.
+///
+/// Supported placeholders are `{{date}}`, `{{time}}`, `{{datetime}}`, `{{name}}` and
+/// `{{env:VAR}}`. Unknown placeholders are left as they are.
+pub struct Template {
+    file_name: String,
+    content: String,
+}
+
+pub struct TemplateContext<'a> {
+    pub created_at: DateTime<Local>,
+    pub name: Option<&'a str>,
+}
+
+impl Template {
+    pub fn new(file_name: &str, content: String) -> Self {
+        Template {
+            file_name: file_name.to_string(),
+            content,
+        }
+    }
+
+    /// Template name is the file name without extension. e.g. `incident` for `incident.md`
+    pub fn name(&self) -> String {
+        Self::name_of(&self.file_name)
+    }
+
+    pub fn name_of(file_name: &str) -> String {
+        Path::new(file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.to_string())
+    }
+
+    pub fn extension(&self) -> Option<String> {
+        Path::new(&self.file_name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+    }
+
+    pub fn render(&self, context: &TemplateContext) -> String {
+        let mut rendered = String::new();
+        let mut rest = self.content.as_str();
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            let after_start = &rest[start + PLACEHOLDER_START.len()..];
+            let end = match after_start.find(PLACEHOLDER_END) {
+                Some(end) => end,
+                None => break,
+            };
+            let key = after_start[..end].trim();
+            rendered.push_str(&rest[..start]);
+            match Self::resolve(key, context) {
+                Some(value) => rendered.push_str(&value),
+                None => rendered.push_str(
+                    &rest[start..start + PLACEHOLDER_START.len() + end + PLACEHOLDER_END.len()],
+                ),
+            }
+            rest = &after_start[end + PLACEHOLDER_END.len()..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+
+    fn resolve(key: &str, context: &TemplateContext) -> Option<String> {
+        match key {
+            "date" => Some(context.created_at.format("%Y-%m-%d").to_string()),
+            "time" => Some(context.created_at.format("%H:%M:%S").to_string()),
+            "datetime" => Some(context.created_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            "name" => Some(context.name.unwrap_or_default().to_string()),
+            _ => key
+                .strip_prefix(ENV_PREFIX)
+                .map(|var| std::env::var(var.trim()).unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use chrono::TimeZone;
+
+    describe "render" {
+        before {
+            let context = TemplateContext {
+                created_at: Local.ymd(2022, 1, 10).and_hms(16, 30, 15),
+                name: Some("incident"),
+            };
+        }
+
+        it "replaces date, time and name" {
+            let template = Template::new("a.md", "# {{name}} {{date}} {{ time }}".to_string());
+            assert_eq!(template.render(&context), "# incident 2022-01-10 16:30:15")
+        }
+
+        it "replaces env vars" {
+            std::env::set_var("KAKISUTE_TEMPLATE_TEST", "value");
+            let template = Template::new("a.md", "{{env:KAKISUTE_TEMPLATE_TEST}}".to_string());
+            assert_eq!(template.render(&context), "value")
+        }
+
+        it "keeps unknown and unclosed placeholders" {
+            let template = Template::new("a.md", "{{unknown}} {{date".to_string());
+            assert_eq!(template.render(&context), "{{unknown}} {{date")
+        }
+    }
+
+    describe "name and extension" {
+        it "split file name" {
+            let template = Template::new("incident.md", String::new());
+            assert_eq!(template.name(), "incident");
+            assert_eq!(template.extension(), Some("md".to_string()))
+        }
+    }
+}
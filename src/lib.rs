@@ -1,5 +1,10 @@
+pub mod config;
 pub mod datetime_helper;
 pub mod domain;
 pub mod repository;
+pub mod rpc;
+pub mod server;
 pub mod service;
+#[cfg(test)]
+mod test_dir;
 pub mod ui;
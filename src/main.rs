@@ -1,11 +1,31 @@
+use std::fs::File;
 use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
 
+use anyhow::{anyhow, Context};
 use clap::{AppSettings, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Generator, Shell};
 use kakisute::{
+    config::Config,
+    datetime_helper::string_to_date,
     repository::Repository,
-    service::{interface::IRepository, kakisute_list::KakisuteList, Service, ServiceTrait},
-    ui,
+    rpc::{self, SharedWriter},
+    server::{self, ServerOptions},
+    service::{
+        archive::ConflictPolicy,
+        importer::{self, ImportFormat},
+        interface::IRepository,
+        kakisute_list::KakisuteList,
+        site, Service, ServiceTrait, Workspace,
+    },
+    ui::{
+        self,
+        index::{AppExit, Selection, Workspaces},
+        keymap::Keymap,
+        list_columns,
+        theme::Theme,
+    },
 };
 
 #[derive(Parser, Debug)]
@@ -17,6 +37,14 @@ struct Args {
     /// <Optional> Specify the directory to store kakisute
     #[clap(long = "data_dir")]
     data_dir: Option<String>,
+
+    /// <Optional> Specify the workspace registered by "workspace add"
+    #[clap(long = "workspace", short = 'w', conflicts_with = "data-dir")]
+    workspace: Option<String>,
+
+    /// <Optional> Specify the notebook (subdirectory of the data directory). "/" means the top level
+    #[clap(long = "notebook", global = true)]
+    notebook: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -26,15 +54,29 @@ enum Action {
     New {
         /// <Optional> Specify kakisute name
         kakisute_name: Option<String>,
+
+        /// <Optional> Pre-fill content with the template in the templates directory
+        #[clap(long = "template", short = 't')]
+        template: Option<String>,
     },
 
+    /// Print available templates
+    Templates {},
+
     /// Print kakisute list
-    List {},
+    List {
+        /// Print only pinned kakisute
+        #[clap(long = "pinned")]
+        is_pinned: bool,
+    },
 
     /// Edit kakisute
     Edit {
         #[clap(long = "latest")]
         is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
         kakisute_name: Option<String>,
     },
 
@@ -42,6 +84,9 @@ enum Action {
     Show {
         #[clap(long = "latest")]
         is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
         kakisute_name: Option<String>,
     },
 
@@ -49,6 +94,9 @@ enum Action {
     Inspect {
         #[clap(long = "latest")]
         is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
         kakisute_name: Option<String>,
     },
 
@@ -56,15 +104,134 @@ enum Action {
     Delete {
         #[clap(long = "latest")]
         is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
+        kakisute_name: Option<String>,
+    },
+
+    /// Pin kakisute to keep it at the top of the list
+    Pin {
+        #[clap(long = "latest")]
+        is_latest: bool,
+        kakisute_name: Option<String>,
+    },
+
+    /// Unpin kakisute
+    Unpin {
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
+        kakisute_name: Option<String>,
+    },
+
+    /// Move kakisute to another notebook
+    Move {
+        #[clap(long = "latest")]
+        is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
         kakisute_name: Option<String>,
+        /// Destination notebook. "/" means the top level
+        #[clap(long = "to")]
+        to: String,
+    },
+
+    /// Print notebook list
+    Notebooks {},
+
+    /// Add or remove tags, then print tags of kakisute
+    Tag {
+        #[clap(long = "latest")]
+        is_latest: bool,
+        /// Select n-th pinned kakisute (starts from 1)
+        #[clap(long = "pinned")]
+        pinned: Option<usize>,
+        kakisute_name: Option<String>,
+        /// Tag to add. Can be given multiple times
+        #[clap(long = "add")]
+        add: Vec<String>,
+        /// Tag to remove. Can be given multiple times
+        #[clap(long = "remove")]
+        remove: Vec<String>,
+    },
+
+    /// Export kakisute into tar.gz archive
+    Export {
+        /// Path of the archive to create
+        #[clap(long = "out")]
+        out: String,
+        /// Export only kakisute created on or after the date (e.g. 2022-01-10)
+        #[clap(long = "since")]
+        since: Option<String>,
+        /// Export only kakisute with the tag
+        #[clap(long = "tag")]
+        tag: Option<String>,
+    },
+
+    /// Export kakisute as a static HTML site
+    ExportHtml {
+        /// Directory to write the site into
+        out_dir: String,
+        /// Title of the site
+        #[clap(long = "title", default_value = "kakisute")]
+        title: String,
+    },
+
+    /// Import kakisute from archive created by export, directory, Markdown vault or gist JSON
+    Import {
+        /// Archive, directory or JSON file to import
+        source: String,
+        /// Format of the source: archive, dir, obsidian or gist
+        #[clap(long = "format", default_value = "archive")]
+        format: ImportFormat,
+        /// What to do when the file already exists: skip, overwrite or keep-both
+        #[clap(long = "on-conflict", default_value = "skip")]
+        on_conflict: ConflictPolicy,
     },
 
     /// Search kakisute
-    Search { query: String },
+    Search {
+        /// Regex or `field:value` terms such as `tag:sql`, joined with AND, OR and NOT
+        #[clap(required_unless_present = "forget")]
+        query: Option<String>,
+        /// Search every configured workspace
+        #[clap(long = "all-workspaces")]
+        all_workspaces: bool,
+        /// Save the query by name. Saved searches are shown as folders in TUI mode
+        #[clap(long = "save", value_name = "NAME")]
+        save: Option<String>,
+        /// Remove the saved search instead of searching
+        #[clap(long = "forget", value_name = "NAME", conflicts_with = "save")]
+        forget: Option<String>,
+    },
 
     /// Start TUI mode
     Interact {},
 
+    /// Speak line-delimited JSON-RPC on stdin and stdout for editor plugins
+    Rpc {},
+
+    /// Serve REST API and web UI
+    Serve {
+        /// Address to listen on
+        #[clap(long = "bind", default_value = "127.0.0.1:8080")]
+        bind: String,
+        /// Require "Authorization: Bearer <token>". KAKISUTE_TOKEN is used when not given
+        #[clap(long = "token")]
+        token: Option<String>,
+        /// Reject requests that change kakisute
+        #[clap(long = "read-only")]
+        read_only: bool,
+    },
+
+    /// Manage named workspaces
+    Workspace {
+        #[clap(subcommand)]
+        action: WorkspaceAction,
+    },
+
     /// Generate completion script
     Completion {
         #[clap(long, short, arg_enum)]
@@ -72,19 +239,74 @@ enum Action {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum WorkspaceAction {
+    /// Print workspace list. "*" marks the default workspace
+    List {},
+
+    /// Add workspace
+    Add { name: String, data_dir: String },
+
+    /// Remove workspace. The data directory is kept as it is
+    Remove { name: String },
+
+    /// Set the default workspace. Reset it if name is not given
+    Default { name: Option<String> },
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Args::parse();
-    let repository = Repository::new(cli.data_dir);
-    let kakisute_list = KakisuteList::from_dir(repository.read_dir());
+    let mut config = Config::load()?;
+
+    if let Action::Workspace { action } = cli.action {
+        return handle_workspace_action(action, &mut config);
+    }
+
+    let workspace = if cli.data_dir.is_some() {
+        None
+    } else {
+        config.resolve_workspace(cli.workspace.as_deref())
+    };
+
+    if let Action::Interact {} = cli.action {
+        return interact(&config, cli.data_dir, workspace, cli.notebook);
+    }
+
+    let data_dir = match &workspace {
+        Some(workspace) => Some(config.workspace_data_dir(workspace)?),
+        None => cli.data_dir,
+    };
+    let repository = Repository::new(data_dir);
+    let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata()?);
     let mut service = Service::new(&repository, &kakisute_list);
+    service.set_workspaces(workspace_list(&config));
+    if cli.notebook.is_some() {
+        service.switch_notebook(cli.notebook.as_deref())?;
+    }
 
     match cli.action {
-        Action::New { kakisute_name } => {
-            let created_kakisute_name = service.create_kakisute(kakisute_name.as_deref())?;
+        Action::New {
+            kakisute_name,
+            template,
+        } => {
+            let created_kakisute_name = if let Some(template) = template {
+                service.create_kakisute_from_template(kakisute_name.as_deref(), &template)?
+            } else {
+                service.create_kakisute(kakisute_name.as_deref())?
+            };
             println!("Created: {}", created_kakisute_name);
         }
-        Action::List {} => {
-            let kakisute_list = service.get_kakisute_list();
+        Action::Templates {} => {
+            for template_name in service.get_template_list()? {
+                println!("{}", template_name);
+            }
+        }
+        Action::List { is_pinned } => {
+            let kakisute_list = if is_pinned {
+                service.get_pinned_kakisute_list()
+            } else {
+                service.get_kakisute_list()
+            };
 
             let stdout = io::stdout();
             let mut handle = io::BufWriter::new(stdout);
@@ -94,45 +316,288 @@ fn main() -> anyhow::Result<()> {
         }
         Action::Edit {
             is_latest,
+            pinned,
             kakisute_name,
         } => {
-            let query = kakisute::service::search_query::SingleQuery::new(is_latest, kakisute_name);
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
             let edited_kakisute_name = service.edit_by_single_query(query)?;
             println!("Edited: {}", edited_kakisute_name);
         }
         Action::Show {
             is_latest,
+            pinned,
             kakisute_name,
         } => {
-            let query = kakisute::service::search_query::SingleQuery::new(is_latest, kakisute_name);
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
             let content = service.get_content_by_single_query(query)?;
             println!("{}", content);
         }
         Action::Inspect {
             is_latest,
+            pinned,
             kakisute_name,
         } => {
-            let query = kakisute::service::search_query::SingleQuery::new(is_latest, kakisute_name);
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
             let info = service.inspect_by_query(query)?;
             println!("{}", info);
         }
         Action::Delete {
             is_latest,
+            pinned,
             kakisute_name,
         } => {
-            let query = kakisute::service::search_query::SingleQuery::new(is_latest, kakisute_name);
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
             let deleted_kakisute_name = service.delete_by_single_query(query)?;
             println!("Deleted: {}", deleted_kakisute_name);
         }
-        Action::Search { query } => {
-            service.search_cli(&query)?;
+        Action::Pin {
+            is_latest,
+            kakisute_name,
+        } => {
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, None, kakisute_name);
+            let pinned_kakisute_name = service.pin_by_single_query(query)?;
+            println!("Pinned: {}", pinned_kakisute_name);
+        }
+        Action::Unpin {
+            pinned,
+            kakisute_name,
+        } => {
+            let query =
+                kakisute::service::search_query::SingleQuery::new(false, pinned, kakisute_name);
+            let unpinned_kakisute_name = service.unpin_by_single_query(query)?;
+            println!("Unpinned: {}", unpinned_kakisute_name);
+        }
+        Action::Move {
+            is_latest,
+            pinned,
+            kakisute_name,
+            to,
+        } => {
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
+            let moved_kakisute_name = service.move_by_single_query(query, &to)?;
+            println!("Moved: {}", moved_kakisute_name);
+        }
+        Action::Notebooks {} => {
+            for notebook in service.get_notebook_list()? {
+                println!("{}", notebook);
+            }
+        }
+        Action::Tag {
+            is_latest,
+            pinned,
+            kakisute_name,
+            add,
+            remove,
+        } => {
+            let query =
+                kakisute::service::search_query::SingleQuery::new(is_latest, pinned, kakisute_name);
+            let (tagged_kakisute_name, tags) = service.tag_by_single_query(query, &add, &remove)?;
+            println!("{}: {}", tagged_kakisute_name, tags.join(", "));
+        }
+        Action::Export { out, since, tag } => {
+            let since = match since {
+                Some(since) => Some(
+                    string_to_date(&since)
+                        .ok_or_else(|| anyhow!("Invalid date: {} (e.g. 2022-01-10)", since))?,
+                ),
+                None => None,
+            };
+            let file = File::create(&out).with_context(|| format!("Failed to create {}", out))?;
+            let report = service.export_archive(file, since, tag.as_deref())?;
+            println!("Exported: {} kakisute to {}", report.exported, out);
+            for (file_name, reason) in &report.skipped {
+                println!("Skipped: {} ({})", file_name, reason);
+            }
+        }
+        Action::ExportHtml { out_dir, title } => {
+            let entries = service.get_site_entries()?;
+            let pages = site::build_site(&title, &entries);
+            site::write_site(Path::new(&out_dir), &pages)?;
+            println!("Exported: {} kakisute to {}", entries.len(), out_dir);
+        }
+        Action::Import {
+            source,
+            format,
+            on_conflict,
+        } => {
+            let content = importer::read(format, Path::new(&source))?;
+            let report = service.import(content, on_conflict)?;
+            for file_name in &report.imported {
+                println!("Imported: {}", file_name);
+            }
+            for file_name in &report.overwritten {
+                println!("Overwritten: {}", file_name);
+            }
+            for (from, to) in &report.renamed {
+                println!("Renamed: {} -> {}", from, to);
+            }
+            for (file_name, reason) in &report.skipped {
+                println!("Skipped: {} ({})", file_name, reason);
+            }
+        }
+        Action::Search {
+            query,
+            all_workspaces,
+            save,
+            forget,
+        } => {
+            if let Some(name) = forget {
+                service.forget_search(&name)?;
+                println!("Forgot: {}", name);
+            } else {
+                // Required by clap unless forgetting
+                let query = query.unwrap_or_default();
+                if all_workspaces {
+                    service.search_cli_all_workspaces(&query)?;
+                } else {
+                    service.search_cli(&query)?;
+                }
+                service.add_search_history(&query)?;
+                if let Some(name) = save {
+                    service.save_search(&name, &query)?;
+                    eprintln!("Saved: {}", name);
+                }
+            }
         }
-        Action::Interact {} => {
-            let _ = ui::index::run_app(&mut service);
+        Action::Rpc {} => {
+            let stdout: SharedWriter = Arc::new(Mutex::new(io::stdout()));
+            let notifier = Arc::clone(&stdout);
+            let _watcher = repository.watch(move |event| {
+                // The client may have gone; the main loop ends when stdin is closed
+                let _ = rpc::send(&notifier, &rpc::notification(&event));
+            })?;
+            rpc::run(&service, io::stdin().lock(), &stdout)?;
+        }
+        Action::Serve {
+            bind,
+            token,
+            read_only,
+        } => {
+            let options = ServerOptions {
+                bind,
+                token: token.or_else(|| std::env::var("KAKISUTE_TOKEN").ok()),
+                read_only,
+            };
+            server::serve(&service, &options)?;
         }
         Action::Completion { shell } => {
             print_completer(shell);
         }
+        Action::Interact {} | Action::Workspace { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Start TUI mode. Reopen it with another workspace when switched in the TUI.
+fn interact(
+    config: &Config,
+    data_dir: Option<String>,
+    mut workspace: Option<String>,
+    notebook: Option<String>,
+) -> anyhow::Result<()> {
+    let mut data_dir = match &workspace {
+        Some(workspace) => Some(config.workspace_data_dir(workspace)?),
+        None => data_dir,
+    };
+    let keymap = Keymap::from_config(config.keymap())?;
+    let theme = Theme::from_config(config.theme())?;
+    let columns = list_columns::from_config(config.list_columns())?;
+    let mut selection = Selection::default();
+    loop {
+        let repository = Repository::new(data_dir);
+        let kakisute_list =
+            KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata()?);
+        let mut service = Service::new(&repository, &kakisute_list);
+        service.set_workspaces(workspace_list(config));
+        if notebook.is_some() {
+            service.switch_notebook(notebook.as_deref())?;
+        }
+
+        // Live reload is best effort; the app works without it
+        let (sender, changes) = mpsc::channel();
+        let _watcher = repository
+            .watch(move |event| {
+                let _ = sender.send(event);
+            })
+            .ok();
+
+        let workspaces = Workspaces {
+            names: config.workspace_names(),
+            current: workspace,
+            selection,
+        };
+        match ui::index::run_app(
+            &mut service,
+            workspaces,
+            changes,
+            keymap.clone(),
+            theme,
+            columns.clone(),
+        ) {
+            Ok(AppExit::SwitchWorkspace(next_workspace, next_selection)) => {
+                data_dir = Some(config.workspace_data_dir(&next_workspace)?);
+                workspace = Some(next_workspace);
+                selection = next_selection;
+            }
+            Ok(AppExit::Quit) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Workspaces are opened only when they are searched, so that an unavailable one is skipped
+fn workspace_list(config: &Config) -> Vec<Workspace> {
+    config
+        .workspaces()
+        .into_iter()
+        .map(|(name, data_dir)| {
+            let data_dir = data_dir.to_string();
+            Workspace {
+                name: name.to_string(),
+                open: Arc::new(move || {
+                    Ok(Box::new(Repository::open(&data_dir)?) as Box<dyn IRepository>)
+                }),
+            }
+        })
+        .collect()
+}
+
+fn handle_workspace_action(action: WorkspaceAction, config: &mut Config) -> anyhow::Result<()> {
+    match action {
+        WorkspaceAction::List {} => {
+            for (name, data_dir) in config.workspaces() {
+                let mark = if config.default_workspace() == Some(name) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}\t{}", mark, name, data_dir);
+            }
+        }
+        WorkspaceAction::Add { name, data_dir } => {
+            config.add_workspace(&name, &data_dir)?;
+            config.save()?;
+            println!("Added: {}", name);
+        }
+        WorkspaceAction::Remove { name } => {
+            config.remove_workspace(&name)?;
+            config.save()?;
+            println!("Removed: {}", name);
+        }
+        WorkspaceAction::Default { name } => {
+            config.set_default_workspace(name.as_deref())?;
+            config.save()?;
+            match name {
+                Some(name) => println!("Default: {}", name),
+                None => println!("Default workspace is reset"),
+            }
+        }
     }
     Ok(())
 }
@@ -1,27 +1,101 @@
 use std::fs::{self, ReadDir};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Ok, Result};
+use anyhow::{anyhow, Context, Ok, Result};
 
+use crate::domain::metadata::Metadata;
+use crate::domain::searches::Searches;
 use crate::service::interface::IRepository;
 
 use self::data_dir::DataDir;
+use self::watcher::{ChangeEvent, DataDirWatcher};
 
 mod data_dir;
+pub mod watcher;
+
+const METADATA_FILE_NAME: &str = ".metadata.json";
+const SEARCHES_FILE_NAME: &str = ".searches.json";
+const TEMPLATE_DIR_NAME: &str = ".templates";
+/// Hidden, so that trashed files are not listed
+const TRASH_DIR_NAME: &str = ".trash";
+
+/// Trash directories of this process, to be removed on exits which skip destructors
+static SESSION_TRASH_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
 
 pub struct Repository {
     data_dir: DataDir,
 }
 
+/// Each process trashes files into its own directory, so that sessions trashing files of the same name
+/// do not overwrite each other. The time tells apart processes which happen to get the same id.
+fn session_id() -> &'static str {
+    static SESSION_ID: OnceLock<String> = OnceLock::new();
+    SESSION_ID.get_or_init(|| {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        format!("{}-{}", std::process::id(), started_at)
+    })
+}
+
+/// Remove what this process left in the trash. For exits by signals, which skip destructors.
+pub fn remove_session_trash() {
+    if let std::result::Result::Ok(dirs) = SESSION_TRASH_DIRS.lock() {
+        for dir in dirs.iter() {
+            let _ = fs::remove_dir_all(dir);
+            // Fails when other sessions have files in the trash
+            if let Some(trash_dir) = dir.parent() {
+                let _ = fs::remove_dir(trash_dir);
+            }
+        }
+    }
+}
+
 impl Repository {
     pub fn new(data_dir: Option<String>) -> Self {
         let data_dir = DataDir::setup(data_dir);
         Repository { data_dir }
     }
+
+    /// Open an existing data directory, such as another workspace to search
+    pub fn open(data_dir: &str) -> Result<Self> {
+        let data_dir = DataDir::open(data_dir)?;
+        Ok(Repository { data_dir })
+    }
+
+    fn trash_path(&self, file_name: &str) -> PathBuf {
+        self.data_dir
+            .join(TRASH_DIR_NAME)
+            .join(session_id())
+            .join(file_name)
+    }
+
+    /// Directories are left when other files are in them
+    fn remove_empty_trash_dirs(&self, file_path: &Path) {
+        let trash_path = self.data_dir.join(TRASH_DIR_NAME);
+        for dir in file_path.ancestors().skip(1) {
+            if !dir.starts_with(&trash_path) || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Call on_change when files in the data directory are changed until the watcher is dropped
+    pub fn watch<F>(&self, on_change: F) -> Result<DataDirWatcher>
+    where
+        F: Fn(ChangeEvent) + Send + 'static,
+    {
+        watcher::watch(self.data_dir.path(), on_change)
+    }
 }
 
 impl IRepository for Repository {
     fn edit(&self, file_name: &str) -> Result<()> {
         let file_path = &self.data_dir.join(file_name);
+        create_parent_dir(file_path)?;
         scrawl::edit(file_path).with_context(|| format!("Failed to edit {}", file_name))?;
         Ok(())
     }
@@ -33,11 +107,108 @@ impl IRepository for Repository {
         Ok(content)
     }
 
+    fn create(&self, file_name: &str, content: &str) -> Result<()> {
+        let file_path = &self.data_dir.join(file_name);
+        if file_path.exists() {
+            return Err(anyhow!("{} already exists", file_name));
+        }
+        create_parent_dir(file_path)?;
+        fs::write(file_path, content).with_context(|| format!("Failed to create {}", file_name))
+    }
+
+    fn overwrite(&self, file_name: &str, content: &str) -> Result<()> {
+        let file_path = &self.data_dir.join(file_name);
+        create_parent_dir(file_path)?;
+        fs::write(file_path, content).with_context(|| format!("Failed to write {}", file_name))
+    }
+
+    fn exists(&self, file_name: &str) -> bool {
+        self.data_dir.join(file_name).exists()
+    }
+
+    fn get_template_file_name_list(&self) -> Result<Vec<String>> {
+        let template_dir = self.data_dir.join(TEMPLATE_DIR_NAME);
+        if !template_dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut template_file_name_list = vec![];
+        for entry in fs::read_dir(template_dir).context("Failed to read templates")? {
+            let path = entry?.path();
+            if path.is_file() {
+                if let Some(file_name) = path.file_name() {
+                    template_file_name_list.push(file_name.to_string_lossy().to_string());
+                }
+            }
+        }
+        template_file_name_list.sort();
+        Ok(template_file_name_list)
+    }
+
+    fn get_template_content(&self, template_file_name: &str) -> Result<String> {
+        let file_path = self
+            .data_dir
+            .join(TEMPLATE_DIR_NAME)
+            .join(template_file_name);
+        fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to get template {}", template_file_name))
+    }
+
     fn delete(&self, file_name: &str) -> Result<()> {
         let file_path = &self.data_dir.join(file_name);
         fs::remove_file(file_path).with_context(|| format!("Failed to delete {}", file_name))
     }
 
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let from_path = &self.data_dir.join(from);
+        let to_path = &self.data_dir.join(to);
+        if to_path.exists() {
+            return Err(anyhow!("{} already exists", to));
+        }
+        create_parent_dir(to_path)?;
+        fs::rename(from_path, to_path).with_context(|| format!("Failed to move {} to {}", from, to))
+    }
+
+    fn trash(&self, file_name: &str) -> Result<()> {
+        let from_path = &self.data_dir.join(file_name);
+        let to_path = &self.trash_path(file_name);
+        if to_path.exists() {
+            return Err(anyhow!("{} is already in the trash", file_name));
+        }
+        let session_dir = self.data_dir.join(TRASH_DIR_NAME).join(session_id());
+        if let std::result::Result::Ok(mut dirs) = SESSION_TRASH_DIRS.lock() {
+            if !dirs.contains(&session_dir) {
+                dirs.push(session_dir);
+            }
+        }
+        create_parent_dir(to_path)?;
+        fs::rename(from_path, to_path)
+            .with_context(|| format!("Failed to move {} to trash", file_name))
+    }
+
+    fn restore(&self, file_name: &str) -> Result<()> {
+        let from_path = &self.trash_path(file_name);
+        let to_path = &self.data_dir.join(file_name);
+        if to_path.exists() {
+            return Err(anyhow!("{} already exists", file_name));
+        }
+        create_parent_dir(to_path)?;
+        fs::rename(from_path, to_path)
+            .with_context(|| format!("Failed to restore {}", file_name))?;
+        self.remove_empty_trash_dirs(from_path);
+        Ok(())
+    }
+
+    fn remove_from_trash(&self, file_name: &str) -> Result<()> {
+        let file_path = self.trash_path(file_name);
+        if !file_path.exists() {
+            return Ok(());
+        }
+        fs::remove_file(&file_path)
+            .with_context(|| format!("Failed to remove {} from trash", file_name))?;
+        self.remove_empty_trash_dirs(&file_path);
+        Ok(())
+    }
+
     fn get_path(&self, file_name: &str) -> Result<String> {
         let path = self.data_dir.join(file_name).to_string_lossy().to_string();
         Ok(path)
@@ -46,4 +217,45 @@ impl IRepository for Repository {
     fn read_dir(&self) -> ReadDir {
         self.data_dir.read_dir()
     }
+
+    fn load_metadata(&self) -> Result<Metadata> {
+        let file_path = &self.data_dir.join(METADATA_FILE_NAME);
+        if !file_path.exists() {
+            return Ok(Metadata::new());
+        }
+        let content = fs::read_to_string(file_path).context("Failed to read metadata")?;
+        let metadata = serde_json::from_str(&content).context("Failed to parse metadata")?;
+        Ok(metadata)
+    }
+
+    fn save_metadata(&self, metadata: &Metadata) -> Result<()> {
+        let file_path = &self.data_dir.join(METADATA_FILE_NAME);
+        let content = serde_json::to_string_pretty(metadata)?;
+        fs::write(file_path, content).context("Failed to save metadata")
+    }
+
+    fn load_searches(&self) -> Result<Searches> {
+        let file_path = &self.data_dir.join(SEARCHES_FILE_NAME);
+        if !file_path.exists() {
+            return Ok(Searches::new());
+        }
+        let content = fs::read_to_string(file_path).context("Failed to read searches")?;
+        let searches = serde_json::from_str(&content).context("Failed to parse searches")?;
+        Ok(searches)
+    }
+
+    fn save_searches(&self, searches: &Searches) -> Result<()> {
+        let file_path = &self.data_dir.join(SEARCHES_FILE_NAME);
+        let content = serde_json::to_string_pretty(searches)?;
+        fs::write(file_path, content).context("Failed to save searches")
+    }
+}
+
+/// Notebooks are created on demand
+fn create_parent_dir(file_path: &Path) -> Result<()> {
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    Ok(())
 }
@@ -4,6 +4,7 @@ use std::{
     process,
 };
 
+use anyhow::{anyhow, Context, Result};
 use directories::ProjectDirs;
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
@@ -36,6 +37,18 @@ impl DataDir {
         }
     }
 
+    /// Unlike setup, the directory is neither created nor checked to be writable
+    pub fn open(dir: &str) -> Result<Self> {
+        let expanded_dir = shellexpand::full(dir)
+            .with_context(|| format!("Can't understand data directory: {:?}", dir))?;
+        let path = Path::new(expanded_dir.as_ref()).to_owned();
+        if !path.is_dir() {
+            return Err(anyhow!("Directory {:?} does not exist", path));
+        }
+        fs::read_dir(&path).with_context(|| format!("Can't read directory {:?}", path))?;
+        Ok(DataDir { path })
+    }
+
     pub fn join(&self, file_name: &str) -> PathBuf {
         self.path.join(file_name)
     }
@@ -52,7 +65,7 @@ impl DataDir {
         });
     }
 
-    fn path(&self) -> &PathBuf {
+    pub fn path(&self) -> &PathBuf {
         &self.path
     }
 
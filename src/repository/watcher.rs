@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::METADATA_FILE_NAME;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Removed => "removed",
+        }
+    }
+}
+
+/// Files changed outside of this process, relative to the data directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub file_names: Vec<String>,
+}
+
+/// Stops watching when dropped
+pub struct DataDirWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+pub fn watch<F>(data_dir: &Path, on_change: F) -> Result<DataDirWatcher>
+where
+    F: Fn(ChangeEvent) + Send + 'static,
+{
+    let root = data_dir
+        .canonicalize()
+        .unwrap_or_else(|_| data_dir.to_path_buf());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Some(event) = event.ok().and_then(|event| to_change_event(&root, event)) {
+            on_change(event);
+        }
+    })
+    .context("Failed to start watching the data directory")?;
+    watcher
+        .watch(data_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", data_dir.display()))?;
+    Ok(DataDirWatcher { _watcher: watcher })
+}
+
+fn to_change_event(root: &Path, event: Event) -> Option<ChangeEvent> {
+    let kind = match event.kind {
+        EventKind::Create(_) => ChangeKind::Created,
+        EventKind::Remove(_) => ChangeKind::Removed,
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => ChangeKind::Modified,
+        EventKind::Access(_) => return None,
+    };
+    let file_names = event
+        .paths
+        .iter()
+        .filter_map(|path| relative_file_name(root, path))
+        .collect::<Vec<String>>();
+    if file_names.is_empty() {
+        return None;
+    }
+    Some(ChangeEvent { kind, file_names })
+}
+
+/// Hidden files are ignored except the metadata which holds pins and tags
+fn relative_file_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let file_name = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    if file_name.is_empty() {
+        return None;
+    }
+    let is_hidden = file_name
+        .split('/')
+        .any(|component| component.starts_with('.'));
+    if is_hidden && file_name != METADATA_FILE_NAME {
+        return None;
+    }
+    Some(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_file_name() {
+        let root = Path::new("/data");
+        assert_eq!(
+            relative_file_name(root, Path::new("/data/work/a.txt")),
+            Some("work/a.txt".to_string())
+        );
+        assert_eq!(
+            relative_file_name(root, Path::new("/data/.metadata.json")),
+            Some(".metadata.json".to_string())
+        );
+        assert_eq!(
+            relative_file_name(root, Path::new("/data/.templates/a.sql")),
+            None
+        );
+        assert_eq!(relative_file_name(root, Path::new("/data")), None);
+        assert_eq!(relative_file_name(root, Path::new("/other/a.txt")), None);
+    }
+}
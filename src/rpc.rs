@@ -0,0 +1,226 @@
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+
+use crate::repository::watcher::ChangeEvent;
+use crate::service::ServiceTrait;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Errors from the service such as a missing file
+pub const APPLICATION_ERROR: i64 = -32000;
+
+pub type SharedWriter = Arc<Mutex<dyn Write + Send>>;
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: &str) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        RpcError::new(APPLICATION_ERROR, &e.to_string())
+    }
+}
+
+/// Handle line-delimited JSON-RPC 2.0 requests until the reader is closed
+pub fn run(service: &dyn ServiceTrait, reader: impl BufRead, writer: &SharedWriter) -> Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(service, &line) {
+            send(writer, &response)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write one message. Shared with the watcher thread which sends notifications.
+pub fn send(writer: &SharedWriter, message: &Value) -> Result<()> {
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    writeln!(writer, "{}", message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn notification(event: &ChangeEvent) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "changed",
+        "params": { "kind": event.kind.as_str(), "file_names": event.file_names },
+    })
+}
+
+/// Returns None for notifications from the client, which have no id
+pub fn handle_line(service: &dyn ServiceTrait, line: &str) -> Option<Value> {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, &e.to_string()),
+            ))
+        }
+    };
+    let id = request.get("id").cloned();
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Invalid request"),
+            ))
+        }
+    };
+    let empty = Map::new();
+    let params = match request.get("params") {
+        None => &empty,
+        Some(Value::Object(params)) => params,
+        Some(_) => {
+            return id.map(|id| {
+                error_response(
+                    id,
+                    RpcError::new(INVALID_PARAMS, "params must be an object"),
+                )
+            })
+        }
+    };
+
+    // Files may be changed by others since the last request
+    let result = service
+        .reload()
+        .map_err(RpcError::from)
+        .and_then(|_| call(service, method, params));
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+fn call(
+    service: &dyn ServiceTrait,
+    method: &str,
+    params: &Map<String, Value>,
+) -> Result<Value, RpcError> {
+    match method {
+        "list" => {
+            let pinned = service.get_pinned_kakisute_list();
+            Ok(Value::Array(
+                service
+                    .get_kakisute_list()
+                    .into_iter()
+                    .map(|file_name| {
+                        json!({ "pinned": pinned.contains(&file_name), "file_name": file_name })
+                    })
+                    .collect(),
+            ))
+        }
+        "get" => {
+            let file_name = string_param(params, "file_name")?;
+            let content = service.get_content_by_index(index_of(service, &file_name)?)?;
+            Ok(json!({ "file_name": file_name, "content": content }))
+        }
+        "create" => {
+            let name = optional_string_param(params, "name")?;
+            let content = optional_string_param(params, "content")?.unwrap_or_default();
+            let file_name = service.create_kakisute_with_content(name.as_deref(), &content)?;
+            service.reload()?;
+            Ok(json!({ "file_name": file_name }))
+        }
+        "update" => {
+            let file_name = string_param(params, "file_name")?;
+            let content = string_param(params, "content")?;
+            let file_name =
+                service.update_content_by_index(index_of(service, &file_name)?, &content)?;
+            Ok(json!({ "file_name": file_name }))
+        }
+        "delete" => {
+            let file_name = string_param(params, "file_name")?;
+            let file_name = service.delete_by_index(index_of(service, &file_name)?)?;
+            service.reload()?;
+            Ok(json!({ "file_name": file_name }))
+        }
+        "move" => {
+            let file_name = string_param(params, "file_name")?;
+            let notebook = optional_string_param(params, "notebook")?.unwrap_or_default();
+            let file_name = service.move_by_index(index_of(service, &file_name)?, &notebook)?;
+            service.reload()?;
+            Ok(json!({ "file_name": file_name }))
+        }
+        "toggle_pin" => {
+            let file_name = string_param(params, "file_name")?;
+            let file_name = service.toggle_pin_by_index(index_of(service, &file_name)?)?;
+            service.reload()?;
+            let pinned = service.get_pinned_kakisute_list().contains(&file_name);
+            Ok(json!({ "file_name": file_name, "pinned": pinned }))
+        }
+        "search" => {
+            let query = string_param(params, "query")?;
+            Ok(json!(service.search(&query)?))
+        }
+        "notebooks" => Ok(json!(service.get_notebook_list()?)),
+        "switch_notebook" => {
+            let notebook = optional_string_param(params, "notebook")?;
+            service.switch_notebook(notebook.as_deref())?;
+            Ok(Value::Null)
+        }
+        "templates" => Ok(json!(service.get_template_list()?)),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            &format!("Method not found: {}", method),
+        )),
+    }
+}
+
+fn index_of(service: &dyn ServiceTrait, file_name: &str) -> Result<usize, RpcError> {
+    service
+        .get_kakisute_list()
+        .iter()
+        .position(|name| name == file_name)
+        .ok_or_else(|| RpcError::new(APPLICATION_ERROR, &format!("File not found: {}", file_name)))
+}
+
+fn string_param(params: &Map<String, Value>, key: &str) -> Result<String, RpcError> {
+    optional_string_param(params, key)?
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, &format!("{} is required", key)))
+}
+
+/// null is the same as missing
+fn optional_string_param(
+    params: &Map<String, Value>,
+    key: &str,
+) -> Result<Option<String>, RpcError> {
+    match params.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(RpcError::new(
+            INVALID_PARAMS,
+            &format!("{} must be a string", key),
+        )),
+    }
+}
@@ -0,0 +1,520 @@
+use std::io::Read;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+use crate::domain::notebook;
+use crate::service::query::Query;
+use crate::service::ServiceTrait;
+
+const WEB_UI: &str = include_str!("server/index.html");
+const API_PREFIX: &str = "/api/";
+const KAKISUTE_PATH: &str = "/api/kakisute";
+const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+pub struct ServerOptions {
+    /// Address to listen on. Requests must be addressed to it or to a loopback name.
+    pub bind: String,
+    /// When set, API requests must have `Authorization: Bearer <token>`
+    pub token: Option<String>,
+    /// Reject requests that change kakisute
+    pub read_only: bool,
+}
+
+/// HTTP request independent of the server implementation
+pub struct ApiRequest<'a> {
+    pub method: &'a str,
+    /// Path and query string
+    pub url: &'a str,
+    pub authorization: Option<&'a str>,
+    /// Sent by browsers with the site the request is made from
+    pub origin: Option<&'a str>,
+    pub host: Option<&'a str>,
+    pub body: &'a str,
+}
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl ApiResponse {
+    fn json(status: u16, value: Value) -> Self {
+        ApiResponse {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        ApiResponse::json(status, json!({ "error": message }))
+    }
+}
+
+/// Serve requests one by one until the process is stopped
+pub fn serve(service: &dyn ServiceTrait, options: &ServerOptions) -> Result<()> {
+    let bind = &options.bind;
+    let server = Server::http(bind).map_err(|e| anyhow!("Failed to listen on {}: {}", bind, e))?;
+    println!("Listening on http://{}", bind);
+    for mut request in server.incoming_requests() {
+        let body_length = request.body_length();
+        let response = match read_body(request.as_reader(), body_length, MAX_BODY_SIZE) {
+            Err(response) => response,
+            Ok(body) => {
+                let header = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|header| header.field.equiv(name))
+                        .map(|header| header.value.to_string())
+                };
+                let authorization = header("Authorization");
+                let origin = header("Origin");
+                let host = header("Host");
+                let method = request.method().to_string();
+                handle(
+                    service,
+                    options,
+                    &ApiRequest {
+                        method: &method,
+                        url: request.url(),
+                        authorization: authorization.as_deref(),
+                        origin: origin.as_deref(),
+                        host: host.as_deref(),
+                        body: &body,
+                    },
+                )
+            }
+        };
+        let content_type = Header::from_bytes("Content-Type", response.content_type)
+            .map_err(|_| anyhow!("Invalid header"))?;
+        let response = Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(content_type);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Failed to respond: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Bodies over the limit are rejected as a whole instead of being cut off
+fn read_body(
+    reader: impl Read,
+    content_length: Option<usize>,
+    limit: u64,
+) -> Result<String, ApiResponse> {
+    let too_large = || ApiResponse::error(413, &format!("Body must be {} bytes or less", limit));
+    if content_length.is_some_and(|length| length as u64 > limit) {
+        return Err(too_large());
+    }
+    let mut body = vec![];
+    if reader.take(limit + 1).read_to_end(&mut body).is_err() {
+        return Err(ApiResponse::error(400, "Failed to read body"));
+    }
+    if body.len() as u64 > limit {
+        return Err(too_large());
+    }
+    String::from_utf8(body).map_err(|_| ApiResponse::error(400, "Body must be UTF-8 text"))
+}
+
+pub fn handle(
+    service: &dyn ServiceTrait,
+    options: &ServerOptions,
+    request: &ApiRequest,
+) -> ApiResponse {
+    let (path, query) = request.url.split_once('?').unwrap_or((request.url, ""));
+
+    // A page of another site may resolve its own name to this address (DNS rebinding),
+    // in which case Origin and Host both carry that name
+    if !request
+        .host
+        .is_some_and(|host| is_allowed_host(host, &options.bind))
+    {
+        return ApiResponse::error(403, "Unknown host");
+    }
+
+    if !path.starts_with(API_PREFIX) {
+        return match (request.method, path) {
+            ("GET", "/") | ("GET", "/index.html") => ApiResponse {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: WEB_UI.to_string(),
+            },
+            _ => ApiResponse::error(404, "Not found"),
+        };
+    }
+
+    // Other sites open in the browser must not change kakisute, even without the token
+    if let Some(origin) = request.origin {
+        if !is_same_origin(origin, request.host) {
+            return ApiResponse::error(403, "Cross-origin request");
+        }
+    }
+    if let Some(token) = &options.token {
+        let expected = format!("Bearer {}", token);
+        let authorization = request.authorization.unwrap_or_default();
+        if !constant_time_eq(authorization.as_bytes(), expected.as_bytes()) {
+            return ApiResponse::error(401, "Unauthorized");
+        }
+    }
+    if options.read_only && !matches!(request.method, "GET" | "HEAD") {
+        return ApiResponse::error(403, "Server is read-only");
+    }
+
+    // Files may be changed by others since the last request
+    let result = service
+        .reload()
+        .and_then(|_| route(service, options, request.method, path, query, request.body));
+    match result {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(500, &e.to_string()),
+    }
+}
+
+fn route(
+    service: &dyn ServiceTrait,
+    options: &ServerOptions,
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &str,
+) -> Result<ApiResponse> {
+    if path == "/api/info" {
+        return Ok(ApiResponse::json(
+            200,
+            json!({ "read_only": options.read_only }),
+        ));
+    }
+    if path == "/api/search" {
+        let q = query_param(query, "q")?.unwrap_or_default();
+        return search(service, &q);
+    }
+    if path == KAKISUTE_PATH {
+        return match method {
+            "GET" => Ok(ApiResponse::json(200, list(service))),
+            "POST" => {
+                let name = query_param(query, "name")?.filter(|name| !name.is_empty());
+                if let Some(Err(e)) = name.as_deref().map(notebook::validate_file_name) {
+                    return Ok(ApiResponse::error(400, &e.to_string()));
+                }
+                let file_name = service.create_kakisute_with_content(name.as_deref(), body)?;
+                service.reload()?;
+                Ok(ApiResponse::json(201, json!({ "file_name": file_name })))
+            }
+            _ => Ok(ApiResponse::error(405, "Method not allowed")),
+        };
+    }
+
+    let file_name = match path
+        .strip_prefix(KAKISUTE_PATH)
+        .and_then(|rest| rest.strip_prefix('/'))
+    {
+        Some(file_name) => percent_decode(file_name, false)?,
+        None => return Ok(ApiResponse::error(404, "Not found")),
+    };
+    let index = match service
+        .get_kakisute_list()
+        .iter()
+        .position(|name| *name == file_name)
+    {
+        Some(index) => index,
+        None => return Ok(ApiResponse::error(404, "File not found")),
+    };
+    match method {
+        "GET" => {
+            let content = service.get_content_by_index(index)?;
+            Ok(ApiResponse::json(
+                200,
+                json!({ "file_name": file_name, "content": content }),
+            ))
+        }
+        "PUT" => {
+            service.update_content_by_index(index, body)?;
+            service.reload()?;
+            Ok(ApiResponse::json(200, json!({ "file_name": file_name })))
+        }
+        "DELETE" => {
+            service.delete_by_index(index)?;
+            service.reload()?;
+            Ok(ApiResponse::json(200, json!({ "file_name": file_name })))
+        }
+        _ => Ok(ApiResponse::error(405, "Method not allowed")),
+    }
+}
+
+/// Origin is like "http://127.0.0.1:8080", while Host is "127.0.0.1:8080"
+fn is_same_origin(origin: &str, host: Option<&str>) -> bool {
+    match (origin.split_once("://"), host) {
+        (Some((_, authority)), Some(host)) => authority.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+/// Host is like "localhost:8080" or "[::1]:8080"
+fn is_allowed_host(host: &str, bind: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    host.eq_ignore_ascii_case(bind)
+        || LOOPBACK_HOSTS
+            .iter()
+            .any(|loopback| name.eq_ignore_ascii_case(loopback))
+}
+
+/// Takes the same time wherever the bytes differ, so that the token can not be guessed byte by byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn list(service: &dyn ServiceTrait) -> Value {
+    let pinned = service.get_pinned_kakisute_list();
+    Value::Array(
+        service
+            .get_kakisute_list()
+            .into_iter()
+            .map(|file_name| json!({ "pinned": pinned.contains(&file_name), "file_name": file_name }))
+            .collect(),
+    )
+}
+
+fn search(service: &dyn ServiceTrait, query: &str) -> Result<ApiResponse> {
+    // Invalid query is the client's fault
+    if let Err(e) = Query::parse(query) {
+        return Ok(ApiResponse::error(400, &e.to_string()));
+    }
+    let results = service
+        .search(query)?
+        .into_iter()
+        .map(|file_name| json!({ "file_name": file_name }))
+        .collect();
+    Ok(ApiResponse::json(200, Value::Array(results)))
+}
+
+fn query_param(query: &str, key: &str) -> Result<Option<String>> {
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if name == key {
+            return Ok(Some(percent_decode(value, true)?));
+        }
+    }
+    Ok(None)
+}
+
+/// `plus_as_space` is for query strings encoded as application/x-www-form-urlencoded
+fn percent_decode(text: &str, plus_as_space: bool) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = text
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| anyhow!("Invalid percent encoding"))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use std::fs;
+
+    use crate::repository::Repository;
+    use crate::service::interface::IRepository;
+    use crate::service::kakisute_list::KakisuteList;
+    use crate::service::Service;
+    use crate::test_dir::TestDir;
+
+    fn setup(name: &str) -> TestDir {
+        let data_dir = TestDir::new(&format!("server_{}", name));
+        fs::create_dir_all(data_dir.join("work")).unwrap();
+        fs::write(data_dir.join("2022_01_10_16_30_15.sql"), "select 1").unwrap();
+        fs::write(data_dir.join("work/2022_01_11_09_00_00.txt"), "memo").unwrap();
+        data_dir
+    }
+
+    fn request<'a>(method: &'a str, url: &'a str, body: &'a str) -> ApiRequest<'a> {
+        ApiRequest { method, url, authorization: None, origin: None, host: Some("127.0.0.1:8080"), body }
+    }
+
+    describe "percent_decode" {
+        it "decodes escaped bytes" {
+            assert_eq!(percent_decode("work%2Fa%20b", false).unwrap(), "work/a b");
+            assert_eq!(percent_decode("a+b", true).unwrap(), "a b");
+            assert_eq!(percent_decode("a+b", false).unwrap(), "a+b");
+            assert!(percent_decode("%zz", false).is_err());
+        }
+    }
+
+    describe "read_body" {
+        it "rejects bodies over the limit instead of cutting them off" {
+            assert_eq!(read_body("abcd".as_bytes(), None, 4).unwrap(), "abcd");
+            assert_eq!(read_body("abcde".as_bytes(), None, 4).unwrap_err().status, 413);
+            assert_eq!(read_body("".as_bytes(), Some(5), 4).unwrap_err().status, 413);
+            assert_eq!(read_body([0xff].as_slice(), None, 4).unwrap_err().status, 400);
+        }
+    }
+
+    describe "handle" {
+        it "serves CRUD and search" {
+            let data_dir = setup("crud");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "127.0.0.1:8080".to_string(), token: None, read_only: false };
+
+            let response = handle(&service, &options, &request("GET", "/api/kakisute", ""));
+            assert_eq!(response.status, 200);
+            assert!(response.body.contains("work/2022_01_11_09_00_00.txt"));
+
+            let response = handle(&service, &options, &request("GET", "/api/kakisute/work%2F2022_01_11_09_00_00.txt", ""));
+            assert_eq!(response.status, 200);
+            assert!(response.body.contains("\"content\":\"memo\""));
+
+            let response = handle(&service, &options, &request("PUT", "/api/kakisute/2022_01_10_16_30_15.sql", "select 2"));
+            assert_eq!(response.status, 200);
+            assert_eq!(fs::read_to_string(data_dir.join("2022_01_10_16_30_15.sql")).unwrap(), "select 2");
+
+            let response = handle(&service, &options, &request("POST", "/api/kakisute?name=new.md", "# new"));
+            assert_eq!(response.status, 201);
+            assert_eq!(service.get_kakisute_list().len(), 3);
+
+            let response = handle(&service, &options, &request("GET", "/api/search?q=select", ""));
+            assert!(response.body.contains("2022_01_10_16_30_15.sql"));
+            assert!(!response.body.contains("work/"));
+
+            let response = handle(&service, &options, &request("DELETE", "/api/kakisute/2022_01_10_16_30_15.sql", ""));
+            assert_eq!(response.status, 200);
+            assert!(!data_dir.join("2022_01_10_16_30_15.sql").exists());
+
+            let response = handle(&service, &options, &request("GET", "/api/kakisute/none.txt", ""));
+            assert_eq!(response.status, 404);
+        }
+
+        it "sees files changed outside the server" {
+            let data_dir = setup("reload");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "127.0.0.1:8080".to_string(), token: None, read_only: false };
+
+            fs::write(data_dir.join("2022_01_12_10_00_00.txt"), "outside").unwrap();
+            let response = handle(&service, &options, &request("GET", "/api/kakisute", ""));
+            assert!(response.body.contains("2022_01_12_10_00_00.txt"));
+            let response = handle(&service, &options, &request("GET", "/api/kakisute/2022_01_12_10_00_00.txt", ""));
+            assert_eq!(response.status, 200);
+
+            fs::remove_file(data_dir.join("2022_01_12_10_00_00.txt")).unwrap();
+            let response = handle(&service, &options, &request("GET", "/api/kakisute/2022_01_12_10_00_00.txt", ""));
+            assert_eq!(response.status, 404);
+        }
+
+        it "rejects file names escaping the data directory" {
+            let data_dir = setup("escape");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "127.0.0.1:8080".to_string(), token: None, read_only: false };
+
+            let response = handle(&service, &options, &request("POST", "/api/kakisute?name=..%2F..%2Fx", ""));
+            assert_eq!(response.status, 400);
+            let response = handle(&service, &options, &request("POST", "/api/kakisute?name=.hidden", ""));
+            assert_eq!(response.status, 400);
+        }
+
+        it "rejects requests from other sites" {
+            let data_dir = setup("origin");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "127.0.0.1:8080".to_string(), token: None, read_only: false };
+
+            let from = |origin| ApiRequest {
+                method: "DELETE",
+                url: "/api/kakisute/2022_01_10_16_30_15.sql",
+                authorization: None,
+                origin: Some(origin),
+                host: Some("127.0.0.1:8080"),
+                body: "",
+            };
+            assert_eq!(handle(&service, &options, &from("https://example.com")).status, 403);
+            assert_eq!(handle(&service, &options, &from("null")).status, 403);
+            assert!(data_dir.join("2022_01_10_16_30_15.sql").exists());
+            assert_eq!(handle(&service, &options, &from("http://127.0.0.1:8080")).status, 200);
+        }
+
+        it "rejects hosts other than loopback names and the bound address" {
+            let data_dir = setup("rebinding");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "192.168.0.2:8080".to_string(), token: None, read_only: false };
+
+            let rebound = |method, url, origin| ApiRequest {
+                method,
+                url,
+                authorization: None,
+                origin,
+                host: Some("evil.example:8080"),
+                body: "x",
+            };
+            let origin = Some("http://evil.example:8080");
+            assert_eq!(handle(&service, &options, &rebound("GET", "/api/kakisute", None)).status, 403);
+            assert_eq!(handle(&service, &options, &rebound("GET", "/api/kakisute/2022_01_10_16_30_15.sql", origin)).status, 403);
+            assert_eq!(handle(&service, &options, &rebound("POST", "/api/kakisute?name=a.txt", origin)).status, 403);
+            assert_eq!(handle(&service, &options, &rebound("DELETE", "/api/kakisute/2022_01_10_16_30_15.sql", origin)).status, 403);
+            assert!(data_dir.join("2022_01_10_16_30_15.sql").exists());
+            assert_eq!(service.get_kakisute_list().len(), 2);
+
+            let to = |host| ApiRequest { host, ..request("GET", "/api/kakisute", "") };
+            assert_eq!(handle(&service, &options, &to(None)).status, 403);
+            assert_eq!(handle(&service, &options, &to(Some("192.168.0.2:8080"))).status, 200);
+            assert_eq!(handle(&service, &options, &to(Some("LOCALHOST"))).status, 200);
+            assert_eq!(handle(&service, &options, &to(Some("[::1]:8080"))).status, 200);
+            assert_eq!(handle(&service, &options, &to(Some("127.0.0.1.evil.example"))).status, 403);
+        }
+
+        it "requires the token and honors read-only mode" {
+            let data_dir = setup("auth");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let options = ServerOptions { bind: "127.0.0.1:8080".to_string(), token: Some("secret".to_string()), read_only: true };
+
+            assert_eq!(handle(&service, &options, &request("GET", "/", "")).status, 200);
+            assert_eq!(handle(&service, &options, &request("GET", "/api/kakisute", "")).status, 401);
+            let wrong = ApiRequest { authorization: Some("Bearer secreT"), ..request("GET", "/api/kakisute", "") };
+            assert_eq!(handle(&service, &options, &wrong).status, 401);
+
+            let authorized = |method, url| ApiRequest { method, url, authorization: Some("Bearer secret"), origin: None, host: Some("localhost:8080"), body: "" };
+            assert_eq!(handle(&service, &options, &authorized("GET", "/api/kakisute")).status, 200);
+            assert_eq!(handle(&service, &options, &authorized("DELETE", "/api/kakisute/2022_01_10_16_30_15.sql")).status, 403);
+            assert!(data_dir.join("2022_01_10_16_30_15.sql").exists());
+        }
+    }
+}
@@ -1,39 +1,261 @@
 use chrono::DateTime;
 use chrono::Local;
-use grep::cli;
-use grep::printer::{ColorSpecs, StandardBuilder};
+use chrono::NaiveDate;
+use grep::cli::{self, StandardStream};
+use grep::matcher::Matcher;
+use grep::printer::{ColorSpecs, Standard, StandardBuilder};
 use grep::regex::RegexMatcher;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
 use std::process;
+use std::sync::Arc;
 
-use anyhow::{anyhow, Ok, Result};
+use anyhow::{anyhow, Context, Ok, Result};
 use grep::searcher::Searcher;
 use termcolor::ColorChoice;
 use walkdir::WalkDir;
 
-use crate::datetime_helper::datetime_to_string;
+use crate::datetime_helper::{datetime_to_string, string_to_datetime};
 use crate::domain::kakisute::Kakisute;
+use crate::domain::notebook;
+use crate::domain::template::{Template, TemplateContext};
 
+use self::archive::{ArchiveContent, ArchiveEntry, ConflictPolicy, ExportReport, ImportReport};
 use self::interface::IRepository;
 use self::kakisute_list::KakisuteList;
+use self::query::{Query, Target};
 use self::search_query::SingleQuery;
+use self::site::SiteEntry;
 
 pub struct Service<'a> {
     kakisute_list: &'a KakisuteList,
     repository: &'a dyn IRepository,
+    /// None shows every notebook, Some(None) shows only the top level of the data directory
+    notebook: RefCell<Option<Option<String>>>,
+    /// Configured workspaces searched by the cross-workspace search
+    workspaces: Vec<Workspace>,
 }
 
+/// Workspace searched by the cross-workspace search
+#[derive(Clone)]
+pub struct Workspace {
+    pub name: String,
+    /// Opens the data directory. Called only when the workspace is searched, possibly on a worker thread.
+    pub open: Arc<dyn Fn() -> Result<Box<dyn IRepository>> + Send + Sync>,
+}
+
+/// Kakisute of a workspace, as matched by the cross-workspace search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceSearchResult {
+    pub workspace: String,
+    pub file_name: String,
+    pub content: String,
+    pub tags: Vec<String>,
+}
+
+impl Workspace {
+    /// Every kakisute in the workspace with its content and tags
+    pub fn load(&self) -> Result<Vec<WorkspaceSearchResult>> {
+        let repository = (self.open)()?;
+        let metadata = repository.load_metadata()?;
+        let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &metadata);
+        Ok(kakisute_list
+            .get_kakisute_file_name_list()
+            .into_iter()
+            .map(|file_name| WorkspaceSearchResult {
+                workspace: self.name.clone(),
+                content: repository.get_content(&file_name).unwrap_or_default(),
+                tags: metadata.tags(&file_name).to_vec(),
+                file_name,
+            })
+            .collect())
+    }
+}
+
+pub mod archive;
+pub mod importer;
 pub mod interface;
 pub mod kakisute_list;
+pub mod query;
 pub mod search_query;
+pub mod site;
 
 impl<'a> Service<'a> {
     pub fn new(repository: &'a dyn IRepository, kakisute_list: &'a KakisuteList) -> Self {
         Service {
             kakisute_list,
             repository,
+            notebook: RefCell::new(None),
+            workspaces: vec![],
         }
     }
 
+    pub fn set_workspaces(&mut self, workspaces: Vec<Workspace>) {
+        self.workspaces = workspaces;
+    }
+
+    pub fn move_by_single_query(&self, query: SingleQuery, notebook: &str) -> Result<String> {
+        let index = self.get_index_by_single_query(query);
+        self.move_by_index(index, notebook)
+    }
+
+    /// Add and remove tags, then returns the file name and its tags
+    pub fn tag_by_single_query(
+        &self,
+        query: SingleQuery,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<(String, Vec<String>)> {
+        let index = self.get_index_by_single_query(query);
+        self.tag_by_index(index, add, remove)
+    }
+
+    /// Export kakisute in the list into tar.gz.
+    /// Files which can not be read as text are skipped with the reason instead of failing the export.
+    pub fn export_archive(
+        &self,
+        writer: impl Write,
+        since: Option<NaiveDate>,
+        tag: Option<&str>,
+    ) -> Result<ExportReport> {
+        let metadata = self.repository.load_metadata()?;
+        let mut entries = vec![];
+        let mut skipped = vec![];
+        for kakisute in self.kakisute_list.get_kakisute_file_list() {
+            let file_name = kakisute.path_name();
+            let created_at = kakisute.created_at();
+            if let (Some(since), Some(created_at)) = (since, created_at) {
+                if created_at.date().naive_local() < since {
+                    continue;
+                }
+            }
+            if let Some(tag) = tag {
+                if !metadata.has_tag(&file_name, tag) {
+                    continue;
+                }
+            }
+            let content = match self.repository.get_content(&file_name) {
+                Result::Ok(content) => content,
+                Err(err) => {
+                    skipped.push((file_name, err.root_cause().to_string()));
+                    continue;
+                }
+            };
+            entries.push(ArchiveEntry {
+                file_name,
+                content,
+                created_at,
+            });
+        }
+        let file_names = entries
+            .iter()
+            .map(|entry| entry.file_name.clone())
+            .collect::<Vec<String>>();
+        archive::write_archive(writer, &entries, &metadata.subset(&file_names))?;
+        Ok(ExportReport {
+            exported: entries.len(),
+            skipped,
+        })
+    }
+
+    /// Collect kakisute in the list to publish as a static site
+    pub fn get_site_entries(&self) -> Result<Vec<SiteEntry>> {
+        let metadata = self.repository.load_metadata()?;
+        let mut entries = vec![];
+        for kakisute in self.kakisute_list.get_kakisute_file_list() {
+            let file_name = kakisute.path_name();
+            entries.push(SiteEntry {
+                content: self.repository.get_content(&file_name)?,
+                created_at: kakisute.created_at(),
+                tags: metadata.tags(&file_name).to_vec(),
+                file_name,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Import kakisute read by `importer` into the current notebook
+    pub fn import(
+        &self,
+        archive_content: ArchiveContent,
+        policy: ConflictPolicy,
+    ) -> Result<ImportReport> {
+        let archived_metadata = archive_content.metadata;
+        let mut report = ImportReport {
+            skipped: archive_content.skipped,
+            ..Default::default()
+        };
+        let mut metadata = self.repository.load_metadata()?;
+
+        for entry in archive_content.entries {
+            let validated = Service::validate_imported_file_name(&entry.file_name);
+            if let Err(err) = &validated {
+                report.skipped.push((entry.file_name, err.to_string()));
+                continue;
+            }
+            let file_name = self.in_current_notebook(&validated?);
+
+            let file_name = if self.repository.exists(&file_name) {
+                match policy {
+                    ConflictPolicy::Skip => {
+                        report
+                            .skipped
+                            .push((entry.file_name, "already exists".to_string()));
+                        continue;
+                    }
+                    ConflictPolicy::Overwrite => {
+                        self.repository.overwrite(&file_name, &entry.content)?;
+                        metadata.remove(&file_name);
+                        report.overwritten.push(file_name.clone());
+                        file_name
+                    }
+                    ConflictPolicy::KeepBoth => {
+                        let new_file_name = archive::keep_both_name(&file_name, |name| {
+                            self.repository.exists(name)
+                        });
+                        self.repository.create(&new_file_name, &entry.content)?;
+                        report
+                            .renamed
+                            .push((entry.file_name.clone(), new_file_name.clone()));
+                        new_file_name
+                    }
+                }
+            } else {
+                self.repository.create(&file_name, &entry.content)?;
+                report.imported.push(file_name.clone());
+                file_name
+            };
+            metadata.copy_from(&archived_metadata, &entry.file_name, &file_name);
+        }
+
+        self.repository.save_metadata(&metadata)?;
+        Ok(report)
+    }
+
+    /// File name must start with the date and must not escape from the data directory
+    fn validate_imported_file_name(file_name: &str) -> Result<String> {
+        let (notebook, base_name) = notebook::split(file_name);
+        let notebook = match notebook {
+            Some(notebook) => notebook::normalize(notebook)?,
+            None => None,
+        };
+        if string_to_datetime(base_name).single().is_none() {
+            return Err(anyhow!("file name does not start with date"));
+        }
+        Ok(notebook::join(notebook.as_deref(), base_name))
+    }
+
+    /// Path relative to the data directory for a new kakisute in the current notebook
+    fn in_current_notebook(&self, file_name: &str) -> String {
+        notebook::join(
+            self.notebook.borrow().clone().flatten().as_deref(),
+            file_name,
+        )
+    }
+
     pub fn delete_by_single_query(&self, query: SingleQuery) -> Result<String> {
         let index = self.get_index_by_single_query(query);
         self.delete_by_index(index)
@@ -54,6 +276,25 @@ impl<'a> Service<'a> {
         self.inspect_by_index(index)
     }
 
+    pub fn pin_by_single_query(&self, query: SingleQuery) -> Result<String> {
+        let index = self.get_index_by_single_query(query);
+        self.set_pinned_by_index(index, true)
+    }
+
+    pub fn unpin_by_single_query(&self, query: SingleQuery) -> Result<String> {
+        let index = self.get_index_by_single_query(query);
+        self.set_pinned_by_index(index, false)
+    }
+
+    fn set_pinned_by_index(&self, index: usize, pinned: bool) -> Result<String> {
+        if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
+            self.set_pinned(&file_name, pinned)?;
+            Ok(file_name)
+        } else {
+            Err(anyhow!("File not found"))
+        }
+    }
+
     fn inspect_by_index(&self, index: usize) -> Result<String> {
         if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
             let path = self.repository.get_path(&file_name)?;
@@ -81,6 +322,19 @@ impl<'a> Service<'a> {
         }
     }
 
+    fn get_template(&self, template_name: &str) -> Result<Template> {
+        let template_file_name = self
+            .repository
+            .get_template_file_name_list()?
+            .into_iter()
+            .find(|file_name| {
+                file_name == template_name || Template::name_of(file_name) == template_name
+            })
+            .ok_or_else(|| anyhow!("Template not found: {}", template_name))?;
+        let content = self.repository.get_template_content(&template_file_name)?;
+        Ok(Template::new(&template_file_name, content))
+    }
+
     fn generate_file_name(date: DateTime<Local>, file_name: Option<&str>) -> String {
         let prefix = datetime_to_string(date);
         if let Some(file_name) = file_name {
@@ -90,34 +344,159 @@ impl<'a> Service<'a> {
         }
     }
     pub fn search_cli(&self, word: &str) -> Result<()> {
-        let matcher = RegexMatcher::new(word)?;
+        let query = Query::parse(word)?;
         let mut searcher = Searcher::new();
-        let mut printer = StandardBuilder::new()
+        let mut printer = Service::build_printer();
+
+        for (file_name, content) in Service::find(self.repository, self.kakisute_list, &query)? {
+            let path = self.repository.get_path(&file_name)?;
+            Service::print_match(&query, &content, &mut searcher, &mut printer, &path, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Save the query to be searched by name later, such as from the TUI
+    pub fn save_search(&self, name: &str, query: &str) -> Result<()> {
+        if name.is_empty() {
+            return Err(anyhow!("Name of the search is empty"));
+        }
+        Query::parse(query)?;
+        let mut searches = self.repository.load_searches()?;
+        searches.save(name, query);
+        self.repository.save_searches(&searches)
+    }
+
+    pub fn forget_search(&self, name: &str) -> Result<()> {
+        let mut searches = self.repository.load_searches()?;
+        if !searches.forget(name) {
+            return Err(anyhow!("No search is saved as {}", name));
+        }
+        self.repository.save_searches(&searches)
+    }
+
+    /// Search every configured workspace. Results are prefixed with the workspace name.
+    pub fn search_cli_all_workspaces(&self, word: &str) -> Result<()> {
+        if self.workspaces.is_empty() {
+            return Err(anyhow!("No workspace is configured"));
+        }
+        let query = Query::parse(word)?;
+        let mut searcher = Searcher::new();
+        let mut printer = Service::build_printer();
+
+        let mut skipped = vec![];
+        let repositories = self.open_workspaces(&mut skipped);
+        for skipped in skipped {
+            eprintln!("Warning: Skipped workspace {}", skipped);
+        }
+        for (workspace, repository) in repositories {
+            let kakisute_list =
+                KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata()?);
+            for (file_name, content) in Service::find(&*repository, &kakisute_list, &query)? {
+                let path = repository.get_path(&file_name)?;
+                let display_path = format!("{}:{}", workspace, file_name);
+                Service::print_match(
+                    &query,
+                    &content,
+                    &mut searcher,
+                    &mut printer,
+                    &path,
+                    Some(&display_path),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Workspaces which can not be opened are added to skipped with the reason
+    fn open_workspaces(&self, skipped: &mut Vec<String>) -> Vec<(&str, Box<dyn IRepository>)> {
+        let mut repositories = vec![];
+        for workspace in &self.workspaces {
+            match (workspace.open)() {
+                Result::Ok(repository) => repositories.push((workspace.name.as_str(), repository)),
+                Err(err) => skipped.push(format!("{}: {:#}", workspace.name, err)),
+            }
+        }
+        repositories
+    }
+
+    /// File names and contents of kakisute in the list matching the query
+    fn find(
+        repository: &dyn IRepository,
+        kakisute_list: &KakisuteList,
+        query: &Query,
+    ) -> Result<Vec<(String, String)>> {
+        let metadata = repository.load_metadata()?;
+        let mut results = vec![];
+        for kakisute in kakisute_list.get_kakisute_file_list() {
+            let file_name = kakisute.path_name();
+            let content = repository.get_content(&file_name).unwrap_or_default();
+            let target = Target {
+                name: &file_name,
+                content: Some(&content),
+                tags: metadata.tags(&file_name),
+                created_at: kakisute.created_at(),
+            };
+            if query.matches(&target) {
+                results.push((file_name, content));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Prints the lines matching the text in the query, or only the path when it matched by other fields
+    fn print_match(
+        query: &Query,
+        content: &str,
+        searcher: &mut Searcher,
+        printer: &mut Standard<StandardStream>,
+        path: &str,
+        display_path: Option<&str>,
+    ) -> Result<()> {
+        if let Some(regex) = query.highlight_regex() {
+            let matcher = RegexMatcher::new(&regex)?;
+            if matcher.is_match(content.as_bytes())? {
+                return Service::search_path(&matcher, searcher, printer, path, display_path);
+            }
+        }
+        writeln!(printer.get_mut(), "{}", display_path.unwrap_or(path))?;
+        Ok(())
+    }
+
+    fn build_printer() -> Standard<StandardStream> {
+        StandardBuilder::new()
             .color_specs(ColorSpecs::default_with_color())
             .build(cli::stdout(if cli::is_tty_stdout() {
                 ColorChoice::Auto
             } else {
                 ColorChoice::Never
-            }));
+            }))
+    }
 
-        for file_name in self.kakisute_list.get_kakisute_file_name_list() {
-            let path = self.repository.get_path(&file_name)?;
-            for result in WalkDir::new(path) {
-                let dent = result?;
-                if !dent.file_type().is_file() {
-                    continue;
-                }
-                let result = searcher.search_path(
-                    &matcher,
-                    dent.path(),
-                    printer.sink_with_path(&matcher, dent.path()),
-                );
-                if let Err(err) = result {
-                    eprintln!("{}: {}", dent.path().display(), err);
-                }
+    /// display_path replaces the path printed with results
+    fn search_path(
+        matcher: &RegexMatcher,
+        searcher: &mut Searcher,
+        printer: &mut Standard<StandardStream>,
+        path: &str,
+        display_path: Option<&str>,
+    ) -> Result<()> {
+        for result in WalkDir::new(path) {
+            let dent = result?;
+            if !dent.file_type().is_file() {
+                continue;
+            }
+            let display_path = display_path.map(Path::new).unwrap_or_else(|| dent.path());
+            let result = searcher.search_path(
+                matcher,
+                dent.path(),
+                printer.sink_with_path(matcher, display_path),
+            );
+            if let Err(err) = result {
+                eprintln!("{}: {}", dent.path().display(), err);
             }
         }
-
         Ok(())
     }
 }
@@ -125,11 +504,60 @@ impl<'a> Service<'a> {
 impl ServiceTrait for Service<'_> {
     fn create_kakisute(&self, file_name: Option<&str>) -> Result<String> {
         let created_at = Local::now();
-        let file_name = Service::generate_file_name(created_at, file_name);
+        let file_name =
+            self.in_current_notebook(&Service::generate_file_name(created_at, file_name));
+        self.repository.edit(&file_name)?;
+        Ok(file_name)
+    }
+
+    /// When file_name is not given, template name and its extension are used as file name
+    fn create_kakisute_from_template(
+        &self,
+        file_name: Option<&str>,
+        template_name: &str,
+    ) -> Result<String> {
+        let template = self.get_template(template_name)?;
+        let created_at = Local::now();
+        let content = template.render(&TemplateContext {
+            created_at,
+            name: file_name,
+        });
+        let default_file_name = template
+            .extension()
+            .map(|extension| template.name() + "." + &extension);
+        let file_name = self.in_current_notebook(&Service::generate_file_name(
+            created_at,
+            file_name.or(default_file_name.as_deref()),
+        ));
+        self.repository.create(&file_name, &content)?;
         self.repository.edit(&file_name)?;
         Ok(file_name)
     }
 
+    /// Create without opening the editor. file_name must be a plain file name.
+    fn create_kakisute_with_content(
+        &self,
+        file_name: Option<&str>,
+        content: &str,
+    ) -> Result<String> {
+        if let Some(file_name) = file_name {
+            notebook::validate_file_name(file_name)?;
+        }
+        let file_name =
+            self.in_current_notebook(&Service::generate_file_name(Local::now(), file_name));
+        self.repository.create(&file_name, content)?;
+        Ok(file_name)
+    }
+
+    fn get_template_list(&self) -> Result<Vec<String>> {
+        Ok(self
+            .repository
+            .get_template_file_name_list()?
+            .iter()
+            .map(|file_name| Template::name_of(file_name))
+            .collect())
+    }
+
     fn edit_by_index(&self, index: usize) -> Result<String> {
         if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
             self.repository.edit(&file_name)?;
@@ -139,35 +567,301 @@ impl ServiceTrait for Service<'_> {
         }
     }
 
+    fn update_content_by_index(&self, index: usize, content: &str) -> Result<String> {
+        if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
+            self.repository.overwrite(&file_name, content)?;
+            Ok(file_name)
+        } else {
+            Err(anyhow!("File not found"))
+        }
+    }
+
     fn delete_by_index(&self, index: usize) -> Result<String> {
         if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
             self.repository.delete(&file_name)?;
+            let mut metadata = self.repository.load_metadata()?;
+            if metadata.remove(&file_name) {
+                self.repository.save_metadata(&metadata)?;
+            }
             Ok(file_name)
         } else {
             Err(anyhow!("File not found"))
         }
     }
 
+    fn get_path_by_index(&self, index: usize) -> Result<String> {
+        match self.kakisute_list.get_file_name_by_index(index) {
+            Some(file_name) => self.repository.get_path(&file_name),
+            None => Err(anyhow!("File not found")),
+        }
+    }
+
     fn get_content_by_index(&self, index: usize) -> Result<String> {
         let kakisute = self.get_kakisute(index)?;
         Ok(kakisute.content())
     }
-    fn reload(&self) {
-        self.kakisute_list.reload(self.repository.read_dir());
+    /// Empty notebook name means the top level of the data directory
+    fn move_by_index(&self, index: usize, notebook: &str) -> Result<String> {
+        if let Some(path_name) = self.kakisute_list.get_file_name_by_index(index) {
+            let notebook = notebook::normalize(notebook)?;
+            let (_, file_name) = notebook::split(&path_name);
+            let new_path_name = notebook::join(notebook.as_deref(), file_name);
+            if new_path_name == path_name {
+                return Ok(new_path_name);
+            }
+            self.rename_file(&path_name, &new_path_name)?;
+            Ok(new_path_name)
+        } else {
+            Err(anyhow!("File not found"))
+        }
+    }
+
+    /// The datetime prefix and the notebook are kept. Empty name falls back to the default name.
+    fn rename_by_index(&self, index: usize, name: &str) -> Result<String> {
+        let path_name = self
+            .kakisute_list
+            .get_file_name_by_index(index)
+            .ok_or_else(|| anyhow!("File not found"))?;
+        notebook::validate_file_name(name)?;
+        let (notebook, file_name) = notebook::split(&path_name);
+        let created_at = string_to_datetime(file_name)
+            .single()
+            .ok_or_else(|| anyhow!("File not found"))?;
+        let new_file_name =
+            Service::generate_file_name(created_at, Some(name).filter(|name| !name.is_empty()));
+        let new_path_name = notebook::join(notebook, &new_file_name);
+        if new_path_name == path_name {
+            return Ok(new_path_name);
+        }
+        if self.repository.exists(&new_path_name) {
+            return Err(anyhow!("Already exists: {}", new_path_name));
+        }
+        self.rename_file(&path_name, &new_path_name)?;
+        Ok(new_path_name)
+    }
+
+    fn tag_by_index(
+        &self,
+        index: usize,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<(String, Vec<String>)> {
+        let file_name = self
+            .kakisute_list
+            .get_file_name_by_index(index)
+            .ok_or_else(|| anyhow!("File not found"))?;
+        let mut metadata = self.repository.load_metadata()?;
+        let mut changed = false;
+        for tag in add {
+            changed |= metadata.add_tag(&file_name, tag.trim());
+        }
+        for tag in remove {
+            changed |= metadata.remove_tag(&file_name, tag.trim());
+        }
+        if changed {
+            self.repository.save_metadata(&metadata)?;
+        }
+        let tags = metadata.tags(&file_name).to_vec();
+        Ok((file_name, tags))
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<()> {
+        self.repository.rename(from, to)?;
+        let mut metadata = self.repository.load_metadata()?;
+        if metadata.rename(from, to) {
+            self.repository.save_metadata(&metadata)?;
+        }
+        Ok(())
+    }
+
+    fn set_tags(&self, file_name: &str, tags: &[String]) -> Result<()> {
+        let mut metadata = self.repository.load_metadata()?;
+        let mut changed = false;
+        for tag in metadata.tags(file_name).to_vec() {
+            if !tags.contains(&tag) {
+                changed |= metadata.remove_tag(file_name, &tag);
+            }
+        }
+        for tag in tags {
+            changed |= metadata.add_tag(file_name, tag);
+        }
+        if changed {
+            self.repository.save_metadata(&metadata)?;
+        }
+        Ok(())
+    }
+
+    fn set_pinned(&self, file_name: &str, pinned: bool) -> Result<()> {
+        let mut metadata = self.repository.load_metadata()?;
+        let changed = if pinned {
+            metadata.pin(file_name)
+        } else {
+            metadata.unpin(file_name)
+        };
+        if changed {
+            self.repository.save_metadata(&metadata)?;
+        }
+        Ok(())
+    }
+
+    fn trash(&self, file_name: &str) -> Result<()> {
+        self.repository.trash(file_name)?;
+        let mut metadata = self.repository.load_metadata()?;
+        if metadata.remove(file_name) {
+            self.repository.save_metadata(&metadata)?;
+        }
+        Ok(())
+    }
+
+    fn restore(&self, file_name: &str, pinned: bool, tags: &[String]) -> Result<()> {
+        self.repository.restore(file_name)?;
+        self.set_pinned(file_name, pinned)?;
+        self.set_tags(file_name, tags)
+    }
+
+    fn remove_from_trash(&self, file_name: &str) -> Result<()> {
+        self.repository.remove_from_trash(file_name)
+    }
+
+    fn export_archive_to_file(&self, path: &str) -> Result<ExportReport> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path))?;
+        self.export_archive(file, None, None)
+    }
+
+    fn switch_notebook(&self, notebook: Option<&str>) -> Result<()> {
+        let notebook = match notebook {
+            Some(notebook) => Some(notebook::normalize(notebook)?),
+            None => None,
+        };
+        *self.notebook.borrow_mut() = notebook;
+        self.reload()
+    }
+
+    fn get_notebook(&self) -> Option<Option<String>> {
+        self.notebook.borrow().clone()
+    }
+
+    fn get_notebook_list(&self) -> Result<Vec<String>> {
+        let metadata = self.repository.load_metadata()?;
+        Ok(KakisuteList::from_dir(self.repository.read_dir(), &metadata).get_notebook_list())
+    }
+
+    /// Same as the filter in the TUI, see Query for the syntax
+    fn search(&self, query: &str) -> Result<Vec<String>> {
+        let query = Query::parse(query)?;
+        Ok(Service::find(self.repository, self.kakisute_list, &query)?
+            .into_iter()
+            .map(|(file_name, _)| file_name)
+            .collect())
+    }
+
+    fn get_workspaces(&self) -> Vec<Workspace> {
+        self.workspaces.clone()
+    }
+
+    fn toggle_pin_by_index(&self, index: usize) -> Result<String> {
+        if let Some(file_name) = self.kakisute_list.get_file_name_by_index(index) {
+            let is_pinned = self.repository.load_metadata()?.is_pinned(&file_name);
+            self.set_pinned_by_index(index, !is_pinned)
+        } else {
+            Err(anyhow!("File not found"))
+        }
+    }
+
+    fn reload(&self) -> Result<()> {
+        let metadata = self.repository.load_metadata()?;
+        self.kakisute_list
+            .reload(self.repository.read_dir(), &metadata);
+        if let Some(notebook) = &*self.notebook.borrow() {
+            self.kakisute_list.retain_notebook(notebook.as_deref());
+        }
+        Ok(())
     }
 
     fn get_kakisute_list(&self) -> Vec<String> {
         self.kakisute_list.get_kakisute_file_name_list()
     }
+
+    fn get_pinned_kakisute_list(&self) -> Vec<String> {
+        self.kakisute_list.get_pinned_kakisute_file_name_list()
+    }
+
+    fn get_kakisute_tags(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        Ok(self.repository.load_metadata()?.tagged().clone())
+    }
+
+    fn get_search_history(&self) -> Result<Vec<String>> {
+        Ok(self.repository.load_searches()?.history().to_vec())
+    }
+
+    fn add_search_history(&self, query: &str) -> Result<()> {
+        let mut searches = self.repository.load_searches()?;
+        if searches.add_history(query) {
+            self.repository.save_searches(&searches)?;
+        }
+        Ok(())
+    }
+
+    fn get_saved_searches(&self) -> Result<BTreeMap<String, String>> {
+        Ok(self.repository.load_searches()?.saved().clone())
+    }
 }
 
 pub trait ServiceTrait {
     fn create_kakisute(&self, file_name: Option<&str>) -> Result<String>;
+    fn create_kakisute_from_template(
+        &self,
+        file_name: Option<&str>,
+        template_name: &str,
+    ) -> Result<String>;
+    fn create_kakisute_with_content(
+        &self,
+        file_name: Option<&str>,
+        content: &str,
+    ) -> Result<String>;
+    fn get_template_list(&self) -> Result<Vec<String>>;
     fn edit_by_index(&self, index: usize) -> Result<String>;
+    fn update_content_by_index(&self, index: usize, content: &str) -> Result<String>;
     fn delete_by_index(&self, index: usize) -> Result<String>;
     fn get_content_by_index(&self, index: usize) -> Result<String>;
-    fn reload(&self);
+    fn get_path_by_index(&self, index: usize) -> Result<String>;
+    fn move_by_index(&self, index: usize, notebook: &str) -> Result<String>;
+    fn rename_by_index(&self, index: usize, name: &str) -> Result<String>;
+    /// Returns the file name and its tags
+    fn tag_by_index(
+        &self,
+        index: usize,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<(String, Vec<String>)>;
+    /// Rename or move the file, carrying the metadata over
+    fn rename_file(&self, from: &str, to: &str) -> Result<()>;
+    fn set_tags(&self, file_name: &str, tags: &[String]) -> Result<()>;
+    fn set_pinned(&self, file_name: &str, pinned: bool) -> Result<()>;
+    /// Move the file into the trash and forget its metadata, which is given back on restore
+    fn trash(&self, file_name: &str) -> Result<()>;
+    fn restore(&self, file_name: &str, pinned: bool, tags: &[String]) -> Result<()>;
+    /// Delete the trashed file for good
+    fn remove_from_trash(&self, file_name: &str) -> Result<()>;
+    /// Export every kakisute in the list. Returns the number of exported kakisute.
+    fn export_archive_to_file(&self, path: &str) -> Result<ExportReport>;
+    fn switch_notebook(&self, notebook: Option<&str>) -> Result<()>;
+    fn get_notebook(&self) -> Option<Option<String>>;
+    fn get_notebook_list(&self) -> Result<Vec<String>>;
+    fn search(&self, query: &str) -> Result<Vec<String>>;
+    /// Workspaces searched by the cross-workspace search
+    fn get_workspaces(&self) -> Vec<Workspace>;
+    fn toggle_pin_by_index(&self, index: usize) -> Result<String>;
+    fn reload(&self) -> Result<()>;
     fn get_kakisute_list(&self) -> Vec<String>;
+    fn get_pinned_kakisute_list(&self) -> Vec<String>;
+    /// File name to tags
+    fn get_kakisute_tags(&self) -> Result<BTreeMap<String, Vec<String>>>;
+    /// Oldest first
+    fn get_search_history(&self) -> Result<Vec<String>>;
+    fn add_search_history(&self, query: &str) -> Result<()>;
+    /// Name to query
+    fn get_saved_searches(&self) -> Result<BTreeMap<String, String>>;
 }
 
 #[cfg(test)]
@@ -194,4 +888,160 @@ speculate! {
             assert_eq!(file_name,"2022_01_10_16_30_15.txt")
         }
     }
+
+    describe "rename_by_index" {
+        it "keeps the datetime, the notebook and the metadata" {
+            use std::fs;
+            use crate::repository::Repository;
+            use crate::test_dir::TestDir;
+
+            let data_dir = TestDir::new("service_rename");
+            fs::create_dir_all(data_dir.join("work")).unwrap();
+            fs::write(data_dir.join("work/2022_01_10_16_30_15.txt"), "memo").unwrap();
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let mut metadata = repository.load_metadata().unwrap();
+            metadata.add_tag("work/2022_01_10_16_30_15.txt", "sql");
+            repository.save_metadata(&metadata).unwrap();
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &metadata);
+            let service = Service::new(&repository, &kakisute_list);
+
+            assert!(service.rename_by_index(0, "a/b").is_err());
+            let renamed = service.rename_by_index(0, "query.sql").unwrap();
+            assert_eq!(renamed, "work/2022_01_10_16_30_15_query.sql");
+            assert!(data_dir.join(&renamed).exists());
+            assert_eq!(repository.load_metadata().unwrap().tags(&renamed), ["sql"]);
+        }
+    }
+
+    describe "trash" {
+        it "restores the file and the metadata" {
+            use std::fs;
+            use crate::repository::Repository;
+            use crate::test_dir::TestDir;
+
+            let data_dir = TestDir::new("service_trash");
+            fs::create_dir_all(data_dir.join("work")).unwrap();
+            fs::write(data_dir.join("work/2022_01_10_16_30_15.txt"), "memo").unwrap();
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+            let file_name = "work/2022_01_10_16_30_15.txt";
+            service.set_pinned(file_name, true).unwrap();
+            service.set_tags(file_name, &["sql".to_string()]).unwrap();
+
+            service.trash(file_name).unwrap();
+            assert!(!data_dir.join(file_name).exists());
+            assert!(!repository.load_metadata().unwrap().is_pinned(file_name));
+            service.reload().unwrap();
+            assert!(service.get_kakisute_list().is_empty());
+
+            service.restore(file_name, true, &["sql".to_string()]).unwrap();
+            assert_eq!(fs::read_to_string(data_dir.join(file_name)).unwrap(), "memo");
+            let metadata = repository.load_metadata().unwrap();
+            assert!(metadata.is_pinned(file_name));
+            assert_eq!(metadata.tags(file_name), ["sql"]);
+
+            fs::write(data_dir.join("2022_01_11_09_00_00.txt"), "other").unwrap();
+            service.trash(file_name).unwrap();
+            service.trash("2022_01_11_09_00_00.txt").unwrap();
+            // Files of this session are kept apart from other sessions
+            let session_dirs = fs::read_dir(data_dir.join(".trash")).unwrap().flatten().collect::<Vec<_>>();
+            assert_eq!(session_dirs.len(), 1);
+            assert!(session_dirs[0].path().join(file_name).exists());
+            service.remove_from_trash(file_name).unwrap();
+            assert!(service.restore(file_name, false, &[]).is_err());
+            assert!(!session_dirs[0].path().join("work").exists());
+            service.restore("2022_01_11_09_00_00.txt", false, &[]).unwrap();
+            service.remove_from_trash("2022_01_11_09_00_00.txt").unwrap();
+            assert!(!data_dir.join(".trash").exists());
+        }
+    }
+
+    describe "pinned" {
+        it "sorts pinned kakisute first and selects them by --pinned" {
+            use std::fs;
+            use crate::repository::Repository;
+            use crate::test_dir::TestDir;
+
+            let data_dir = TestDir::new("service_pinned");
+            fs::write(data_dir.join("2022_01_10_09_00_00.txt"), "a").unwrap();
+            fs::write(data_dir.join("2022_01_11_09_00_00.txt"), "b").unwrap();
+            fs::write(data_dir.join("2022_01_12_09_00_00.txt"), "c").unwrap();
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let mut metadata = repository.load_metadata().unwrap();
+            metadata.pin("2022_01_11_09_00_00.txt");
+            repository.save_metadata(&metadata).unwrap();
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &metadata);
+            let service = Service::new(&repository, &kakisute_list);
+            let pinned = |n: usize| SingleQuery::new(false, Some(n), None);
+
+            assert_eq!(
+                service.get_kakisute_list(),
+                ["2022_01_11_09_00_00.txt", "2022_01_10_09_00_00.txt", "2022_01_12_09_00_00.txt"]
+            );
+            assert_eq!(service.get_content_by_single_query(pinned(1)).unwrap(), "b");
+            // --latest is not affected by pins
+            assert_eq!(service.get_content_by_single_query(SingleQuery::new(true, None, None)).unwrap(), "c");
+
+            service.pin_by_single_query(SingleQuery::new(false, None, Some("2022_01_12_09_00_00.txt".to_string()))).unwrap();
+            service.reload().unwrap();
+            assert_eq!(service.get_pinned_kakisute_list(), ["2022_01_11_09_00_00.txt", "2022_01_12_09_00_00.txt"]);
+            assert_eq!(service.get_content_by_single_query(pinned(2)).unwrap(), "c");
+
+            service.unpin_by_single_query(pinned(1)).unwrap();
+            service.reload().unwrap();
+            assert_eq!(service.get_content_by_single_query(pinned(1)).unwrap(), "c");
+        }
+    }
+
+    describe "export_archive" {
+        it "skips files which are not text" {
+            use std::fs;
+            use crate::repository::Repository;
+            use crate::test_dir::TestDir;
+
+            let data_dir = TestDir::new("service_export");
+            fs::write(data_dir.join("2022_01_10_09_00_00.txt"), "a").unwrap();
+            fs::write(data_dir.join("2022_01_11_09_00_00.bin"), [0xff, 0xfe]).unwrap();
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+
+            let mut archive = vec![];
+            let report = service.export_archive(&mut archive, None, None).unwrap();
+            assert_eq!(report.exported, 1);
+            assert_eq!(report.skipped.len(), 1);
+            assert_eq!(report.skipped[0].0, "2022_01_11_09_00_00.bin");
+            let content = archive::read_archive(archive.as_slice()).unwrap();
+            assert_eq!(content.entries.len(), 1);
+            assert_eq!(content.entries[0].file_name, "2022_01_10_09_00_00.txt");
+        }
+    }
+
+    describe "searches" {
+        it "keeps the history and the saved searches in the data directory" {
+            use crate::repository::Repository;
+            use crate::test_dir::TestDir;
+
+            let data_dir = TestDir::new("service_searches");
+            let repository = Repository::new(Some(data_dir.to_string_lossy().to_string()));
+            let kakisute_list = KakisuteList::from_dir(repository.read_dir(), &repository.load_metadata().unwrap());
+            let service = Service::new(&repository, &kakisute_list);
+
+            service.add_search_history("select").unwrap();
+            service.add_search_history("").unwrap();
+            assert_eq!(service.get_search_history().unwrap(), ["select"]);
+
+            service.save_search("prod", "SELECT.*prod").unwrap();
+            assert!(service.save_search("broken", "(").is_err());
+            assert!(service.save_search("", "prod").is_err());
+            assert_eq!(
+                service.get_saved_searches().unwrap(),
+                BTreeMap::from([("prod".to_string(), "SELECT.*prod".to_string())])
+            );
+            service.forget_search("prod").unwrap();
+            assert!(service.forget_search("prod").is_err());
+            assert_eq!(service.get_search_history().unwrap(), ["select"]);
+        }
+    }
 }
@@ -0,0 +1,226 @@
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use tar::{Archive, Builder, EntryType, Header};
+
+use crate::domain::metadata::Metadata;
+
+/// Metadata of exported kakisute is stored alongside them
+pub const ARCHIVE_METADATA_FILE_NAME: &str = ".metadata.json";
+
+pub struct ArchiveEntry {
+    /// Path relative to the data directory
+    pub file_name: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Local>>,
+}
+
+/// How to handle a file which already exists on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    KeepBoth,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "keep-both" => Ok(ConflictPolicy::KeepBoth),
+            _ => Err(anyhow!(
+                "Unknown policy: {} (skip, overwrite or keep-both)",
+                s
+            )),
+        }
+    }
+}
+
+pub struct ArchiveContent {
+    pub entries: Vec<ArchiveEntry>,
+    pub metadata: Metadata,
+    /// Pairs of the name in the archive and the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Default, Debug)]
+pub struct ExportReport {
+    pub exported: usize,
+    /// Pairs of the file name and the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Default, Debug)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub overwritten: Vec<String>,
+    /// Pairs of the name in the archive and the name actually used
+    pub renamed: Vec<(String, String)>,
+    /// Pairs of the name in the archive and the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Write tar.gz archive. Modification time of each entry is the time encoded in its name.
+pub fn write_archive<W: Write>(
+    writer: W,
+    entries: &[ArchiveEntry],
+    metadata: &Metadata,
+) -> Result<()> {
+    let encoder = GzEncoder::new(writer, Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    for entry in entries {
+        let mtime = entry
+            .created_at
+            .map(|created_at| created_at.timestamp().max(0) as u64)
+            .unwrap_or(0);
+        append_file(
+            &mut builder,
+            &entry.file_name,
+            entry.content.as_bytes(),
+            mtime,
+        )?;
+    }
+
+    let metadata = serde_json::to_string_pretty(metadata)?;
+    let now = Local::now().timestamp().max(0) as u64;
+    append_file(
+        &mut builder,
+        ARCHIVE_METADATA_FILE_NAME,
+        metadata.as_bytes(),
+        now,
+    )?;
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn append_file<W: Write>(
+    builder: &mut Builder<W>,
+    path: &str,
+    content: &[u8],
+    mtime: u64,
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    builder
+        .append_data(&mut header, path, content)
+        .with_context(|| format!("Failed to archive {}", path))
+}
+
+/// Read tar.gz archive written by `write_archive`.
+/// Entries which are not files or not UTF-8 are returned as skipped with the reason.
+pub fn read_archive<R: Read>(reader: R) -> Result<ArchiveContent> {
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    let mut entries = vec![];
+    let mut metadata = Metadata::new();
+    let mut skipped = vec![];
+
+    for entry in archive.entries().context("Failed to read archive")? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let mut content = String::new();
+        if entry.read_to_string(&mut content).is_err() {
+            skipped.push((path, "not a UTF-8 text file".to_string()));
+            continue;
+        }
+        if path == ARCHIVE_METADATA_FILE_NAME {
+            metadata = serde_json::from_str(&content).context("Failed to parse metadata")?;
+            continue;
+        }
+        entries.push(ArchiveEntry {
+            file_name: path,
+            content,
+            created_at: None,
+        });
+    }
+
+    Ok(ArchiveContent {
+        entries,
+        metadata,
+        skipped,
+    })
+}
+
+/// Find a name which does not exist by adding a number, keeping the date prefix
+pub fn keep_both_name(file_name: &str, exists: impl Fn(&str) -> bool) -> String {
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.ends_with('/') && !stem.is_empty() => {
+            (stem, Some(extension))
+        }
+        _ => (file_name, None),
+    };
+    let mut n = 1;
+    loop {
+        let candidate = match extension {
+            Some(extension) => format!("{}_{}.{}", stem, n, extension),
+            None => format!("{}_{}", stem, n),
+        };
+        if !exists(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "archive" {
+        it "round trips entries and metadata" {
+            let mut metadata = Metadata::new();
+            metadata.pin("work/2022_01_10_16_30_15.txt");
+            let entries = vec![
+                ArchiveEntry {
+                    file_name: "work/2022_01_10_16_30_15.txt".to_string(),
+                    content: "content".to_string(),
+                    created_at: None,
+                },
+            ];
+            let mut buffer = vec![];
+            write_archive(&mut buffer, &entries, &metadata).unwrap();
+
+            let content = read_archive(buffer.as_slice()).unwrap();
+            assert_eq!(content.entries.len(), 1);
+            assert_eq!(content.entries[0].file_name, "work/2022_01_10_16_30_15.txt");
+            assert_eq!(content.entries[0].content, "content");
+            assert_eq!(content.metadata, metadata);
+            assert!(content.skipped.is_empty());
+        }
+    }
+
+    describe "keep_both_name" {
+        it "adds number before extension" {
+            let name = keep_both_name("2022_01_10_16_30_15.txt", |name| name == "2022_01_10_16_30_15_1.txt");
+            assert_eq!(name, "2022_01_10_16_30_15_2.txt");
+        }
+
+        it "adds number to the end without extension" {
+            let name = keep_both_name("work/2022_01_10_16_30_15_memo", |_| false);
+            assert_eq!(name, "work/2022_01_10_16_30_15_memo_1");
+        }
+    }
+
+    describe "conflict_policy" {
+        it "parses names" {
+            assert_eq!("keep-both".parse::<ConflictPolicy>().unwrap(), ConflictPolicy::KeepBoth);
+            assert!("unknown".parse::<ConflictPolicy>().is_err());
+        }
+    }
+}
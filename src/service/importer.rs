@@ -0,0 +1,318 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::datetime_helper::{datetime_to_string, string_to_datetime};
+use crate::domain::{metadata::Metadata, notebook};
+
+use super::archive::{self, ArchiveContent, ArchiveEntry};
+
+const MARKDOWN_EXTENSION: &str = "md";
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Where to import kakisute from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// tar.gz created by export
+    Archive,
+    /// Arbitrary directory tree
+    Dir,
+    /// Obsidian-style Markdown vault
+    Obsidian,
+    /// JSON exported from GitHub gists
+    Gist,
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "archive" => Ok(ImportFormat::Archive),
+            "dir" => Ok(ImportFormat::Dir),
+            "obsidian" => Ok(ImportFormat::Obsidian),
+            "gist" => Ok(ImportFormat::Gist),
+            _ => Err(anyhow!(
+                "Unknown format: {} (archive, dir, obsidian or gist)",
+                s
+            )),
+        }
+    }
+}
+
+pub fn read(format: ImportFormat, path: &Path) -> Result<ArchiveContent> {
+    match format {
+        ImportFormat::Archive => {
+            let file = fs::File::open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            archive::read_archive(file)
+        }
+        ImportFormat::Dir => read_dir_tree(path, false),
+        ImportFormat::Obsidian => read_dir_tree(path, true),
+        ImportFormat::Gist => {
+            let file = fs::File::open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            read_gist_json(file)
+        }
+    }
+}
+
+/// Subdirectories become notebooks and the modification time is used as the created time.
+/// Hidden files and directories (e.g. `.obsidian`, `.git`) are ignored.
+fn read_dir_tree(root: &Path, markdown_only: bool) -> Result<ArchiveContent> {
+    if !root.is_dir() {
+        return Err(anyhow!("Not a directory: {}", root.display()));
+    }
+    let mut content = ArchiveContent {
+        entries: vec![],
+        metadata: Metadata::new(),
+        skipped: vec![],
+    };
+
+    let walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.file_name()));
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry
+            .path()
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if markdown_only
+            && entry
+                .path()
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                != Some(MARKDOWN_EXTENSION.to_string())
+        {
+            content
+                .skipped
+                .push((relative_path, "not a Markdown file".to_string()));
+            continue;
+        }
+
+        let text = match fs::read_to_string(entry.path()) {
+            Ok(text) => text,
+            Err(_) => {
+                content
+                    .skipped
+                    .push((relative_path, "not a UTF-8 text file".to_string()));
+                continue;
+            }
+        };
+        let modified_at = entry
+            .metadata()?
+            .modified()
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(|_| Local::now());
+
+        let (dir, file_name) = notebook::split(&relative_path);
+        let file_name = notebook::join(dir, &with_date_prefix(file_name, modified_at));
+        if markdown_only {
+            for tag in front_matter_tags(&text) {
+                content.metadata.add_tag(&file_name, &tag);
+            }
+        }
+        content.entries.push(ArchiveEntry {
+            file_name,
+            content: text,
+            created_at: Some(modified_at),
+        });
+    }
+
+    Ok(content)
+}
+
+fn is_hidden(file_name: &std::ffi::OsStr) -> bool {
+    file_name.to_string_lossy().starts_with('.')
+}
+
+/// Files already named by kakisute are kept as they are
+fn with_date_prefix(file_name: &str, date: DateTime<Local>) -> String {
+    if string_to_datetime(file_name).single().is_some() {
+        return file_name.to_string();
+    }
+    datetime_to_string(date) + "_" + file_name
+}
+
+/// Tags in YAML front matter. Both `tags: [a, b]` and block list are supported.
+fn front_matter_tags(text: &str) -> Vec<String> {
+    let mut lines = text.lines();
+    if lines.next().map(|line| line.trim_end()) != Some(FRONT_MATTER_DELIMITER) {
+        return vec![];
+    }
+
+    let mut tags = vec![];
+    let mut in_tags = false;
+    for line in lines {
+        if line.trim_end() == FRONT_MATTER_DELIMITER {
+            break;
+        }
+        if in_tags {
+            if let Some(item) = line.trim_start().strip_prefix("- ") {
+                tags.push(item.to_string());
+                continue;
+            }
+            in_tags = false;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        if key != "tags" && key != "tag" {
+            continue;
+        }
+        if value.is_empty() {
+            in_tags = true;
+            continue;
+        }
+        let value = value.trim_start_matches('[').trim_end_matches(']');
+        tags.extend(value.split([',', ' ']).map(|tag| tag.to_string()));
+    }
+
+    tags.iter()
+        .map(|tag| {
+            tag.trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .trim_start_matches('#')
+                .to_string()
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GistExport {
+    List(Vec<Gist>),
+    Single(Gist),
+}
+
+#[derive(Deserialize)]
+struct Gist {
+    #[serde(default)]
+    id: String,
+    created_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    files: std::collections::BTreeMap<String, GistFile>,
+}
+
+#[derive(Deserialize)]
+struct GistFile {
+    filename: Option<String>,
+    content: Option<String>,
+}
+
+/// Accepts both a gist object and an array of them as returned by GitHub API.
+/// Files without content (e.g. truncated ones) are skipped.
+fn read_gist_json<R: Read>(reader: R) -> Result<ArchiveContent> {
+    let export: GistExport =
+        serde_json::from_reader(reader).context("Failed to parse gist JSON")?;
+    let gists = match export {
+        GistExport::List(gists) => gists,
+        GistExport::Single(gist) => vec![gist],
+    };
+    let mut content = ArchiveContent {
+        entries: vec![],
+        metadata: Metadata::new(),
+        skipped: vec![],
+    };
+
+    for gist in gists {
+        let created_at = gist.created_at.unwrap_or_else(Local::now);
+        for (key, file) in gist.files {
+            let file_name = file.filename.unwrap_or(key);
+            let label = format!("{}/{}", gist.id, file_name);
+            let text = match file.content {
+                Some(text) => text,
+                None => {
+                    content.skipped.push((label, "no content".to_string()));
+                    continue;
+                }
+            };
+            if file_name.contains('/') || is_hidden(file_name.as_ref()) {
+                content
+                    .skipped
+                    .push((label, "invalid file name".to_string()));
+                continue;
+            }
+            content.entries.push(ArchiveEntry {
+                file_name: with_date_prefix(&file_name, created_at),
+                content: text,
+                created_at: Some(created_at),
+            });
+        }
+    }
+
+    Ok(content)
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use chrono::TimeZone;
+
+    describe "front_matter_tags" {
+        it "reads inline list" {
+            let text = "---\ntitle: a\ntags: [sql, \"prod\"]\n---\nbody";
+            assert_eq!(front_matter_tags(text), vec!["sql", "prod"]);
+        }
+
+        it "reads block list" {
+            let text = "---\ntags:\n  - sql\n  - '#prod'\naliases: b\n---\nbody";
+            assert_eq!(front_matter_tags(text), vec!["sql", "prod"]);
+        }
+
+        it "ignores text without front matter" {
+            assert!(front_matter_tags("tags: [sql]").is_empty());
+        }
+    }
+
+    describe "with_date_prefix" {
+        before {
+            let date = Local.ymd(2022, 1, 10).and_hms(16, 30, 15);
+        }
+
+        it "adds date" {
+            assert_eq!(with_date_prefix("memo.md", date), "2022_01_10_16_30_15_memo.md");
+        }
+
+        it "keeps file named by kakisute" {
+            assert_eq!(with_date_prefix("2021_01_01_00_00_00.txt", date), "2021_01_01_00_00_00.txt");
+        }
+    }
+
+    describe "read_gist_json" {
+        it "reads files and skips the ones without content" {
+            let json = r#"[{
+                "id": "abc",
+                "created_at": "2022-01-10T16:30:15Z",
+                "files": {
+                    "query.sql": {"filename": "query.sql", "content": "select 1"},
+                    "big.log": {"filename": "big.log"}
+                }
+            }]"#;
+            let content = read_gist_json(json.as_bytes()).unwrap();
+            assert_eq!(content.entries.len(), 1);
+            assert!(content.entries[0].file_name.ends_with("_query.sql"));
+            assert_eq!(content.entries[0].content, "select 1");
+            assert_eq!(content.skipped, vec![("abc/big.log".to_string(), "no content".to_string())]);
+        }
+    }
+}
@@ -1,9 +1,28 @@
 use anyhow::Result;
 use std::fs::ReadDir;
+
+use crate::domain::metadata::Metadata;
+use crate::domain::searches::Searches;
+
 pub trait IRepository {
     fn read_dir(&self) -> ReadDir;
     fn edit(&self, file_name: &str) -> Result<()>;
     fn get_path(&self, file_name: &str) -> Result<String>;
     fn delete(&self, file_name: &str) -> Result<()>;
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+    /// Move the file into the trash, from which it can be restored
+    fn trash(&self, file_name: &str) -> Result<()>;
+    fn restore(&self, file_name: &str) -> Result<()>;
+    /// Other files in the trash are kept, as they may be restored by other sessions
+    fn remove_from_trash(&self, file_name: &str) -> Result<()>;
     fn get_content(&self, file_name: &str) -> Result<String>;
+    fn create(&self, file_name: &str, content: &str) -> Result<()>;
+    fn overwrite(&self, file_name: &str, content: &str) -> Result<()>;
+    fn exists(&self, file_name: &str) -> bool;
+    fn get_template_file_name_list(&self) -> Result<Vec<String>>;
+    fn get_template_content(&self, template_file_name: &str) -> Result<String>;
+    fn load_metadata(&self) -> Result<Metadata>;
+    fn save_metadata(&self, metadata: &Metadata) -> Result<()>;
+    fn load_searches(&self) -> Result<Searches>;
+    fn save_searches(&self, searches: &Searches) -> Result<()>;
 }
@@ -1,9 +1,13 @@
 use kakisute_file::KakisuteFile;
 use std::rc::Rc;
-use std::{cell::RefCell, fs::ReadDir};
+use std::{
+    cell::RefCell,
+    fs::{self, ReadDir},
+};
 
 use super::search_query::SingleQuery;
-mod kakisute_file;
+use crate::domain::{metadata::Metadata, notebook};
+pub mod kakisute_file;
 
 #[derive(Clone, Debug)]
 pub struct KakisuteList {
@@ -23,23 +27,67 @@ impl KakisuteList {
         }
     }
 
-    pub fn from_dir(read_dir: ReadDir) -> Self {
+    pub fn from_dir(read_dir: ReadDir, metadata: &Metadata) -> Self {
         let mut kakisute_list = Self::new();
 
-        for file in read_dir {
-            let kakisute_file = KakisuteFile::from_path(&file.unwrap().path());
-            if let Some(kakisute_file) = kakisute_file {
-                kakisute_list.add(kakisute_file);
-            }
-        }
+        kakisute_list.add_dir(read_dir, None, metadata);
 
         kakisute_list.sort();
 
         kakisute_list
     }
 
-    pub fn reload(&self, read_dir: ReadDir) {
-        let kakisute_list = Self::from_dir(read_dir);
+    /// Subdirectories are read as notebooks. Hidden directories (e.g. `.templates`) are skipped.
+    /// Symlinked directories are skipped as well, as they may loop back to the data directory.
+    fn add_dir(&mut self, read_dir: ReadDir, notebook: Option<&str>, metadata: &Metadata) {
+        // Entries which can not be read are skipped rather than failing the whole list
+        for file in read_dir.flatten() {
+            let path = file.path();
+            let is_symlink = file
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_symlink());
+            if path.is_dir() {
+                if is_symlink {
+                    continue;
+                }
+                let dir_name = file.file_name().to_string_lossy().to_string();
+                if dir_name.starts_with('.') {
+                    continue;
+                }
+                if let Ok(sub_read_dir) = fs::read_dir(&path) {
+                    let sub_notebook = notebook::join(notebook, &dir_name);
+                    self.add_dir(sub_read_dir, Some(&sub_notebook), metadata);
+                }
+                continue;
+            }
+
+            let kakisute_file = KakisuteFile::from_path(&path, notebook);
+            if let Some(mut kakisute_file) = kakisute_file {
+                kakisute_file.set_pinned(metadata.is_pinned(&kakisute_file.path_name()));
+                self.add(kakisute_file);
+            }
+        }
+    }
+
+    /// Keep only kakisute in the notebook. None means the top level of the data directory.
+    pub fn retain_notebook(&self, notebook: Option<&str>) {
+        self.borrow_mut()
+            .retain(|kakisute| kakisute.notebook() == notebook);
+    }
+
+    pub fn get_notebook_list(&self) -> Vec<String> {
+        let mut notebook_list = self
+            .borrow()
+            .iter()
+            .filter_map(|kakisute| kakisute.notebook().map(|notebook| notebook.to_string()))
+            .collect::<Vec<String>>();
+        notebook_list.sort();
+        notebook_list.dedup();
+        notebook_list
+    }
+
+    pub fn reload(&self, read_dir: ReadDir, metadata: &Metadata) {
+        let kakisute_list = Self::from_dir(read_dir, metadata);
         *self.borrow_mut() = kakisute_list.ref_files.borrow().clone();
     }
 
@@ -51,8 +99,15 @@ impl KakisuteList {
         self.len() == 0
     }
 
+    /// Pinned kakisute come first, then the others.
+    /// Each section is grouped by notebook and sorted by created date.
     fn sort(&self) {
-        self.borrow_mut().sort();
+        self.borrow_mut().sort_by(|a, b| {
+            b.is_pinned()
+                .cmp(&a.is_pinned())
+                .then_with(|| a.notebook().cmp(&b.notebook()))
+                .then_with(|| a.cmp(b))
+        });
     }
 
     fn add(&mut self, file: KakisuteFile) {
@@ -62,7 +117,19 @@ impl KakisuteList {
     pub fn get_kakisute_file_name_list(&self) -> Vec<String> {
         self.borrow()
             .iter()
-            .map(|kakisute| kakisute.file_name().to_string())
+            .map(|kakisute| kakisute.path_name())
+            .collect()
+    }
+
+    pub fn get_kakisute_file_list(&self) -> Vec<KakisuteFile> {
+        self.borrow().clone()
+    }
+
+    pub fn get_pinned_kakisute_file_name_list(&self) -> Vec<String> {
+        self.borrow()
+            .iter()
+            .filter(|kakisute| kakisute.is_pinned())
+            .map(|kakisute| kakisute.path_name())
             .collect()
     }
 
@@ -77,7 +144,7 @@ impl KakisuteList {
     pub fn get_file_name_by_index(&self, index: usize) -> Option<String> {
         self.borrow()
             .get(index)
-            .map(|kakisute| kakisute.file_name().to_string())
+            .map(|kakisute| kakisute.path_name())
     }
 
     pub fn get_matching_index(&self, query: SingleQuery) -> Option<usize> {
@@ -86,23 +153,121 @@ impl KakisuteList {
         match file_name {
             Some(file_name) => self.get_index_by_file_name(&file_name),
             None => {
-                if is_latest {
-                    Some(self.get_last_index())
+                if let Some(pinned) = query.pinned {
+                    self.get_pinned_index(pinned)
+                } else if is_latest {
+                    self.get_last_index()
                 } else {
-                    println!("File name or \"--latest\" flag is required.");
+                    println!("File name, \"--latest\" or \"--pinned\" is required.");
                     None
                 }
             }
         }
     }
 
+    /// Both the path relative to the data directory and the bare file name are accepted
     fn get_index_by_file_name(&self, file_name: &str) -> Option<usize> {
-        self.borrow_mut()
+        let files = self.borrow();
+        files
             .iter()
-            .position(|file| file.file_name() == file_name)
+            .position(|file| file.path_name() == file_name)
+            .or_else(|| files.iter().position(|file| file.file_name() == file_name))
+    }
+
+    /// Index of the most recently created kakisute, regardless of being pinned
+    fn get_last_index(&self) -> Option<usize> {
+        self.borrow()
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    /// `n` starts from 1 as it is shown to users
+    fn get_pinned_index(&self, n: usize) -> Option<usize> {
+        if n == 0 || n > self.get_pinned_kakisute_file_name_list().len() {
+            return None;
+        }
+        Some(n - 1)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_pinned_first() {
+        let data_dir = TestDir::new("list_pinned");
+        fs::create_dir_all(data_dir.join("work")).unwrap();
+        fs::write(data_dir.join("2022_01_10_09_00_00.txt"), "").unwrap();
+        fs::write(data_dir.join("2022_01_11_09_00_00.txt"), "").unwrap();
+        fs::write(data_dir.join("work/2022_01_09_09_00_00.txt"), "").unwrap();
+        fs::write(data_dir.join("2022_01_12_09_00_00.txt"), "").unwrap();
+        let mut metadata = Metadata::new();
+        metadata.pin("2022_01_12_09_00_00.txt");
+        metadata.pin("work/2022_01_09_09_00_00.txt");
+
+        let kakisute_list = KakisuteList::from_dir(fs::read_dir(&data_dir).unwrap(), &metadata);
+        assert_eq!(
+            kakisute_list.get_kakisute_file_name_list(),
+            vec![
+                "2022_01_12_09_00_00.txt",
+                "work/2022_01_09_09_00_00.txt",
+                "2022_01_10_09_00_00.txt",
+                "2022_01_11_09_00_00.txt",
+            ]
+        );
+
+        let pinned = |n: usize| SingleQuery::new(false, Some(n), None);
+        assert_eq!(kakisute_list.get_matching_index(pinned(1)), Some(0));
+        assert_eq!(kakisute_list.get_matching_index(pinned(2)), Some(1));
+        assert_eq!(kakisute_list.get_matching_index(pinned(0)), None);
+        assert_eq!(kakisute_list.get_matching_index(pinned(3)), None);
+        let latest = SingleQuery::new(true, None, None);
+        assert_eq!(kakisute_list.get_matching_index(latest), Some(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_skip_symlinked_directories() {
+        let data_dir = TestDir::new("list_symlink");
+        fs::create_dir_all(data_dir.join("work")).unwrap();
+        fs::write(data_dir.join("work/2022_01_10_16_30_15.txt"), "").unwrap();
+        std::os::unix::fs::symlink("..", data_dir.join("work/loop")).unwrap();
+        std::os::unix::fs::symlink("work", data_dir.join("linked")).unwrap();
+
+        let kakisute_list =
+            KakisuteList::from_dir(fs::read_dir(&data_dir).unwrap(), &Metadata::new());
+        assert_eq!(
+            kakisute_list.get_kakisute_file_name_list(),
+            vec!["work/2022_01_10_16_30_15.txt"]
+        );
+    }
+
+    #[test]
+    fn test_read_nested_notebooks() {
+        let data_dir = TestDir::new("list_notebooks");
+        fs::create_dir_all(data_dir.join("work/db")).unwrap();
+        fs::create_dir_all(data_dir.join(".templates")).unwrap();
+        fs::create_dir_all(data_dir.join("work/.cache")).unwrap();
+        fs::write(data_dir.join("2022_01_10_16_30_15.txt"), "").unwrap();
+        fs::write(data_dir.join("work/2022_01_11_09_00_00.txt"), "").unwrap();
+        fs::write(data_dir.join("work/db/2022_01_12_09_00_00.sql"), "").unwrap();
+        fs::write(data_dir.join(".templates/2022_01_13_09_00_00.md"), "").unwrap();
+        fs::write(data_dir.join("work/.cache/2022_01_14_09_00_00.txt"), "").unwrap();
 
-    fn get_last_index(&self) -> usize {
-        self.borrow_mut().len() - 1
+        let kakisute_list =
+            KakisuteList::from_dir(fs::read_dir(&data_dir).unwrap(), &Metadata::new());
+        assert_eq!(
+            kakisute_list.get_kakisute_file_name_list(),
+            vec![
+                "2022_01_10_16_30_15.txt",
+                "work/2022_01_11_09_00_00.txt",
+                "work/db/2022_01_12_09_00_00.sql",
+            ]
+        );
+        assert_eq!(kakisute_list.get_notebook_list(), vec!["work", "work/db"]);
     }
 }
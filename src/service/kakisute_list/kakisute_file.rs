@@ -3,10 +3,14 @@ use std::path::Path;
 
 use chrono::{DateTime, Local};
 
+use crate::domain::notebook;
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct KakisuteFile {
     file_name: String,
     created_at: Option<DateTime<Local>>,
+    notebook: Option<String>,
+    pinned: bool,
 }
 
 impl KakisuteFile {
@@ -14,7 +18,28 @@ impl KakisuteFile {
         &self.file_name
     }
 
-    pub fn from_path(path: &Path) -> Option<Self> {
+    pub fn created_at(&self) -> Option<DateTime<Local>> {
+        self.created_at
+    }
+
+    pub fn notebook(&self) -> Option<&str> {
+        self.notebook.as_deref()
+    }
+
+    /// Path relative to the data directory, which identifies the kakisute
+    pub fn path_name(&self) -> String {
+        notebook::join(self.notebook(), &self.file_name)
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    pub fn from_path(path: &Path, notebook: Option<&str>) -> Option<Self> {
         if !path.is_file() {
             return None;
         };
@@ -25,6 +50,8 @@ impl KakisuteFile {
         created_at.single().map(|created_at| KakisuteFile {
             file_name,
             created_at: Some(created_at),
+            notebook: notebook.map(|notebook| notebook.to_string()),
+            pinned: false,
         })
     }
 }
@@ -0,0 +1,573 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use grep::matcher::Matcher;
+use grep::regex::RegexMatcher;
+
+const FIELDS: &[&str] = &["name", "content", "tag", "ext", "after", "before"];
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Search query shared by the CLI and the TUI, such as `tag:sql (name:prod OR "drop table") NOT after:2024-01-01`.
+/// Terms next to each other are joined with AND, which binds tighter than OR.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Empty query, which matches everything
+    All,
+    Term(Term),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// File name or content
+    Text(Pattern),
+    Name(Pattern),
+    Content(Pattern),
+    Tag(String),
+    /// Without the leading dot
+    Ext(String),
+    /// Created on or after the day
+    After(NaiveDate),
+    /// Created before the day
+    Before(NaiveDate),
+}
+
+/// Regex, or a literal phrase when quoted
+#[derive(Clone)]
+pub struct Pattern {
+    source: String,
+    /// Boxed to keep the query small, as RegexMatcher is large
+    matcher: Box<RegexMatcher>,
+}
+
+/// What a query is evaluated against
+pub struct Target<'a> {
+    /// Path relative to the data directory
+    pub name: &'a str,
+    /// None while the content is not loaded. Only the name is searched then.
+    pub content: Option<&'a str>,
+    pub tags: &'a [String],
+    pub created_at: Option<DateTime<Local>>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query> {
+        Parser {
+            tokens: tokenize(query)?,
+            position: 0,
+        }
+        .parse()
+    }
+
+    pub fn matches(&self, target: &Target) -> bool {
+        match self {
+            Query::All => true,
+            Query::Term(term) => term.matches(target),
+            Query::And(left, right) => left.matches(target) && right.matches(target),
+            Query::Or(left, right) => left.matches(target) || right.matches(target),
+            Query::Not(query) => !query.matches(target),
+        }
+    }
+
+    /// Regex matching the text searched in contents, except under NOT. Used to show where it matched.
+    pub fn highlight_regex(&self) -> Option<String> {
+        let mut patterns = vec![];
+        self.collect_content_patterns(&mut patterns);
+        if patterns.is_empty() {
+            return None;
+        }
+        Some(
+            patterns
+                .iter()
+                .map(|pattern| format!("(?:{})", pattern.source))
+                .collect::<Vec<String>>()
+                .join("|"),
+        )
+    }
+
+    fn collect_content_patterns<'a>(&'a self, patterns: &mut Vec<&'a Pattern>) {
+        match self {
+            Query::Term(Term::Text(pattern) | Term::Content(pattern)) => patterns.push(pattern),
+            Query::And(left, right) | Query::Or(left, right) => {
+                left.collect_content_patterns(patterns);
+                right.collect_content_patterns(patterns);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, target: &Target) -> bool {
+        let created_on = target
+            .created_at
+            .map(|created_at| created_at.naive_local().date());
+        match self {
+            Term::Text(pattern) => {
+                pattern.is_match(target.name)
+                    || target
+                        .content
+                        .is_some_and(|content| pattern.is_match(content))
+            }
+            Term::Name(pattern) => pattern.is_match(target.name),
+            Term::Content(pattern) => target
+                .content
+                .is_some_and(|content| pattern.is_match(content)),
+            Term::Tag(tag) => target.tags.contains(tag),
+            Term::Ext(ext) => Path::new(target.name)
+                .extension()
+                .is_some_and(|extension| extension.to_string_lossy().eq_ignore_ascii_case(ext)),
+            Term::After(date) => created_on.is_some_and(|created_on| created_on >= *date),
+            Term::Before(date) => created_on.is_some_and(|created_on| created_on < *date),
+        }
+    }
+}
+
+impl Pattern {
+    fn regex(source: &str) -> Result<Self> {
+        let matcher = RegexMatcher::new(source).map_err(|err| {
+            // The last line tells what is wrong, such as "error: unclosed group"
+            let message = err.to_string();
+            let reason = message
+                .lines()
+                .last()
+                .unwrap_or_default()
+                .trim_start_matches("error: ")
+                .to_string();
+            anyhow!(reason)
+        })?;
+        Ok(Pattern {
+            source: source.to_string(),
+            matcher: Box::new(matcher),
+        })
+    }
+
+    fn phrase(phrase: &str) -> Result<Self> {
+        Pattern::regex(&escape(phrase))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        self.matcher.is_match(text.as_bytes()).unwrap_or(false)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+/// Same as the meta characters escaped by the regex crate
+fn escape(phrase: &str) -> String {
+    let mut escaped = String::new();
+    for c in phrase.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word {
+        field: Option<String>,
+        value: String,
+        quoted: bool,
+    },
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Word { field, value, .. } => match field {
+                Some(field) => write!(f, "\"{}:{}\"", field, value),
+                None => write!(f, "\"{}\"", value),
+            },
+        }
+    }
+}
+
+/// Tokens with the column where they start, counted from 1
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>> {
+    let chars = query.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            // "(?i)" and the like are flags of a regex
+            '(' if chars.get(i + 1) != Some(&'?') => {
+                tokens.push((Token::Open, column));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::Close, column));
+                i += 1;
+            }
+            '"' => {
+                let (value, end) = read_quoted(&chars, i)?;
+                tokens.push((
+                    Token::Word {
+                        field: None,
+                        value,
+                        quoted: true,
+                    },
+                    column,
+                ));
+                i = end;
+            }
+            _ => {
+                let (token, end) = read_word(&chars, i)?;
+                tokens.push((token, column));
+                i = end;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the phrase and the index after the closing quote. `\"` is a quote in the phrase.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let mut value = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((value, i + 1)),
+            '\\' if i + 1 < chars.len() && matches!(chars[i + 1], '"' | '\\') => {
+                value.push(chars[i + 1]);
+                i += 2;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(anyhow!("Unclosed quote at column {}", start + 1))
+}
+
+/// A word ends at whitespace or at ")" closing a group, so "(a OR b)" is a group while "a(b)?" is a regex
+fn read_word(chars: &[char], start: usize) -> Result<(Token, usize)> {
+    let mut word = String::new();
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() && !chars[i].is_whitespace() {
+        match chars[i] {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            // Quoted value of a field such as name:"a b"
+            '"' if word.ends_with(':') => break,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        word.push(chars[i]);
+        i += 1;
+    }
+    let token = match word.as_str() {
+        "AND" => Token::And,
+        "OR" => Token::Or,
+        "NOT" => Token::Not,
+        _ => match field_of(&word, start)? {
+            Some((field, value)) if value.is_empty() => {
+                if chars.get(i) != Some(&'"') {
+                    return Err(anyhow!(
+                        "Missing value after \"{}:\" at column {}",
+                        field,
+                        start + 1
+                    ));
+                }
+                let (value, end) = read_quoted(chars, i)?;
+                i = end;
+                Token::Word {
+                    field: Some(field),
+                    value,
+                    quoted: true,
+                }
+            }
+            Some((field, value)) => Token::Word {
+                field: Some(field),
+                value,
+                quoted: false,
+            },
+            None => Token::Word {
+                field: None,
+                value: word,
+                quoted: false,
+            },
+        },
+    };
+    Ok((token, i))
+}
+
+/// Lowercase words before ":" are fields, so that a typo such as "tga:" is reported.
+/// Others such as "TODO:", "std::fmt" or "https://example" are searched as they are.
+fn field_of(word: &str, start: usize) -> Result<Option<(String, String)>> {
+    let (field, value) = match word.split_once(':') {
+        Some((field, value)) => (field, value),
+        None => return Ok(None),
+    };
+    if FIELDS.contains(&field) {
+        return Ok(Some((field.to_string(), value.to_string())));
+    }
+    let is_field_like = !field.is_empty()
+        && field.chars().all(|c| c.is_ascii_lowercase())
+        && !value.starts_with(':')
+        && !value.starts_with("//");
+    if is_field_like {
+        return Err(anyhow!(
+            "Unknown field \"{}\" at column {} (available: {}). Quote it to search the text",
+            field,
+            start + 1,
+            FIELDS.join(", ")
+        ));
+    }
+    Ok(None)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<Query> {
+        if self.tokens.is_empty() {
+            return Ok(Query::All);
+        }
+        let query = self.parse_or()?;
+        match self.tokens.get(self.position) {
+            // Anything else is consumed as a term joined with AND
+            Some((token, column)) => Err(anyhow!("Unexpected {} at column {}", token, column)),
+            None => Ok(query),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut query = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(Token::Or | Token::Close) | None => return Ok(query),
+                Some(Token::And) => self.position += 1,
+                Some(_) => {}
+            }
+            query = Query::And(Box::new(query), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Query> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Query> {
+        let (token, column) = match self.tokens.get(self.position) {
+            Some((token, column)) => (token, *column),
+            None => {
+                let (last, _) = &self.tokens[self.position - 1];
+                return Err(anyhow!("Expected a term after {}", last));
+            }
+        };
+        self.position += 1;
+        match token {
+            Token::Open => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(anyhow!("Missing \")\" for \"(\" at column {}", column));
+                }
+                self.position += 1;
+                Ok(query)
+            }
+            Token::Word {
+                field,
+                value,
+                quoted,
+            } => Ok(Query::Term(
+                term(field.as_deref(), value, *quoted)
+                    .map_err(|err| anyhow!("{} at column {}: {}", token, column, err))?,
+            )),
+            token => Err(anyhow!(
+                "Expected a term but found {} at column {}",
+                token,
+                column
+            )),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+}
+
+fn term(field: Option<&str>, value: &str, quoted: bool) -> Result<Term> {
+    let pattern = || {
+        if quoted {
+            Pattern::phrase(value)
+        } else {
+            Pattern::regex(value)
+        }
+    };
+    let date = || {
+        NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map_err(|_| anyhow!("Invalid date, expected YYYY-MM-DD"))
+    };
+    Ok(match field {
+        None => Term::Text(pattern()?),
+        Some("name") => Term::Name(pattern()?),
+        Some("content") => Term::Content(pattern()?),
+        Some("tag") => Term::Tag(value.trim_start_matches('#').to_string()),
+        Some("ext") => Term::Ext(value.trim_start_matches('.').to_string()),
+        Some("after") => Term::After(date()?),
+        Some("before") => Term::Before(date()?),
+        Some(field) => return Err(anyhow!("Unknown field \"{}\"", field)),
+    })
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use chrono::TimeZone;
+
+    describe "parse" {
+        it "joins terms with AND tighter than OR" {
+            let query = Query::parse("a b OR NOT c").unwrap();
+            let text = |value: &str| Box::new(Query::Term(Term::Text(Pattern::regex(value).unwrap())));
+            assert_eq!(
+                query,
+                Query::Or(
+                    Box::new(Query::And(text("a"), text("b"))),
+                    Box::new(Query::Not(text("c")))
+                )
+            );
+            assert_eq!(Query::parse(" ").unwrap(), Query::All);
+        }
+
+        it "reads fields, phrases and groups" {
+            let query = Query::parse("tag:#sql AND (name:\"a.b\" OR f(o)?)").unwrap();
+            assert_eq!(
+                query,
+                Query::And(
+                    Box::new(Query::Term(Term::Tag("sql".to_string()))),
+                    Box::new(Query::Or(
+                        Box::new(Query::Term(Term::Name(Pattern::regex("a\\.b").unwrap()))),
+                        Box::new(Query::Term(Term::Text(Pattern::regex("f(o)?").unwrap())))
+                    ))
+                )
+            );
+            assert_eq!(
+                Query::parse("TODO:").unwrap(),
+                Query::Term(Term::Text(Pattern::regex("TODO:").unwrap()))
+            );
+        }
+
+        it "searches words which are not fields or groups as text" {
+            let text = |value: &str| Query::Term(Term::Text(Pattern::regex(value).unwrap()));
+            assert_eq!(Query::parse("std::fmt").unwrap(), text("std::fmt"));
+            assert_eq!(Query::parse("https://example").unwrap(), text("https://example"));
+            assert_eq!(Query::parse("(?i)USE").unwrap(), text("(?i)USE"));
+            assert_eq!(
+                Query::parse("((?i)use OR name:(?i)a)").unwrap(),
+                Query::Or(
+                    Box::new(text("(?i)use")),
+                    Box::new(Query::Term(Term::Name(Pattern::regex("(?i)a").unwrap())))
+                )
+            );
+        }
+
+        it "tells what is wrong" {
+            let error = |query: &str| Query::parse(query).unwrap_err().to_string();
+            assert_eq!(error("a \"b"), "Unclosed quote at column 3");
+            assert_eq!(error("(a OR b"), "Missing \")\" for \"(\" at column 1");
+            assert_eq!(error("a)"), "Unexpected \")\" at column 2");
+            assert_eq!(error("a AND"), "Expected a term after AND");
+            assert_eq!(error("OR a"), "Expected a term but found OR at column 1");
+            assert_eq!(error("name:"), "Missing value after \"name:\" at column 1");
+            assert!(error("tga:sql").starts_with("Unknown field \"tga\" at column 1"));
+            assert!(error("a note:x").starts_with("Unknown field \"note\" at column 3"));
+            assert_eq!(error("x after:2024-13-01"), "\"after:2024-13-01\" at column 3: Invalid date, expected YYYY-MM-DD");
+            assert_eq!(error("content:(a"), "\"content:(a\" at column 1: unclosed group");
+        }
+    }
+
+    describe "matches" {
+        before {
+            let tags = vec!["sql".to_string()];
+            let target = Target {
+                name: "work/2024_01_10_09_00_00_prod.SQL",
+                content: Some("select * from users;\ndrop table users;"),
+                tags: &tags,
+                created_at: Some(Local.ymd(2024, 1, 10).and_hms(9, 0, 0)),
+            };
+        }
+
+        it "evaluates the fields" {
+            let matches = |query: &str| Query::parse(query).unwrap().matches(&target);
+            assert!(matches("prod select"));
+            assert!(matches("name:work/ content:users"));
+            assert!(!matches("content:prod"));
+            assert!(matches("tag:sql ext:.sql"));
+            assert!(!matches("tag:s"));
+            assert!(matches("after:2024-01-10 before:2024-01-11"));
+            assert!(!matches("after:2024-01-11 OR before:2024-01-10"));
+        }
+
+        it "evaluates phrases and operators" {
+            let matches = |query: &str| Query::parse(query).unwrap().matches(&target);
+            assert!(matches("\"drop table\""));
+            assert!(!matches("\"select.*\""));
+            assert!(matches("NOT tag:md (missing OR users)"));
+            assert!(!matches("NOT (tag:md OR users)"));
+        }
+
+        it "searches only the name until the content is loaded" {
+            let target = Target { content: None, ..target };
+            let query = Query::parse("prod OR content:users").unwrap();
+            assert!(query.matches(&target));
+            assert!(!Query::parse("users").unwrap().matches(&target));
+        }
+    }
+
+    describe "highlight_regex" {
+        it "joins the patterns searched in contents" {
+            let query = Query::parse("tag:sql (a OR content:\"b.c\") NOT d").unwrap();
+            assert_eq!(query.highlight_regex().unwrap(), "(?:a)|(?:b\\.c)");
+            assert_eq!(Query::parse("name:a").unwrap().highlight_regex(), None);
+        }
+    }
+}
@@ -1,12 +1,14 @@
 pub struct SingleQuery {
     pub is_latest: bool,
+    pub pinned: Option<usize>,
     pub file_name: Option<String>,
 }
 
 impl SingleQuery {
-    pub fn new(is_latest: bool, file_name: Option<String>) -> Self {
+    pub fn new(is_latest: bool, pinned: Option<usize>, file_name: Option<String>) -> Self {
         SingleQuery {
             is_latest,
+            pinned,
             file_name,
         }
     }
@@ -0,0 +1,620 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+
+use crate::domain::notebook;
+
+const STYLE_FILE_NAME: &str = "style.css";
+const SEARCH_INDEX_FILE_NAME: &str = "search-index.js";
+const SNIPPET_DIR: &str = "snippets";
+const TAG_DIR: &str = "tags";
+const UNKNOWN_DATE: &str = "Unknown date";
+/// Length of text stored in the search index for each kakisute
+const SEARCH_TEXT_LENGTH: usize = 2000;
+
+const STYLE: &str = r#"body { font-family: sans-serif; margin: 0 auto; max-width: 60rem; padding: 1rem; color: #222; }
+a { color: #0550ae; text-decoration: none; }
+a:hover { text-decoration: underline; }
+h2 { border-bottom: 1px solid #ddd; font-size: 1.1rem; }
+ul { list-style: none; padding-left: 0; }
+li { margin: 0.2rem 0; }
+.tag { background: #eef; border-radius: 0.3rem; font-size: 0.8rem; margin-left: 0.3rem; padding: 0 0.3rem; }
+.notebook { color: #666; }
+input#search { box-sizing: border-box; font-size: 1rem; padding: 0.4rem; width: 100%; }
+pre { background: #f6f8fa; border-radius: 0.3rem; overflow-x: auto; padding: 1rem; }
+.hl-comment { color: #6a737d; font-style: italic; }
+.hl-string { color: #032f62; }
+.hl-number { color: #005cc5; }
+.hl-keyword { color: #d73a49; font-weight: bold; }
+"#;
+
+const SEARCH_SCRIPT: &str = r#"<script src="search-index.js"></script>
+<script>
+(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("results");
+  var groups = document.getElementById("groups");
+  input.addEventListener("input", function () {
+    var words = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    if (words.length === 0) { groups.hidden = false; return; }
+    groups.hidden = true;
+    window.KAKISUTE_INDEX.filter(function (item) {
+      var text = (item.title + " " + item.tags.join(" ") + " " + item.text).toLowerCase();
+      return words.every(function (word) { return text.indexOf(word) !== -1; });
+    }).forEach(function (item) {
+      var li = document.createElement("li");
+      var a = document.createElement("a");
+      a.href = item.url;
+      a.textContent = item.title;
+      li.appendChild(a);
+      results.appendChild(li);
+    });
+  });
+})();
+</script>
+"#;
+
+/// Kakisute to publish
+pub struct SiteEntry {
+    /// Path relative to the data directory
+    pub file_name: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Local>>,
+    pub tags: Vec<String>,
+}
+
+/// File of the generated site. `path` is relative to the output directory
+pub struct SitePage {
+    pub path: String,
+    pub content: String,
+}
+
+/// Build read-only browsable pages. Everything is local so that it works without network.
+pub fn build_site(title: &str, entries: &[SiteEntry]) -> Vec<SitePage> {
+    let mut entries = entries.iter().collect::<Vec<&SiteEntry>>();
+    // Newest first
+    entries.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then(a.file_name.cmp(&b.file_name))
+    });
+
+    let mut pages = vec![
+        SitePage {
+            path: STYLE_FILE_NAME.to_string(),
+            content: STYLE.to_string(),
+        },
+        SitePage {
+            path: "index.html".to_string(),
+            content: build_index(title, &entries),
+        },
+        SitePage {
+            path: SEARCH_INDEX_FILE_NAME.to_string(),
+            content: build_search_index(&entries),
+        },
+    ];
+
+    for entry in &entries {
+        pages.push(SitePage {
+            path: snippet_path(&entry.file_name),
+            content: build_snippet_page(title, entry),
+        });
+    }
+
+    let mut tagged: BTreeMap<&str, Vec<&SiteEntry>> = BTreeMap::new();
+    for entry in &entries {
+        for tag in &entry.tags {
+            tagged.entry(tag).or_default().push(entry);
+        }
+    }
+    for (tag, entries) in tagged {
+        pages.push(SitePage {
+            path: tag_path(tag),
+            content: build_tag_page(title, tag, &entries),
+        });
+    }
+
+    pages
+}
+
+pub fn write_site(out_dir: &Path, pages: &[SitePage]) -> Result<()> {
+    for page in pages {
+        let path = out_dir.join(&page.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, &page.content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn snippet_path(file_name: &str) -> String {
+    format!("{}/{}.html", SNIPPET_DIR, page_name(file_name))
+}
+
+fn tag_path(tag: &str) -> String {
+    format!("{}/{}.html", TAG_DIR, page_name(tag))
+}
+
+/// Other characters than lowercase alphanumerics and "-" are written as "_" and the hex of their bytes,
+/// so that different names never share a page, even on case-insensitive file systems
+fn page_name(name: &str) -> String {
+    let mut encoded = String::new();
+    for c in name.chars() {
+        if (c.is_alphanumeric() && !c.is_uppercase()) || c == '-' {
+            encoded.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("_{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
+
+/// Percent-encoded URL of a page, to be put in an attribute after `escape`
+fn page_url(root: &str, path: &str) -> String {
+    let mut url = root.to_string();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_./".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+fn format_date(created_at: Option<DateTime<Local>>) -> String {
+    created_at
+        .map(|created_at| created_at.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| UNKNOWN_DATE.to_string())
+}
+
+/// `root` is the relative path from the page to the output directory
+fn layout(site_title: &str, page_title: &str, root: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{page_title} - {site_title}</title>
+<link rel="stylesheet" href="{style}">
+</head>
+<body>
+<header><a href="{index}">{site_title}</a></header>
+{body}
+</body>
+</html>
+"#,
+        page_title = escape(page_title),
+        site_title = escape(site_title),
+        style = escape(&page_url(root, STYLE_FILE_NAME)),
+        index = escape(&page_url(root, "index.html")),
+        body = body
+    )
+}
+
+fn entry_item(entry: &SiteEntry, root: &str) -> String {
+    let (notebook, file_name) = notebook::split(&entry.file_name);
+    let notebook = notebook
+        .map(|notebook| format!(r#"<span class="notebook">{}/</span>"#, escape(notebook)))
+        .unwrap_or_default();
+    let tags = entry
+        .tags
+        .iter()
+        .map(|tag| {
+            format!(
+                r#"<a class="tag" href="{}">{}</a>"#,
+                escape(&page_url(root, &tag_path(tag))),
+                escape(tag)
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<li><a href="{}">{}{}</a>{}</li>"#,
+        escape(&page_url(root, &snippet_path(&entry.file_name))),
+        notebook,
+        escape(file_name),
+        tags
+    )
+}
+
+fn build_index(title: &str, entries: &[&SiteEntry]) -> String {
+    let mut groups: Vec<(String, Vec<&SiteEntry>)> = vec![];
+    for entry in entries {
+        let date = format_date(entry.created_at);
+        match groups.last_mut() {
+            Some((last_date, group)) if *last_date == date => group.push(entry),
+            _ => groups.push((date, vec![entry])),
+        }
+    }
+
+    let mut body = String::from(
+        r#"<input id="search" type="search" placeholder="Search" autofocus>
+<ul id="results"></ul>
+<div id="groups">
+"#,
+    );
+    for (date, group) in groups {
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&date)));
+        for entry in group {
+            body.push_str(&entry_item(entry, ""));
+            body.push('\n');
+        }
+        body.push_str("</ul>\n");
+    }
+    body.push_str("</div>\n");
+    body.push_str(SEARCH_SCRIPT);
+    layout(title, "Index", "", &body)
+}
+
+fn build_snippet_page(title: &str, entry: &SiteEntry) -> String {
+    let extension = Path::new(&entry.file_name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tags = entry
+        .tags
+        .iter()
+        .map(|tag| {
+            format!(
+                r#"<a class="tag" href="{}">{}</a>"#,
+                escape(&page_url("../", &tag_path(tag))),
+                escape(tag)
+            )
+        })
+        .collect::<String>();
+    let body = format!(
+        "<h1>{}</h1>\n<p>{}{}</p>\n<pre><code>{}</code></pre>\n",
+        escape(&entry.file_name),
+        escape(&format_date(entry.created_at)),
+        tags,
+        highlight(&entry.content, &extension)
+    );
+    layout(title, &entry.file_name, "../", &body)
+}
+
+fn build_tag_page(title: &str, tag: &str, entries: &[&SiteEntry]) -> String {
+    let items = entries
+        .iter()
+        .map(|entry| entry_item(entry, "../"))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let body = format!("<h1>#{}</h1>\n<ul>\n{}\n</ul>\n", escape(tag), items);
+    layout(title, &format!("#{}", tag), "../", &body)
+}
+
+/// JavaScript instead of JSON so that it can be loaded from `file://` without fetch
+fn build_search_index(entries: &[&SiteEntry]) -> String {
+    let items = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "title": entry.file_name,
+                "url": page_url("", &snippet_path(&entry.file_name)),
+                "date": format_date(entry.created_at),
+                "tags": entry.tags,
+                "text": entry.content.chars().take(SEARCH_TEXT_LENGTH).collect::<String>(),
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+    format!(
+        "window.KAKISUTE_INDEX = {};\n",
+        serde_json::Value::Array(items)
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    keywords: &'static [&'static str],
+    case_insensitive: bool,
+}
+
+fn syntax_for(extension: &str) -> Option<Syntax> {
+    let c_like_comments: &[&str] = &["//"];
+    let syntax = match extension.to_lowercase().as_str() {
+        "sql" => Syntax {
+            line_comments: &["--"],
+            block_comment: Some(("/*", "*/")),
+            keywords: &[
+                "select", "from", "where", "and", "or", "not", "insert", "into", "values",
+                "update", "set", "delete", "create", "table", "drop", "alter", "join", "left",
+                "right", "inner", "outer", "on", "group", "by", "order", "having", "limit", "as",
+                "null", "is", "in", "like", "distinct", "union", "with", "case", "when", "then",
+                "else", "end",
+            ],
+            case_insensitive: true,
+        },
+        "rs" => Syntax {
+            line_comments: c_like_comments,
+            block_comment: Some(("/*", "*/")),
+            keywords: &[
+                "as", "break", "const", "continue", "crate", "else", "enum", "fn", "for", "if",
+                "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+                "self", "Self", "static", "struct", "trait", "true", "false", "type", "unsafe",
+                "use", "where", "while", "async", "await", "dyn",
+            ],
+            case_insensitive: false,
+        },
+        "js" | "ts" | "jsx" | "tsx" | "java" | "c" | "h" | "cpp" | "go" | "kt" | "swift" => {
+            Syntax {
+                line_comments: c_like_comments,
+                block_comment: Some(("/*", "*/")),
+                keywords: &[
+                    "break",
+                    "case",
+                    "catch",
+                    "class",
+                    "const",
+                    "continue",
+                    "default",
+                    "do",
+                    "else",
+                    "export",
+                    "extends",
+                    "false",
+                    "for",
+                    "func",
+                    "function",
+                    "if",
+                    "import",
+                    "interface",
+                    "let",
+                    "new",
+                    "null",
+                    "package",
+                    "private",
+                    "public",
+                    "return",
+                    "static",
+                    "struct",
+                    "switch",
+                    "this",
+                    "throw",
+                    "true",
+                    "try",
+                    "type",
+                    "var",
+                    "void",
+                    "while",
+                ],
+                case_insensitive: false,
+            }
+        }
+        "py" => Syntax {
+            line_comments: &["#"],
+            block_comment: None,
+            keywords: &[
+                "and", "as", "class", "def", "elif", "else", "except", "False", "for", "from",
+                "if", "import", "in", "is", "lambda", "None", "not", "or", "pass", "raise",
+                "return", "True", "try", "while", "with", "yield",
+            ],
+            case_insensitive: false,
+        },
+        "sh" | "bash" | "zsh" => Syntax {
+            line_comments: &["#"],
+            block_comment: None,
+            keywords: &[
+                "if", "then", "else", "elif", "fi", "for", "in", "do", "done", "while", "case",
+                "esac", "function", "export", "local", "return",
+            ],
+            case_insensitive: false,
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Small highlighter for comments, strings, numbers and keywords. Unknown languages are escaped only.
+fn highlight(code: &str, extension: &str) -> String {
+    let syntax = match syntax_for(extension) {
+        Some(syntax) => syntax,
+        None => return escape(code),
+    };
+
+    let mut html = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        if let Some((start, end)) = syntax.block_comment {
+            if rest.starts_with(start) {
+                let length = rest[start.len()..]
+                    .find(end)
+                    .map(|position| start.len() + position + end.len())
+                    .unwrap_or(rest.len());
+                push_span(&mut html, "hl-comment", &rest[..length]);
+                rest = &rest[length..];
+                continue;
+            }
+        }
+        if syntax
+            .line_comments
+            .iter()
+            .any(|comment| rest.starts_with(comment))
+        {
+            let length = rest.find('\n').unwrap_or(rest.len());
+            push_span(&mut html, "hl-comment", &rest[..length]);
+            rest = &rest[length..];
+            continue;
+        }
+        if c == '"' || c == '\'' || c == '`' {
+            let mut length = rest.len();
+            let mut escaped = false;
+            for (position, next) in rest.char_indices().skip(1) {
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' {
+                    escaped = true;
+                } else if next == c {
+                    length = position + next.len_utf8();
+                    break;
+                }
+            }
+            push_span(&mut html, "hl-string", &rest[..length]);
+            rest = &rest[length..];
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let length = rest
+                .find(|next: char| !(next.is_alphanumeric() || next == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..length];
+            let is_keyword = syntax.keywords.iter().any(|keyword| {
+                if syntax.case_insensitive {
+                    keyword.eq_ignore_ascii_case(word)
+                } else {
+                    *keyword == word
+                }
+            });
+            if c.is_ascii_digit() {
+                push_span(&mut html, "hl-number", word);
+            } else if is_keyword {
+                push_span(&mut html, "hl-keyword", word);
+            } else {
+                html.push_str(&escape(word));
+            }
+            rest = &rest[length..];
+            continue;
+        }
+        html.push_str(&escape(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+    html
+}
+
+fn push_span(html: &mut String, class: &str, text: &str) {
+    html.push_str(&format!(
+        r#"<span class="{}">{}</span>"#,
+        class,
+        escape(text)
+    ));
+}
+
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use chrono::TimeZone;
+
+    describe "highlight" {
+        it "escapes unknown languages" {
+            assert_eq!(highlight("<a>", "txt"), "&lt;a&gt;");
+        }
+
+        it "marks keywords, strings, numbers and comments" {
+            let html = highlight("SELECT 'a<b', 1 -- note", "sql");
+            assert_eq!(
+                html,
+                "<span class=\"hl-keyword\">SELECT</span> <span class=\"hl-string\">&#39;a&lt;b&#39;</span>, <span class=\"hl-number\">1</span> <span class=\"hl-comment\">-- note</span>"
+            );
+        }
+
+        it "closes unterminated strings at the end" {
+            assert_eq!(highlight("\"abc", "rs"), "<span class=\"hl-string\">&quot;abc</span>");
+        }
+    }
+
+    describe "tag_path" {
+        it "gives each tag its own page" {
+            let tags = ["c++", "c__", "a.b", "a_b", "SQL", "sql", "メモ"];
+            let paths = tags.iter().map(|tag| tag_path(tag)).collect::<Vec<String>>();
+            assert_eq!(paths, vec![
+                "tags/c_2B_2B.html",
+                "tags/c_5F_5F.html",
+                "tags/a_2Eb.html",
+                "tags/a_5Fb.html",
+                "tags/_53_51_4C.html",
+                "tags/sql.html",
+                "tags/メモ.html",
+            ]);
+        }
+    }
+
+    describe "snippet_path" {
+        it "gives each kakisute its own page and links it safely" {
+            let entry = |file_name: &str| SiteEntry {
+                file_name: file_name.to_string(),
+                content: String::new(),
+                created_at: None,
+                tags: vec!["メモ".to_string()],
+            };
+            let entries = vec![entry("a/b_x.txt"), entry("a__b_x.txt"), entry("x\"y#z%.txt")];
+            let pages = build_site("kakisute", &entries);
+            let paths = pages.iter().map(|page| page.path.as_str()).collect::<Vec<&str>>();
+            assert!(paths.contains(&"snippets/a_2Fb_5Fx_2Etxt.html"));
+            assert!(paths.contains(&"snippets/a_5F_5Fb_5Fx_2Etxt.html"));
+            assert!(paths.contains(&"snippets/x_22y_23z_25_2Etxt.html"));
+
+            let index = &pages[1].content;
+            assert!(index.contains(r#"<a href="snippets/x_22y_23z_25_2Etxt.html">x&quot;y#z%.txt</a>"#));
+            assert!(index.contains(r#"href="tags/%E3%83%A1%E3%83%A2.html""#));
+            assert!(!index.contains("x\"y"));
+            assert!(pages[2].content.contains("\"url\":\"snippets/x_22y_23z_25_2Etxt.html\""));
+        }
+    }
+
+    describe "build_site" {
+        before {
+            let entries = vec![
+                SiteEntry {
+                    file_name: "2022_01_10_16_30_15.sql".to_string(),
+                    content: "select 1".to_string(),
+                    created_at: Some(Local.ymd(2022, 1, 10).and_hms(16, 30, 15)),
+                    tags: vec!["sql".to_string()],
+                },
+                SiteEntry {
+                    file_name: "work/2022_01_11_09_00_00.txt".to_string(),
+                    content: "memo".to_string(),
+                    created_at: Some(Local.ymd(2022, 1, 11).and_hms(9, 0, 0)),
+                    tags: vec![],
+                },
+            ];
+            let pages = build_site("kakisute", &entries);
+        }
+
+        it "generates index, snippet, tag pages and search index" {
+                let paths = pages.iter().map(|page| page.path.as_str()).collect::<Vec<&str>>();
+            assert_eq!(paths, vec![
+                "style.css",
+                "index.html",
+                "search-index.js",
+                "snippets/work_2F2022_5F01_5F11_5F09_5F00_5F00_2Etxt.html",
+                "snippets/2022_5F01_5F10_5F16_5F30_5F15_2Esql.html",
+                "tags/sql.html",
+            ]);
+        }
+
+        it "groups index by date with the newest first" {
+            let index = &pages[1].content;
+            let newer = index.find("2022-01-11").unwrap();
+            let older = index.find("2022-01-10").unwrap();
+            assert!(newer < older);
+        }
+    }
+}
@@ -3,5 +3,8 @@ mod components;
 mod display_data;
 pub mod index;
 mod input_handler;
+pub mod keymap;
+pub mod list_columns;
 mod renderer;
 mod terminal_manager;
+pub mod theme;
@@ -1,50 +1,511 @@
-use crate::service::ServiceTrait;
+use crate::datetime_helper::{string_to_datetime, strip_datetime};
+use crate::domain::notebook;
+use crate::service::query::{Query, Target};
+use crate::service::{ServiceTrait, WorkspaceSearchResult};
+use crate::ui::components::command_palette::CommandPalette;
+use crate::ui::components::content_loader::ContentLoader;
 use crate::ui::components::filtered_list::FilteredList;
+use crate::ui::components::history::History;
+use crate::ui::components::list_index::ListIndex;
+use crate::ui::components::text_editor::TextEditor;
 use crate::ui::components::text_input::TextInput;
-use crate::ui::display_data::Info;
-use anyhow::Result;
+use crate::ui::components::workspace_loader::WorkspaceLoader;
+use crate::ui::display_data::{Info, ListEntry};
+use crate::ui::keymap::{Action, Key, KeyResolution, Keymap, KeymapEntry};
+use crate::ui::list_columns::{ListColumn, DEFAULT_LIST_COLUMNS};
+use crate::ui::renderer::{PaneSettings, MAX_LIST_WIDTH_PERCENT, MIN_LIST_WIDTH_PERCENT};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::result::Result::Ok;
+use std::time::{Duration, Instant};
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+/// How long a status message stays in the status bar
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// Percent of the width changed by a key to resize the list
+const LIST_RESIZE_STEP: u16 = 5;
+/// Two clicks on the same item within this duration make a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+/// Suggested path of the archive exported from the command palette
+const DEFAULT_EXPORT_PATH: &str = "kakisute.tar.gz";
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
 pub enum Mode {
     Normal,
     Insert,
     DeleteConfirm,
     Search,
+    SelectTemplate,
+    Move,
+    SelectWorkspace,
+    Help,
+    Command,
+    Prompt,
+    /// Editing the content in the content pane
+    Edit,
+}
+
+/// Commands of the palette which need an argument
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Prompt {
+    Rename,
+    Tag,
+    Export,
+}
+
+/// Change made in the app which can be undone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The file is kept in the trash with its metadata here
+    Delete {
+        file_name: String,
+        pinned: bool,
+        tags: Vec<String>,
+    },
+    /// Rename and move
+    Rename { from: String, to: String },
+    Tag {
+        file_name: String,
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+    /// `pinned` is the state after the change
+    Pin { file_name: String, pinned: bool },
+}
+
+impl Change {
+    fn undo(&self, service: &dyn ServiceTrait) -> Result<()> {
+        match self {
+            Change::Delete {
+                file_name,
+                pinned,
+                tags,
+            } => service.restore(file_name, *pinned, tags),
+            Change::Rename { from, to } => service.rename_file(to, from),
+            Change::Tag {
+                file_name, before, ..
+            } => service.set_tags(file_name, before),
+            Change::Pin { file_name, pinned } => service.set_pinned(file_name, !pinned),
+        }
+    }
+
+    fn redo(&self, service: &dyn ServiceTrait) -> Result<()> {
+        match self {
+            Change::Delete { file_name, .. } => service.trash(file_name),
+            Change::Rename { from, to } => service.rename_file(from, to),
+            Change::Tag {
+                file_name, after, ..
+            } => service.set_tags(file_name, after),
+            Change::Pin { file_name, pinned } => service.set_pinned(file_name, *pinned),
+        }
+    }
+
+    /// File to select after undo or redo
+    fn file_name(&self, is_undone: bool) -> &str {
+        match self {
+            Change::Rename { from, .. } if is_undone => from,
+            Change::Rename { to, .. } => to,
+            Change::Delete { file_name, .. }
+            | Change::Tag { file_name, .. }
+            | Change::Pin { file_name, .. } => file_name,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Change::Delete { file_name, .. } => format!("delete {}", file_name),
+            Change::Rename { from, to } => format!("rename {} to {}", from, to),
+            Change::Tag { file_name, .. } => format!("tag {}", file_name),
+            Change::Pin {
+                file_name,
+                pinned: true,
+            } => format!("pin {}", file_name),
+            Change::Pin {
+                file_name,
+                pinned: false,
+            } => format!("unpin {}", file_name),
+        }
+    }
+}
+
+/// Pinned kakisute stay at the top in every order
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SortOrder {
+    /// Grouped by notebook, oldest first. Same as the order of the service.
+    Date,
+    DateDesc,
+    Name,
 }
 
 pub struct AppInteractor<'a> {
     mode: Mode,
     new_filename: TextInput,
     search_query: TextInput,
+    move_destination: TextInput,
     exit: bool,
     service: &'a dyn ServiceTrait,
     filtered_list: FilteredList,
     cached_content: RefCell<HashMap<String, String>>,
+    content_loader: Option<ContentLoader>,
     kakisute_name_list: Vec<String>,
+    pinned_kakisute_name_list: Vec<String>,
+    /// File name to tags
+    kakisute_tags: BTreeMap<String, Vec<String>>,
+    list_columns: Vec<ListColumn>,
+    template_list: Vec<String>,
+    template_index: ListIndex,
+    selected_template: Option<String>,
+    workspace_list: Vec<String>,
+    workspace_index: ListIndex,
+    current_workspace: Option<String>,
+    next_workspace: Option<String>,
+    search_all_workspaces: bool,
+    /// Name and parsed query of the saved search shown as a notebook
+    saved_search: Option<(String, Query)>,
+    /// Why the search query can not be parsed. The list is kept as it was meanwhile.
+    search_error: Option<String>,
+    /// Regex of the text the search matched in contents
+    content_highlight: Option<String>,
+    /// Kakisute of every workspace loaded so far for the cross-workspace search
+    workspace_files: Vec<WorkspaceSearchResult>,
+    workspace_loader: Option<WorkspaceLoader>,
+    workspace_search_results: Vec<WorkspaceSearchResult>,
+    workspace_search_index: ListIndex,
+    /// Query and kakisute to open with next_workspace
+    next_selection: Selection,
+    /// Kakisute to select once its content is loaded and it matches the query
+    pending_selection: Option<String>,
+    status_message: Option<(String, Instant)>,
+    error_message: Option<String>,
+    keymap: Keymap,
+    /// Keys typed so far of a multi-key sequence such as "gg"
+    pending_keys: Vec<Key>,
+    /// Mode whose keybindings are shown in the help overlay
+    help_mode: Mode,
+    help_scroll: u16,
+    command_palette: CommandPalette,
+    prompt: Prompt,
+    prompt_input: TextInput,
+    sort_order: SortOrder,
+    panes: PaneSettings,
+    is_dragging_divider: bool,
+    /// Position in the list and time of the last click, to detect double-clicks
+    last_click: Option<(usize, Instant)>,
+    /// Lines scrolled in the content pane and the kakisute it belongs to
+    content_scroll: (Option<String>, u16),
+    history: History<Change>,
+    /// Files this session moved into the trash. They are removed for good when the app is closed.
+    trashed: BTreeSet<String>,
+    editor: TextEditor,
+    /// Kakisute opened in the editor
+    editing: Option<Editing>,
+}
+
+/// Search query and kakisute to select when the app is reopened with another workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub search_query: String,
+    pub file_name: Option<String>,
+}
+
+struct Editing {
+    file_name: String,
+    /// Content of the file as last seen, to tell changes made outside of the app
+    content: Option<String>,
+    changed_outside: bool,
+    /// Set once the user was told about the changes, so that saving again overwrites them
+    overwrite: bool,
 }
 
 impl<'a> AppInteractor<'a> {
     pub fn new(service: &'a dyn ServiceTrait) -> Self {
         let kakisute_name_list = service.get_kakisute_list();
+        let pinned_kakisute_name_list = service.get_pinned_kakisute_list();
+        // Tags are only shown in the list, so the list is usable without them
+        let kakisute_tags = service.get_kakisute_tags().unwrap_or_default();
         let filtered_list = FilteredList::new(kakisute_name_list.len());
-        AppInteractor {
+        let mut app_interactor = AppInteractor {
             mode: Mode::Normal,
             new_filename: TextInput::new(),
             search_query: TextInput::new(),
+            move_destination: TextInput::new(),
             exit: false,
             service,
             filtered_list,
             kakisute_name_list,
+            pinned_kakisute_name_list,
+            kakisute_tags,
+            list_columns: DEFAULT_LIST_COLUMNS.to_vec(),
             cached_content: RefCell::new(HashMap::new()),
+            content_loader: None,
+            template_list: vec![],
+            template_index: ListIndex::new(0),
+            selected_template: None,
+            workspace_list: vec![],
+            workspace_index: ListIndex::new(0),
+            current_workspace: None,
+            next_workspace: None,
+            search_all_workspaces: false,
+            saved_search: None,
+            search_error: None,
+            content_highlight: None,
+            workspace_files: vec![],
+            workspace_loader: None,
+            workspace_search_results: vec![],
+            workspace_search_index: ListIndex::new(0),
+            next_selection: Selection::default(),
+            pending_selection: None,
+            status_message: None,
+            error_message: None,
+            keymap: Keymap::default(),
+            pending_keys: vec![],
+            help_mode: Mode::Normal,
+            help_scroll: 0,
+            command_palette: CommandPalette::new(),
+            prompt: Prompt::Rename,
+            prompt_input: TextInput::new(),
+            sort_order: SortOrder::Date,
+            panes: PaneSettings::default(),
+            is_dragging_divider: false,
+            last_click: None,
+            content_scroll: (None, 0),
+            history: History::new(),
+            trashed: BTreeSet::new(),
+            editor: TextEditor::new(),
+            editing: None,
+        };
+        app_interactor.start_loading_content();
+        app_interactor
+    }
+
+    pub fn set_list_columns(&mut self, list_columns: Vec<ListColumn>) {
+        self.list_columns = list_columns;
+    }
+
+    /// Searches of the previous sessions are recalled with Up in the search box
+    pub fn load_search_history(&self) -> Result<()> {
+        self.search_query
+            .set_history(self.service.get_search_history()?);
+        Ok(())
+    }
+
+    /// Keep the query in the search history of the data directory
+    pub fn record_search(&self) -> Result<()> {
+        let query = self.search_query.get();
+        Query::parse(&query)?;
+        self.service.add_search_history(&query)
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.pending_keys.clear();
+    }
+
+    /// Returns the action when the key completes a sequence in the current mode
+    pub fn resolve_key(&mut self, key: Key) -> Option<Action> {
+        self.pending_keys.push(key);
+        match self.keymap.resolve(self.mode, &self.pending_keys) {
+            KeyResolution::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            KeyResolution::Pending => None,
+            KeyResolution::Unbound => {
+                // The key may start another sequence after an unfinished one
+                let had_pending_keys = self.pending_keys.len() > 1;
+                self.pending_keys.clear();
+                if had_pending_keys {
+                    self.resolve_key(key)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn has_pending_keys(&self) -> bool {
+        !self.pending_keys.is_empty()
+    }
+
+    pub fn set_workspaces(&mut self, workspace_list: Vec<String>, current: Option<String>) {
+        self.workspace_list = workspace_list;
+        self.current_workspace = current;
+    }
+
+    /// Load contents which are not cached yet in the background. A running load is abandoned.
+    fn start_loading_content(&mut self) {
+        let files = {
+            let cached_content = self.cached_content.borrow();
+            self.kakisute_name_list
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| !cached_content.contains_key(*name))
+                .filter_map(|(index, name)| {
+                    let path = self.service.get_path_by_index(index).ok()?;
+                    Some((name.clone(), path))
+                })
+                .collect::<Vec<(String, String)>>()
+        };
+        self.content_loader = if files.is_empty() {
+            None
+        } else {
+            Some(ContentLoader::start(files))
+        };
+    }
+
+    /// Called periodically by the event loop. Search results are updated as contents are loaded.
+    pub fn tick(&mut self) -> Result<()> {
+        if let Some((_, shown_at)) = &self.status_message {
+            if shown_at.elapsed() >= STATUS_MESSAGE_DURATION {
+                self.status_message = None;
+            }
+        }
+        self.poll_content_loader()?;
+        self.poll_workspace_loader()?;
+        self.apply_pending_selection();
+        Ok(())
+    }
+
+    fn poll_content_loader(&mut self) -> Result<()> {
+        let loader = match &mut self.content_loader {
+            Some(loader) => loader,
+            None => return Ok(()),
+        };
+        let loaded = loader.poll();
+        if loader.is_done() {
+            self.content_loader = None;
+        }
+        if loaded.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut cached_content = self.cached_content.borrow_mut();
+            for (name, content) in loaded {
+                if let Some(content) = content {
+                    cached_content.entry(name).or_insert(content);
+                }
+            }
+        }
+        if self.is_filtered() {
+            let selected = self.get_selected_kakisute_name();
+            self.filter()?;
+            if let Some(selected) = selected {
+                self.select_kakisute_name(&selected);
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_workspace_loader(&mut self) -> Result<()> {
+        let loader = match &mut self.workspace_loader {
+            Some(loader) => loader,
+            None => return Ok(()),
+        };
+        let loaded = loader.poll();
+        if loader.is_done() {
+            self.workspace_loader = None;
+        }
+        if loaded.is_empty() {
+            return Ok(());
+        }
+        let mut skipped = vec![];
+        for workspace in loaded {
+            match workspace {
+                Ok(files) => self.workspace_files.extend(files),
+                Err(reason) => skipped.push(reason),
+            }
+        }
+        if !skipped.is_empty() {
+            self.show_status(format!("Skipped workspace {}", skipped.join(", ")));
+        }
+        let selected = self
+            .get_selected_workspace_search_result()
+            .map(|result| (result.workspace.clone(), result.file_name.clone()));
+        self.filter()?;
+        if let Some(position) = selected.and_then(|(workspace, file_name)| {
+            self.workspace_search_results
+                .iter()
+                .position(|result| result.workspace == workspace && result.file_name == file_name)
+        }) {
+            self.workspace_search_index.increment_n(position as u16);
+        }
+        Ok(())
+    }
+
+    /// Set the search query and select the kakisute, as they were in the previous workspace
+    pub fn restore_selection(&mut self, selection: Selection) -> Result<()> {
+        self.search_query.set(&selection.search_query);
+        self.filter()?;
+        self.pending_selection = selection.file_name;
+        self.apply_pending_selection();
+        Ok(())
+    }
+
+    /// The kakisute may be filtered out until its content is loaded, so it is tried on every tick
+    fn apply_pending_selection(&mut self) {
+        let file_name = match &self.pending_selection {
+            Some(file_name) => file_name.clone(),
+            None => return,
+        };
+        self.select_kakisute_name(&file_name);
+        if self.get_selected_kakisute_name().as_ref() == Some(&file_name)
+            || self.content_loader.is_none()
+        {
+            self.pending_selection = None;
+        }
+    }
+
+    /// Show message in the status bar for a while
+    pub fn show_status(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// Show error in a modal until any key is pressed
+    pub fn show_error(&mut self, error: &anyhow::Error) {
+        self.error_message = Some(format!("{:#}", error));
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error_message.is_some()
+    }
+
+    pub fn dismiss_error(&mut self) {
+        self.error_message = None;
+    }
+
+    pub fn is_pinned(&self, file_name: &str) -> bool {
+        self.pinned_kakisute_name_list
+            .iter()
+            .any(|pinned| pinned == file_name)
+    }
+
+    /// (loaded, total) while contents are loaded in the background
+    pub fn get_loading_progress(&self) -> Option<(usize, usize)> {
+        if self.search_all_workspaces {
+            return self
+                .workspace_loader
+                .as_ref()
+                .map(|loader| loader.progress());
         }
+        self.content_loader.as_ref().map(|loader| loader.progress())
+    }
+
+    fn get_selected_kakisute_name(&self) -> Option<String> {
+        self.filtered_list
+            .get_original_index()
+            .ok()
+            .and_then(|index| self.kakisute_name_list.get(index).cloned())
     }
 
-    fn load_all_kakisute(&self) {
-        for i in 0..self.kakisute_name_list.len() {
-            self.load_kakisute_content(i);
+    fn select_kakisute_name(&mut self, name: &str) {
+        if let Some(index) = self
+            .kakisute_name_list
+            .iter()
+            .position(|kakisute_name| kakisute_name == name)
+        {
+            self.filtered_list.select_original_index(index);
         }
     }
 
@@ -52,30 +513,481 @@ impl<'a> AppInteractor<'a> {
         Ok(match self.mode {
             Mode::Insert => &self.new_filename,
             Mode::Search => &self.search_query,
+            Mode::Move => &self.move_destination,
+            Mode::Command => self.command_palette.query(),
+            Mode::Prompt => &self.prompt_input,
             _ => return Err(anyhow::anyhow!("")),
         })
     }
 
     pub fn reload(&mut self) -> Result<()> {
-        self.service.reload();
+        self.service.reload()?;
         self.kakisute_name_list = self.service.get_kakisute_list();
+        self.pinned_kakisute_name_list = self.service.get_pinned_kakisute_list();
+        self.kakisute_tags = self.service.get_kakisute_tags()?;
         self.filtered_list = FilteredList::new(self.kakisute_name_list.len());
+        self.sort_list();
         self.new_filename.clear();
         self.search_query.clear();
+        self.move_destination.clear();
+        self.selected_template = None;
+        self.set_search_all_workspaces(false);
         self.mode = Mode::Normal;
         self.cached_content = RefCell::new(HashMap::new());
+        self.search_error = None;
+        self.content_highlight = None;
+        if self.saved_search.is_some() {
+            self.filter()?;
+        }
+        self.start_loading_content();
         Ok(())
     }
 
+    /// The list changes as contents are loaded
+    fn is_filtered(&self) -> bool {
+        !self.search_all_workspaces
+            && (!self.search_query.get().is_empty() || self.saved_search.is_some())
+    }
+
+    /// Apply changes made outside of the app, keeping the mode, the inputs and the selection
+    pub fn apply_file_changes(&mut self, file_names: &[String]) -> Result<()> {
+        {
+            let mut cached_content = self.cached_content.borrow_mut();
+            for file_name in file_names {
+                cached_content.remove(file_name);
+            }
+        }
+        let selected = self.get_selected_kakisute_name();
+
+        self.service.reload()?;
+        self.kakisute_name_list = self.service.get_kakisute_list();
+        self.pinned_kakisute_name_list = self.service.get_pinned_kakisute_list();
+        self.kakisute_tags = self.service.get_kakisute_tags()?;
+        self.filtered_list = FilteredList::new(self.kakisute_name_list.len());
+        if self.is_filtered() {
+            self.filter()?;
+        } else {
+            self.sort_list();
+        }
+        if let Some(selected) = selected {
+            self.select_kakisute_name(&selected);
+        }
+        self.start_loading_content();
+
+        let edited_changed = self
+            .editing
+            .as_ref()
+            .is_some_and(|editing| file_names.contains(&editing.file_name));
+        if edited_changed && self.check_changed_outside() {
+            let file_name = self.editing.as_ref().unwrap().file_name.clone();
+            self.show_status(format!("{} was changed outside of the app", file_name));
+        }
+        Ok(())
+    }
+
+    fn read_content(&self, file_name: &str) -> Option<String> {
+        let index = self
+            .kakisute_name_list
+            .iter()
+            .position(|name| name == file_name)?;
+        self.service.get_content_by_index(index).ok()
+    }
+
+    /// Returns true if the edited file differs from what was last seen
+    fn check_changed_outside(&mut self) -> bool {
+        let content = match &self.editing {
+            Some(editing) => self.read_content(&editing.file_name),
+            None => return false,
+        };
+        let editing = self.editing.as_mut().unwrap();
+        if content == editing.content {
+            return false;
+        }
+        editing.content = content;
+        editing.changed_outside = true;
+        editing.overwrite = false;
+        true
+    }
+
     pub fn filter(&mut self) -> Result<()> {
-        self.load_all_kakisute();
-        self.filtered_list.filter(
-            &self.search_query.get(),
-            self.kakisute_name_list
+        let query = match Query::parse(&self.search_query.get()) {
+            Ok(query) => query,
+            Err(err) => {
+                self.search_error = Some(err.to_string());
+                return Ok(());
+            }
+        };
+        self.search_error = None;
+        if self.search_all_workspaces {
+            // Workspaces not loaded yet are added by tick as the loader catches up
+            let results = self
+                .workspace_files
                 .iter()
-                .map(|s| s.to_owned() + self.cached_content.borrow().get(s).unwrap().as_str())
-                .collect::<Vec<String>>(),
-        )
+                .filter(|file| {
+                    query.matches(&Target {
+                        name: &file.file_name,
+                        content: Some(&file.content),
+                        tags: &file.tags,
+                        created_at: created_at(&file.file_name),
+                    })
+                })
+                .cloned()
+                .collect();
+            self.set_workspace_search_results(results);
+            self.content_highlight = query.highlight_regex();
+            return Ok(());
+        }
+        let saved_query = match &self.saved_search {
+            Some((_, saved_query)) => saved_query.clone(),
+            None => Query::All,
+        };
+        // Contents not loaded yet are matched by name only until the loader catches up
+        let cached_content = self.cached_content.borrow();
+        let names = &self.kakisute_name_list;
+        let tags = &self.kakisute_tags;
+        self.filtered_list.filter(names.len(), |index| {
+            let name = &names[index];
+            let target = Target {
+                name,
+                content: cached_content.get(name).map(|content| content.as_str()),
+                tags: tags.get(name).map_or(&[], |tags| tags.as_slice()),
+                created_at: created_at(name),
+            };
+            query.matches(&target) && saved_query.matches(&target)
+        });
+        drop(cached_content);
+        self.content_highlight = match (query.highlight_regex(), saved_query.highlight_regex()) {
+            (Some(typed), Some(saved)) => Some(format!("{}|{}", typed, saved)),
+            (typed, saved) => typed.or(saved),
+        };
+        self.sort_list();
+        Ok(())
+    }
+
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sort_order = sort_order;
+        self.sort_list();
+    }
+
+    fn sort_list(&mut self) {
+        let names = &self.kakisute_name_list;
+        let pinned = &self.pinned_kakisute_name_list;
+        let is_unpinned = |index: usize| !pinned.contains(&names[index]);
+        match self.sort_order {
+            SortOrder::Date => self.filtered_list.sort_by_key(|index| index),
+            SortOrder::DateDesc => self.filtered_list.sort_by_key(|index| {
+                let (_, file_name) = notebook::split(&names[index]);
+                (is_unpinned(index), Reverse(file_name))
+            }),
+            SortOrder::Name => self.filtered_list.sort_by_key(|index| {
+                let (_, file_name) = notebook::split(&names[index]);
+                (
+                    is_unpinned(index),
+                    strip_datetime(file_name).to_lowercase(),
+                    index,
+                )
+            }),
+        }
+    }
+
+    /// Select by the position in the shown list. Returns true on a double-click.
+    pub fn click_list_item(&mut self, position: usize) -> bool {
+        let list_size = if self.search_all_workspaces {
+            self.workspace_search_results.len()
+        } else {
+            self.generate_list_entries().len()
+        };
+        if position >= list_size {
+            return false;
+        }
+        let is_double_click = matches!(
+            self.last_click,
+            Some((last_position, clicked_at))
+                if last_position == position && clicked_at.elapsed() <= DOUBLE_CLICK_INTERVAL
+        );
+        self.last_click = if is_double_click {
+            None
+        } else {
+            Some((position, Instant::now()))
+        };
+        if self.search_all_workspaces {
+            self.workspace_search_index = ListIndex::new(list_size);
+            self.workspace_search_index.increment_n(position as u16);
+        } else {
+            self.filtered_list.select(position);
+        }
+        is_double_click
+    }
+
+    pub fn get_list_index(&self) -> Option<usize> {
+        if self.search_all_workspaces {
+            self.workspace_search_index.get_index().ok()
+        } else {
+            self.filtered_list.get_index().ok()
+        }
+    }
+
+    pub fn need_search_box(&self) -> bool {
+        !self.search_query.get().is_empty() || self.mode == Mode::Search
+    }
+
+    pub fn get_panes(&self) -> PaneSettings {
+        self.panes
+    }
+
+    pub fn set_list_width_percent(&mut self, percent: u16) {
+        self.panes.list_width_percent =
+            percent.clamp(MIN_LIST_WIDTH_PERCENT, MAX_LIST_WIDTH_PERCENT);
+    }
+
+    pub fn grow_list(&mut self) {
+        self.set_list_width_percent(self.panes.list_width_percent + LIST_RESIZE_STEP);
+    }
+
+    pub fn shrink_list(&mut self) {
+        self.set_list_width_percent(
+            self.panes
+                .list_width_percent
+                .saturating_sub(LIST_RESIZE_STEP),
+        );
+    }
+
+    /// Hiding the preview also leaves the zoom
+    pub fn toggle_preview(&mut self) {
+        self.panes.show_preview = !self.panes.show_preview;
+        self.panes.zoom_preview = false;
+    }
+
+    /// Zooming shows the preview if it is hidden
+    pub fn toggle_zoom_preview(&mut self) {
+        self.panes.zoom_preview = !self.panes.zoom_preview;
+        self.panes.show_preview = true;
+    }
+
+    pub fn start_dragging_divider(&mut self) {
+        self.is_dragging_divider = true;
+    }
+
+    pub fn stop_dragging_divider(&mut self) {
+        self.is_dragging_divider = false;
+    }
+
+    pub fn is_dragging_divider(&self) -> bool {
+        self.is_dragging_divider
+    }
+
+    /// Scrolled lines are reset when another kakisute is selected
+    fn get_content_scroll(&self) -> u16 {
+        match &self.content_scroll {
+            (name, scroll) if *name == self.get_selected_content_key() => *scroll,
+            _ => 0,
+        }
+    }
+
+    /// Identifies the content shown in the content pane
+    fn get_selected_content_key(&self) -> Option<String> {
+        if self.search_all_workspaces {
+            self.get_selected_workspace_search_result()
+                .map(|result| format!("{}:{}", result.workspace, result.file_name))
+        } else {
+            self.get_selected_kakisute_name()
+        }
+    }
+
+    /// The last line stays at the top at most
+    pub fn scroll_content_down(&mut self, lines: u16) {
+        let line_count = self
+            .get_selected_kakisute_content()
+            .map_or(0, |content| content.lines().count());
+        let max_scroll = line_count.saturating_sub(1).min(u16::MAX as usize) as u16;
+        let scroll = self
+            .get_content_scroll()
+            .saturating_add(lines)
+            .min(max_scroll);
+        self.content_scroll = (self.get_selected_content_key(), scroll);
+    }
+
+    pub fn scroll_content_up(&mut self, lines: u16) {
+        let scroll = self.get_content_scroll().saturating_sub(lines);
+        self.content_scroll = (self.get_selected_content_key(), scroll);
+    }
+
+    /// Show the keybindings of the current mode
+    pub fn open_help(&mut self) {
+        self.help_mode = self.mode;
+        self.help_scroll = 0;
+        self.enter_mode(Mode::Help);
+    }
+
+    pub fn close_help(&mut self) {
+        self.enter_mode(self.help_mode);
+    }
+
+    pub fn scroll_help_down(&mut self) {
+        let last_line = self.keymap.entries(self.help_mode).len().saturating_sub(1);
+        self.help_scroll = (self.help_scroll + 1).min(last_line as u16);
+    }
+
+    pub fn scroll_help_up(&mut self) {
+        self.help_scroll = self.help_scroll.saturating_sub(1);
+    }
+
+    /// Every action of Normal mode can be run from the palette
+    pub fn open_command_palette(&mut self) {
+        let entries = self
+            .keymap
+            .entries(Mode::Normal)
+            .into_iter()
+            .filter(|entry| !matches!(entry.action, Action::Help | Action::CommandPalette))
+            .collect();
+        self.command_palette.open(entries);
+        self.enter_mode(Mode::Command);
+    }
+
+    pub fn filter_commands(&mut self) {
+        self.command_palette.update();
+    }
+
+    pub fn select_next_command(&mut self) {
+        self.command_palette.select_next();
+    }
+
+    pub fn select_previous_command(&mut self) {
+        self.command_palette.select_previous();
+    }
+
+    /// Leave the palette and returns the action to run in Normal mode
+    pub fn decide_command(&mut self) -> Option<Action> {
+        let action = self.command_palette.get_selected_action();
+        self.enter_mode(Mode::Normal);
+        action
+    }
+
+    /// Ask the argument of the command. The input starts with the current value if any.
+    pub fn start_prompt(&mut self, prompt: Prompt) {
+        let value = match prompt {
+            Prompt::Rename => self
+                .get_selected_kakisute_name()
+                .map(|name| {
+                    let (_, file_name) = notebook::split(&name);
+                    // Default names such as ".txt" are not worth keeping
+                    let name = strip_datetime(file_name);
+                    if name.starts_with('.') {
+                        String::new()
+                    } else {
+                        name.to_string()
+                    }
+                })
+                .unwrap_or_default(),
+            Prompt::Tag => String::new(),
+            Prompt::Export => DEFAULT_EXPORT_PATH.to_string(),
+        };
+        self.prompt = prompt;
+        self.prompt_input.set(&value);
+        self.enter_mode(Mode::Prompt);
+    }
+
+    /// Returns the message to show in the status bar
+    pub fn run_prompt(&mut self) -> Result<String> {
+        let input = self.prompt_input.get();
+        match self.prompt {
+            Prompt::Rename => {
+                let (index, from) = self.get_selected_index_and_name()?;
+                let file_name = self.service.rename_by_index(index, input.trim())?;
+                self.record_rename(from, &file_name);
+                Ok(format!("Renamed: {}", file_name))
+            }
+            Prompt::Tag => {
+                let (index, _) = self.get_selected_index_and_name()?;
+                let (add, remove) = parse_tags(&input);
+                let (file_name, tags) = self.service.tag_by_index(index, &add, &remove)?;
+                let before = self
+                    .kakisute_tags
+                    .get(&file_name)
+                    .cloned()
+                    .unwrap_or_default();
+                if before != tags {
+                    self.history.record(Change::Tag {
+                        file_name: file_name.clone(),
+                        before,
+                        after: tags.clone(),
+                    });
+                }
+                Ok(format!("Tags of {}: {}", file_name, tags.join(", ")))
+            }
+            Prompt::Export => {
+                let report = self.service.export_archive_to_file(input.trim())?;
+                let mut message =
+                    format!("Exported {} kakisute to {}", report.exported, input.trim());
+                if !report.skipped.is_empty() {
+                    let skipped = report
+                        .skipped
+                        .iter()
+                        .map(|(file_name, _)| file_name.as_str())
+                        .collect::<Vec<&str>>();
+                    message.push_str(&format!(", skipped {}", skipped.join(", ")));
+                }
+                Ok(message)
+            }
+        }
+    }
+
+    pub fn toggle_search_all_workspaces(&mut self) -> Result<()> {
+        self.set_search_all_workspaces(!self.search_all_workspaces);
+        self.filter()
+    }
+
+    pub fn is_searching_all_workspaces(&self) -> bool {
+        self.search_all_workspaces
+    }
+
+    /// Workspaces are loaded in the background when turned on, and forgotten when turned off
+    fn set_search_all_workspaces(&mut self, search_all_workspaces: bool) {
+        if search_all_workspaces && !self.search_all_workspaces {
+            self.workspace_loader = Some(WorkspaceLoader::start(self.service.get_workspaces()));
+        }
+        self.search_all_workspaces = search_all_workspaces;
+        if !search_all_workspaces {
+            self.workspace_loader = None;
+            self.workspace_files = vec![];
+            self.set_workspace_search_results(vec![]);
+        }
+    }
+
+    fn set_workspace_search_results(&mut self, results: Vec<WorkspaceSearchResult>) {
+        self.workspace_search_index = ListIndex::new(results.len());
+        self.workspace_search_results = results;
+    }
+
+    fn get_selected_workspace_search_result(&self) -> Option<&WorkspaceSearchResult> {
+        let index = self.workspace_search_index.get_index().ok()?;
+        self.workspace_search_results.get(index)
+    }
+
+    /// Reopen the app with the workspace of the selected result.
+    /// When it is in the current workspace, the query filters the current list instead.
+    pub fn open_workspace_search_result(&mut self) -> Result<()> {
+        let selected = self
+            .get_selected_workspace_search_result()
+            .map(|result| (result.workspace.clone(), result.file_name.clone()));
+        self.set_search_all_workspaces(false);
+        self.enter_mode(Mode::Normal);
+        match selected {
+            Some((workspace, file_name)) if Some(&workspace) != self.current_workspace.as_ref() => {
+                self.next_workspace = Some(workspace);
+                self.next_selection = Selection {
+                    search_query: self.search_query.get(),
+                    file_name: Some(file_name),
+                };
+                self.exit();
+                Ok(())
+            }
+            Some((_, file_name)) => self.restore_selection(Selection {
+                search_query: self.search_query.get(),
+                file_name: Some(file_name),
+            }),
+            None => self.filter(),
+        }
     }
 
     pub fn enter_mode(&mut self, mode: Mode) {
@@ -83,10 +995,18 @@ impl<'a> AppInteractor<'a> {
     }
 
     pub fn select_next(&mut self) {
-        self.filtered_list.select_next();
+        if self.search_all_workspaces {
+            self.workspace_search_index.increment();
+        } else {
+            self.filtered_list.select_next();
+        }
     }
     pub fn select_previous(&mut self) {
-        self.filtered_list.select_previous();
+        if self.search_all_workspaces {
+            self.workspace_search_index.decrement();
+        } else {
+            self.filtered_list.select_previous();
+        }
     }
     pub fn select_next_n(&mut self, n: u16) {
         self.filtered_list.select_next_n(n);
@@ -94,6 +1014,12 @@ impl<'a> AppInteractor<'a> {
     pub fn select_previous_n(&mut self, n: u16) {
         self.filtered_list.select_previous_n(n);
     }
+    pub fn select_first(&mut self) {
+        self.filtered_list.select_previous_n(u16::MAX);
+    }
+    pub fn select_last(&mut self) {
+        self.filtered_list.select_next_n(u16::MAX);
+    }
 
     pub fn clear_text_input(&self) {
         let text_input = self.get_current_text_input().unwrap();
@@ -101,6 +1027,11 @@ impl<'a> AppInteractor<'a> {
     }
 
     pub fn get_selected_kakisute_content(&self) -> Option<String> {
+        if self.search_all_workspaces {
+            return self
+                .get_selected_workspace_search_result()
+                .map(|result| result.content.clone());
+        }
         let index = self.filtered_list.get_original_index().ok()?;
         self.load_kakisute_content(index)
     }
@@ -132,21 +1063,281 @@ impl<'a> AppInteractor<'a> {
             .and_then(|index| self.service.edit_by_index(index))
     }
 
-    pub fn create_new_kakisute_with_file_name(&self) -> Result<()> {
-        self.service
-            .create_kakisute(Some(&self.new_filename.get()))?;
+    /// Show template picker before asking file name if there is any template
+    pub fn start_creating_with_file_name(&mut self) -> Result<()> {
+        self.template_list = self.service.get_template_list()?;
+        self.selected_template = None;
+        if self.template_list.is_empty() {
+            self.enter_mode(Mode::Insert);
+        } else {
+            // The first item stands for creating without template
+            self.template_index = ListIndex::new(self.template_list.len() + 1);
+            self.enter_mode(Mode::SelectTemplate);
+        }
         Ok(())
     }
 
-    pub fn create_new_kakisute(&self) -> Result<()> {
-        self.service.create_kakisute(None)?;
+    pub fn select_next_template(&mut self) {
+        self.template_index.increment();
+    }
+
+    pub fn select_previous_template(&mut self) {
+        self.template_index.decrement();
+    }
+
+    pub fn decide_template(&mut self) {
+        self.selected_template = match self.template_index.get_index() {
+            Ok(index) if index > 0 => self.template_list.get(index - 1).cloned(),
+            _ => None,
+        };
+        self.enter_mode(Mode::Insert);
+    }
+
+    pub fn start_switching_workspace(&mut self) {
+        if self.workspace_list.is_empty() {
+            return;
+        }
+        self.workspace_index = ListIndex::new(self.workspace_list.len());
+        if let Some(position) = self
+            .workspace_list
+            .iter()
+            .position(|workspace| Some(workspace) == self.current_workspace.as_ref())
+        {
+            self.workspace_index.increment_n(position as u16);
+        }
+        self.enter_mode(Mode::SelectWorkspace);
+    }
+
+    pub fn select_next_workspace(&mut self) {
+        self.workspace_index.increment();
+    }
+
+    pub fn select_previous_workspace(&mut self) {
+        self.workspace_index.decrement();
+    }
+
+    /// Exit to let the caller reopen the app with the selected workspace
+    pub fn decide_workspace(&mut self) {
+        let selected = self
+            .workspace_index
+            .get_index()
+            .ok()
+            .and_then(|index| self.workspace_list.get(index).cloned());
+        self.enter_mode(Mode::Normal);
+        if selected.is_some() && selected != self.current_workspace {
+            self.next_workspace = selected;
+            self.exit();
+        }
+    }
+
+    pub fn get_next_workspace(&self) -> Option<&str> {
+        self.next_workspace.as_deref()
+    }
+
+    pub fn get_next_selection(&self) -> Selection {
+        self.next_selection.clone()
+    }
+
+    pub fn create_new_kakisute_with_file_name(&self) -> Result<String> {
+        let new_filename = self.new_filename.get();
+        let file_name = Some(new_filename.as_str()).filter(|name| !name.is_empty());
+        if let Some(template) = &self.selected_template {
+            self.service
+                .create_kakisute_from_template(file_name, template)
+        } else {
+            self.service.create_kakisute(file_name)
+        }
+    }
+
+    pub fn create_new_kakisute(&self) -> Result<String> {
+        self.service.create_kakisute(None)
+    }
+
+    /// The file is moved into the trash so that the deletion can be undone
+    pub fn delete_kakisute(&mut self) -> Result<String> {
+        let (_, file_name) = self.get_selected_index_and_name()?;
+        self.service.trash(&file_name)?;
+        self.trashed.insert(file_name.clone());
+        self.history.record(Change::Delete {
+            file_name: file_name.clone(),
+            pinned: self.is_pinned(&file_name),
+            tags: self
+                .kakisute_tags
+                .get(&file_name)
+                .cloned()
+                .unwrap_or_default(),
+        });
+        Ok(file_name)
+    }
+
+    pub fn toggle_pin_kakisute(&mut self) -> Result<String> {
+        let (index, file_name) = self.get_selected_index_and_name()?;
+        self.service.toggle_pin_by_index(index)?;
+        self.history.record(Change::Pin {
+            file_name: file_name.clone(),
+            pinned: !self.is_pinned(&file_name),
+        });
+        Ok(file_name)
+    }
+
+    pub fn move_kakisute(&mut self) -> Result<String> {
+        let (index, from) = self.get_selected_index_and_name()?;
+        let file_name = self
+            .service
+            .move_by_index(index, &self.move_destination.get())?;
+        self.record_rename(from, &file_name);
+        Ok(file_name)
+    }
+
+    fn get_selected_index_and_name(&self) -> Result<(usize, String)> {
+        let index = self.filtered_list.get_original_index()?;
+        let file_name = self
+            .kakisute_name_list
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("File not found"))?;
+        Ok((index, file_name))
+    }
+
+    fn record_rename(&mut self, from: String, to: &str) {
+        if from != to {
+            self.history.record(Change::Rename {
+                from,
+                to: to.to_string(),
+            });
+        }
+    }
+
+    /// Returns the message to show in the status bar
+    pub fn undo(&mut self) -> Result<String> {
+        let service = self.service;
+        match self.history.undo(|change| change.undo(service))?.cloned() {
+            Some(change) => {
+                if let Change::Delete { file_name, .. } = &change {
+                    self.trashed.remove(file_name);
+                }
+                self.reload()?;
+                self.select_kakisute_name(change.file_name(true));
+                Ok(format!("Undone: {}", change.describe()))
+            }
+            None => Ok("Nothing to undo".to_string()),
+        }
+    }
+
+    /// Returns the message to show in the status bar
+    pub fn redo(&mut self) -> Result<String> {
+        let service = self.service;
+        match self.history.redo(|change| change.redo(service))?.cloned() {
+            Some(change) => {
+                if let Change::Delete { file_name, .. } = &change {
+                    self.trashed.insert(file_name.clone());
+                }
+                self.reload()?;
+                self.select_kakisute_name(change.file_name(false));
+                Ok(format!("Redone: {}", change.describe()))
+            }
+            None => Ok("Nothing to redo".to_string()),
+        }
+    }
+
+    /// Edit the selected kakisute in the content pane
+    pub fn start_quick_edit(&mut self) -> Result<()> {
+        let (index, file_name) = self.get_selected_index_and_name()?;
+        let content = self.service.get_content_by_index(index)?;
+        self.editor.open(&content);
+        self.editing = Some(Editing {
+            file_name,
+            content: Some(content),
+            changed_outside: false,
+            overwrite: false,
+        });
+        self.panes.show_preview = true;
+        self.enter_mode(Mode::Edit);
         Ok(())
     }
 
-    pub fn delete_kakisute(&self) -> Result<String> {
-        self.filtered_list
-            .get_original_index()
-            .and_then(|index| self.service.delete_by_index(index))
+    /// Returns the saved file name, or None if the file was changed outside of the app.
+    /// Saving again overwrites the changes.
+    pub fn save_quick_edit(&mut self) -> Result<Option<String>> {
+        self.check_changed_outside();
+        let editing = self
+            .editing
+            .as_mut()
+            .ok_or_else(|| anyhow!("No file is being edited"))?;
+        if editing.changed_outside && !editing.overwrite {
+            editing.overwrite = true;
+            return Ok(None);
+        }
+        let file_name = editing.file_name.clone();
+        let index = self
+            .kakisute_name_list
+            .iter()
+            .position(|name| *name == file_name)
+            .ok_or_else(|| anyhow!("File not found: {}", file_name))?;
+        self.service
+            .update_content_by_index(index, &self.editor.get())?;
+        self.editing = None;
+        self.enter_mode(Mode::Normal);
+        self.apply_file_changes(std::slice::from_ref(&file_name))?;
+        Ok(Some(file_name))
+    }
+
+    /// Returns true if there were changes to discard
+    pub fn cancel_quick_edit(&mut self) -> bool {
+        self.editing = None;
+        self.enter_mode(Mode::Normal);
+        self.editor.is_modified()
+    }
+
+    pub fn editor_mut(&mut self) -> &mut TextEditor {
+        &mut self.editor
+    }
+
+    /// Cycle through all notebooks -> top level -> each notebook -> each saved search
+    pub fn switch_to_next_notebook(&mut self) -> Result<()> {
+        let mut scopes: Vec<Option<Option<String>>> = vec![None, Some(None)];
+        scopes.extend(
+            self.service
+                .get_notebook_list()?
+                .into_iter()
+                .map(|notebook| Some(Some(notebook))),
+        );
+        // Searches saved before the query language may not parse. They are skipped rather than failing on every key.
+        let mut saved_searches = vec![];
+        let mut invalid = vec![];
+        for (name, saved_query) in self.service.get_saved_searches()? {
+            match Query::parse(&saved_query) {
+                Ok(query) => saved_searches.push((name, query)),
+                Err(err) => invalid.push(format!("{} ({})", name, err)),
+            }
+        }
+        if !invalid.is_empty() {
+            self.show_status(format!("Skipped saved search {}", invalid.join(", ")));
+        }
+        let current = match &self.saved_search {
+            Some((name, _)) => saved_searches
+                .iter()
+                .position(|(saved, _)| saved == name)
+                .map(|position| scopes.len() + position),
+            None => {
+                let current = self.service.get_notebook();
+                scopes.iter().position(|scope| scope == &current)
+            }
+        };
+        let next = current
+            .map(|position| (position + 1) % (scopes.len() + saved_searches.len()))
+            .unwrap_or(0);
+        // Saved searches are searched in all notebooks
+        match scopes.get(next) {
+            Some(None) | None => self.service.switch_notebook(None)?,
+            Some(Some(notebook)) => self
+                .service
+                .switch_notebook(Some(notebook.as_deref().unwrap_or_default()))?,
+        }
+        self.saved_search = next
+            .checked_sub(scopes.len())
+            .map(|position| saved_searches[position].clone());
+        self.reload()
     }
 
     pub fn is_kakisute_selected(&self) -> bool {
@@ -162,9 +1353,49 @@ impl<'a> AppInteractor<'a> {
         text_input.push(c);
     }
 
-    pub fn pop_text_input(&self) {
-        let text_input = self.get_current_text_input().unwrap();
-        text_input.pop();
+    /// Returns false if the action does not edit the input of the mode
+    pub fn edit_text_input(&self, action: Action) -> bool {
+        let text_input = match self.get_current_text_input() {
+            Ok(text_input) => text_input,
+            Err(_) => return false,
+        };
+        match action {
+            Action::DeleteChar => {
+                text_input.pop();
+            }
+            Action::DeleteNextChar => {
+                text_input.delete_next();
+            }
+            Action::Left => text_input.move_left(),
+            Action::Right => text_input.move_right(),
+            Action::LineStart => text_input.move_to_start(),
+            Action::LineEnd => text_input.move_to_end(),
+            Action::WordLeft => text_input.move_word_left(),
+            Action::WordRight => text_input.move_word_right(),
+            Action::DeleteWord => text_input.delete_word(),
+            Action::DeleteToStart => text_input.delete_to_start(),
+            Action::HistoryPrevious => {
+                text_input.previous_history();
+            }
+            Action::HistoryNext => {
+                text_input.next_history();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn paste_text_input(&self, text: &str) {
+        if let Ok(text_input) = self.get_current_text_input() {
+            text_input.paste(text);
+        }
+    }
+
+    /// Keep the decided input so that it can be recalled with the history
+    pub fn remember_text_input(&self) {
+        if let Ok(text_input) = self.get_current_text_input() {
+            text_input.remember();
+        }
     }
 
     pub fn exit(&mut self) {
@@ -175,76 +1406,401 @@ impl<'a> AppInteractor<'a> {
         self.exit
     }
 
-    pub fn generate_info(&mut self) -> Info {
+    pub fn generate_info(&mut self) -> Info<'_> {
         let content = self.get_selected_kakisute_content();
-        let kakisute_name_list = self.filtered_list.get_kakisute_file_name_list(
-            self.kakisute_name_list.iter().map(|s| s.as_str()).collect(),
-        );
+        let kakisute_name_list = if self.search_all_workspaces {
+            self.workspace_search_results
+                .iter()
+                .map(|result| ListEntry {
+                    name: &result.file_name,
+                    workspace: Some(&result.workspace),
+                    pinned: false,
+                    created_at: created_at(&result.file_name),
+                    first_line: first_line(&result.content),
+                    size: Some(result.content.len()),
+                    tags: result.tags.clone(),
+                })
+                .collect()
+        } else {
+            self.generate_list_entries()
+        };
         Info {
-            index: self.filtered_list.get_index().ok(),
+            index: self.get_list_index(),
             mode: self.mode,
             kakisute_list: kakisute_name_list,
+            list_columns: self.list_columns.clone(),
             content,
             new_filename: self.new_filename.get(),
             search_query: self.search_query.get(),
+            search_error: self.search_error.clone(),
+            content_highlight: self.content_highlight.clone(),
+            template_list: self.template_list.clone(),
+            template_index: self.template_index.get_index().ok(),
+            selected_template: self.selected_template.clone(),
+            move_destination: self.move_destination.get(),
+            notebook: self.service.get_notebook(),
+            saved_search: self.saved_search.as_ref().map(|(name, _)| name.clone()),
+            workspace_list: self.workspace_list.clone(),
+            workspace_index: self.workspace_index.get_index().ok(),
+            workspace: self.current_workspace.clone(),
+            search_all_workspaces: self.search_all_workspaces,
+            loading: self.get_loading_progress(),
+            status_message: self
+                .status_message
+                .as_ref()
+                .map(|(message, _)| message.clone()),
+            error_message: self.error_message.clone(),
+            help: self.keymap.help(self.mode),
+            help_entries: if self.mode == Mode::Help {
+                self.keymap.entries(self.help_mode)
+            } else {
+                vec![]
+            },
+            help_scroll: self.help_scroll,
+            command_query: self.command_palette.query().get(),
+            command_list: self
+                .command_palette
+                .get_matched_entries()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<KeymapEntry>>(),
+            command_index: self.command_palette.get_index(),
+            prompt: self.prompt,
+            prompt_input: self.prompt_input.get(),
+            need_search_box: self.need_search_box(),
+            panes: self.panes,
+            content_scroll: self.get_content_scroll(),
+            input_cursor: self
+                .get_current_text_input()
+                .map_or(0, |text_input| text_input.cursor_width()),
+            editing: self
+                .editing
+                .as_ref()
+                .filter(|_| self.mode == Mode::Edit)
+                .map(|editing| editing.file_name.clone()),
+            editor_lines: if self.mode == Mode::Edit {
+                self.editor.get_lines()
+            } else {
+                vec![]
+            },
+            editor_cursor: self.editor.get_cursor(),
+            editor_modified: self.editor.is_modified(),
         }
     }
+
+    fn generate_list_entries(&self) -> Vec<ListEntry<'_>> {
+        let cached_content = self.cached_content.borrow();
+        self.filtered_list
+            .get_kakisute_file_name_list(
+                self.kakisute_name_list.iter().map(|s| s.as_str()).collect(),
+            )
+            .into_iter()
+            .map(|name| {
+                let content = cached_content.get(name);
+                ListEntry {
+                    name,
+                    workspace: None,
+                    pinned: self.pinned_kakisute_name_list.iter().any(|p| p == name),
+                    created_at: created_at(name),
+                    first_line: content.and_then(|content| first_line(content)),
+                    size: content.map(|content| content.len()),
+                    tags: self.kakisute_tags.get(name).cloned().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Undo can not restore the trashed files after the app is closed. Runs on errors as well
+impl Drop for AppInteractor<'_> {
+    fn drop(&mut self) {
+        for file_name in &self.trashed {
+            if let Err(err) = self.service.remove_from_trash(file_name) {
+                eprintln!("Error: {:?}", err);
+            }
+        }
+    }
+}
+
+fn created_at(name: &str) -> Option<DateTime<Local>> {
+    string_to_datetime(notebook::split(name).1).single()
+}
+
+/// Leading "#" of Markdown headings is dropped
+fn first_line(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string())
+}
+
+/// "a -b c" adds a and c, and removes b
+fn parse_tags(input: &str) -> (Vec<String>, Vec<String>) {
+    let mut add = vec![];
+    let mut remove = vec![];
+    for tag in input.split_whitespace() {
+        match (tag.strip_prefix('-'), tag.strip_prefix('+')) {
+            (Some(tag), _) if !tag.is_empty() => remove.push(tag.to_string()),
+            (_, Some(tag)) if !tag.is_empty() => add.push(tag.to_string()),
+            (None, None) => add.push(tag.to_string()),
+            _ => {}
+        }
+    }
+    (add, remove)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AppInteractor, Mode, ServiceTrait};
-    use crate::service::kakisute_list::KakisuteList;
+    use super::{
+        parse_tags, Action, AppInteractor, FilteredList, Key, Mode, Prompt, Selection,
+        ServiceTrait, SortOrder,
+    };
+    use crate::domain::metadata::Metadata;
+    use crate::repository::Repository;
+    use crate::service::archive::ExportReport;
+    use crate::service::interface::IRepository;
+    use crate::service::{kakisute_list::KakisuteList, Workspace};
+    use crate::test_dir::TestDir;
     use anyhow::Result;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     impl ServiceMock {
         fn new(kakisute_list: KakisuteList) -> Self {
-            ServiceMock { kakisute_list }
+            ServiceMock {
+                kakisute_list,
+                notebook: RefCell::new(None),
+                calls: RefCell::new(vec![]),
+                saved_searches: BTreeMap::new(),
+                content: RefCell::new("Ok".to_string()),
+                workspaces: vec![],
+            }
         }
     }
 
     struct ServiceMock {
         kakisute_list: KakisuteList,
+        notebook: RefCell<Option<Option<String>>>,
+        /// Changes to files by name, which are made by undo and redo
+        calls: RefCell<Vec<String>>,
+        saved_searches: BTreeMap<String, String>,
+        /// Content of every file
+        content: RefCell<String>,
+        workspaces: Vec<Workspace>,
     }
 
     impl ServiceTrait for ServiceMock {
         fn create_kakisute(&self, _: Option<&str>) -> Result<String> {
             Ok("Ok".to_string())
         }
+        fn create_kakisute_from_template(&self, _: Option<&str>, _: &str) -> Result<String> {
+            Ok("Ok".to_string())
+        }
+        fn create_kakisute_with_content(&self, _: Option<&str>, _: &str) -> Result<String> {
+            Ok("Ok".to_string())
+        }
+        fn get_template_list(&self) -> Result<Vec<String>> {
+            Ok(vec![])
+        }
         fn edit_by_index(&self, _: usize) -> Result<String> {
             Ok("ok".to_string())
         }
+        fn update_content_by_index(&self, index: usize, content: &str) -> Result<String> {
+            self.calls
+                .borrow_mut()
+                .push(format!("update {} {}", index, content));
+            Ok("ok".to_string())
+        }
         fn delete_by_index(&self, _: usize) -> Result<String> {
             Ok("ok".to_string())
         }
         fn get_content_by_index(&self, _: usize) -> Result<String> {
-            Ok("Ok".to_string())
+            Ok(self.content.borrow().clone())
+        }
+        fn get_path_by_index(&self, _: usize) -> Result<String> {
+            Err(anyhow::anyhow!("No file in the mock"))
         }
 
-        fn reload(&self) {}
+        fn toggle_pin_by_index(&self, _: usize) -> Result<String> {
+            Ok("ok".to_string())
+        }
+
+        fn move_by_index(&self, _: usize, _: &str) -> Result<String> {
+            Ok("ok".to_string())
+        }
+
+        fn rename_by_index(&self, _: usize, name: &str) -> Result<String> {
+            Ok(name.to_string())
+        }
+
+        fn tag_by_index(
+            &self,
+            _: usize,
+            add: &[String],
+            _: &[String],
+        ) -> Result<(String, Vec<String>)> {
+            Ok(("ok".to_string(), add.to_vec()))
+        }
+
+        fn export_archive_to_file(&self, _: &str) -> Result<ExportReport> {
+            Ok(ExportReport::default())
+        }
+
+        fn rename_file(&self, from: &str, to: &str) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("rename {} {}", from, to));
+            Ok(())
+        }
+
+        fn set_tags(&self, file_name: &str, tags: &[String]) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("tags {} {}", file_name, tags.join(",")));
+            Ok(())
+        }
+
+        fn set_pinned(&self, file_name: &str, pinned: bool) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("pin {} {}", file_name, pinned));
+            Ok(())
+        }
+
+        fn trash(&self, file_name: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("trash {}", file_name));
+            Ok(())
+        }
+
+        fn restore(&self, file_name: &str, pinned: bool, tags: &[String]) -> Result<()> {
+            self.calls.borrow_mut().push(format!(
+                "restore {} {} {}",
+                file_name,
+                pinned,
+                tags.join(",")
+            ));
+            Ok(())
+        }
+
+        fn remove_from_trash(&self, file_name: &str) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("remove_from_trash {}", file_name));
+            Ok(())
+        }
+
+        fn switch_notebook(&self, notebook: Option<&str>) -> Result<()> {
+            *self.notebook.borrow_mut() = notebook
+                .map(|notebook| Some(notebook.to_string()).filter(|notebook| !notebook.is_empty()));
+            Ok(())
+        }
+
+        fn get_notebook(&self) -> Option<Option<String>> {
+            self.notebook.borrow().clone()
+        }
+
+        fn get_notebook_list(&self) -> Result<Vec<String>> {
+            Ok(vec!["work".to_string()])
+        }
+
+        fn search(&self, _: &str) -> Result<Vec<String>> {
+            Ok(vec![])
+        }
+
+        fn get_workspaces(&self) -> Vec<Workspace> {
+            self.workspaces.clone()
+        }
+
+        fn reload(&self) -> Result<()> {
+            Ok(())
+        }
 
         fn get_kakisute_list(&self) -> Vec<String> {
             self.kakisute_list.get_kakisute_file_name_list()
         }
+
+        fn get_pinned_kakisute_list(&self) -> Vec<String> {
+            self.kakisute_list.get_pinned_kakisute_file_name_list()
+        }
+
+        fn get_kakisute_tags(&self) -> Result<BTreeMap<String, Vec<String>>> {
+            Ok(BTreeMap::new())
+        }
+
+        fn get_search_history(&self) -> Result<Vec<String>> {
+            Ok(vec!["select".to_string()])
+        }
+
+        fn add_search_history(&self, query: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("history {}", query));
+            Ok(())
+        }
+
+        fn get_saved_searches(&self) -> Result<BTreeMap<String, String>> {
+            Ok(self.saved_searches.clone())
+        }
     }
 
     #[test]
     fn test_empty_app_interactor() {
-        let mut service = ServiceMock::new(KakisuteList::new());
-        let app_interactor = AppInteractor::new(&mut service);
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
         assert!(!app_interactor.is_kakisute_selected());
         assert!(app_interactor.edit_kakisute().is_err());
         assert!(app_interactor.delete_kakisute().is_err());
+        assert!(app_interactor.toggle_pin_kakisute().is_err());
         assert!(app_interactor.get_selected_kakisute_content().is_none());
         assert_eq!(app_interactor.mode, Mode::Normal);
         assert_eq!(app_interactor.new_filename.get(), "");
         assert!(!app_interactor.exit);
     }
 
+    #[test]
+    fn test_resolve_key_sequence() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        let g = "g".parse::<Key>().unwrap();
+        assert_eq!(app_interactor.resolve_key(g), None);
+        assert!(app_interactor.has_pending_keys());
+        assert_eq!(app_interactor.resolve_key(g), Some(Action::Top));
+        assert!(!app_interactor.has_pending_keys());
+
+        // An unfinished sequence is dropped and the key is resolved by itself
+        assert_eq!(app_interactor.resolve_key(g), None);
+        let j = "j".parse::<Key>().unwrap();
+        assert_eq!(app_interactor.resolve_key(j), Some(Action::Down));
+    }
+
+    #[test]
+    fn test_error_and_status() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.show_error(&anyhow::anyhow!("Failed to open editor"));
+        assert!(app_interactor.has_error());
+        assert_eq!(
+            app_interactor.generate_info().error_message.as_deref(),
+            Some("Failed to open editor")
+        );
+        app_interactor.dismiss_error();
+        assert!(!app_interactor.has_error());
+
+        app_interactor.show_status("Deleted: a.txt".to_string());
+        app_interactor.tick().unwrap();
+        assert_eq!(
+            app_interactor.generate_info().status_message.as_deref(),
+            Some("Deleted: a.txt")
+        );
+    }
+
     #[test]
     fn test_mode_switch() {
-        let mut service = ServiceMock::new(KakisuteList::new());
-        let mut app_interactor = AppInteractor::new(&mut service);
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
         app_interactor.enter_mode(Mode::Insert);
         assert_eq!(app_interactor.mode, Mode::Insert);
         app_interactor.enter_mode(Mode::Normal);
@@ -252,4 +1808,548 @@ mod tests {
         app_interactor.enter_mode(Mode::DeleteConfirm);
         assert_eq!(app_interactor.mode, Mode::DeleteConfirm);
     }
+
+    #[test]
+    fn test_switch_workspace() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.start_switching_workspace();
+        assert_eq!(app_interactor.mode, Mode::Normal);
+
+        app_interactor.set_workspaces(
+            vec!["personal".to_string(), "work".to_string()],
+            Some("work".to_string()),
+        );
+        app_interactor.start_switching_workspace();
+        assert_eq!(app_interactor.mode, Mode::SelectWorkspace);
+        app_interactor.decide_workspace();
+        assert!(!app_interactor.is_exited());
+
+        app_interactor.start_switching_workspace();
+        app_interactor.select_next_workspace();
+        app_interactor.decide_workspace();
+        assert!(app_interactor.is_exited());
+        assert_eq!(app_interactor.get_next_workspace(), Some("personal"));
+    }
+
+    /// Tick until the workspaces are loaded in the background
+    fn wait_for_workspaces(app_interactor: &mut AppInteractor) {
+        let started_at = Instant::now();
+        while app_interactor.workspace_loader.is_some()
+            && started_at.elapsed() < Duration::from_secs(5)
+        {
+            app_interactor.tick().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_search_all_workspaces() {
+        let data_dir = TestDir::new("app_search_all_workspaces");
+        fs::write(data_dir.join("2022_01_10_16_30_15.txt"), "content").unwrap();
+        fs::write(data_dir.join("2022_01_11_16_30_15.txt"), "other").unwrap();
+        let opened = Arc::new(AtomicUsize::new(0));
+        let mut service = ServiceMock::new(KakisuteList::new());
+        service.workspaces = vec![
+            Workspace {
+                name: "personal".to_string(),
+                open: {
+                    let data_dir = data_dir.to_string_lossy().to_string();
+                    let opened = opened.clone();
+                    Arc::new(move || {
+                        opened.fetch_add(1, Ordering::SeqCst);
+                        Ok(Box::new(Repository::open(&data_dir)?) as Box<dyn IRepository>)
+                    })
+                },
+            },
+            Workspace {
+                name: "broken".to_string(),
+                open: Arc::new(|| Err(anyhow::anyhow!("Directory does not exist"))),
+            },
+        ];
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.set_workspaces(
+            vec!["personal".to_string(), "work".to_string()],
+            Some("work".to_string()),
+        );
+        app_interactor.enter_mode(Mode::Search);
+        app_interactor.search_query.set("cont");
+        app_interactor.toggle_search_all_workspaces().unwrap();
+        assert!(app_interactor.is_searching_all_workspaces());
+        wait_for_workspaces(&mut app_interactor);
+        assert_eq!(
+            app_interactor.get_selected_kakisute_content(),
+            Some("content".to_string())
+        );
+        assert_eq!(
+            app_interactor.generate_info().status_message.as_deref(),
+            Some("Skipped workspace broken: Directory does not exist")
+        );
+
+        // Typing filters the loaded list without opening the workspaces again
+        app_interactor.search_query.set("");
+        app_interactor.filter().unwrap();
+        assert_eq!(app_interactor.generate_info().kakisute_list.len(), 2);
+        app_interactor.search_query.set("cont");
+        app_interactor.filter().unwrap();
+        assert_eq!(app_interactor.generate_info().kakisute_list.len(), 1);
+        assert_eq!(opened.load(Ordering::SeqCst), 1);
+
+        app_interactor.open_workspace_search_result().unwrap();
+        assert!(!app_interactor.is_searching_all_workspaces());
+        assert!(app_interactor.is_exited());
+        assert_eq!(app_interactor.get_next_workspace(), Some("personal"));
+        assert_eq!(
+            app_interactor.get_next_selection(),
+            Selection {
+                search_query: "cont".to_string(),
+                file_name: Some("2022_01_10_16_30_15.txt".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_restore_selection() {
+        let data_dir = TestDir::new("app_restore_selection");
+        fs::write(data_dir.join("2022_01_10_16_30_15.txt"), "").unwrap();
+        fs::write(data_dir.join("2022_01_11_16_30_15.txt"), "").unwrap();
+        fs::write(data_dir.join("2022_01_12_16_30_15.txt"), "").unwrap();
+        let kakisute_list =
+            KakisuteList::from_dir(fs::read_dir(&data_dir).unwrap(), &Metadata::new());
+        let service = ServiceMock::new(kakisute_list);
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor
+            .restore_selection(Selection {
+                search_query: "2022_01_1".to_string(),
+                file_name: Some("2022_01_11_16_30_15.txt".to_string()),
+            })
+            .unwrap();
+        assert_eq!(app_interactor.search_query.get(), "2022_01_1");
+        assert_eq!(app_interactor.generate_info().kakisute_list.len(), 3);
+        assert_eq!(
+            app_interactor.get_selected_kakisute_name().as_deref(),
+            Some("2022_01_11_16_30_15.txt")
+        );
+        assert_eq!(app_interactor.pending_selection, None);
+    }
+
+    #[test]
+    fn test_switch_notebook() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        assert_eq!(service.get_notebook(), None);
+        app_interactor.switch_to_next_notebook().unwrap();
+        assert_eq!(service.get_notebook(), Some(None));
+        app_interactor.switch_to_next_notebook().unwrap();
+        assert_eq!(service.get_notebook(), Some(Some("work".to_string())));
+        app_interactor.switch_to_next_notebook().unwrap();
+        assert_eq!(service.get_notebook(), None);
+    }
+
+    #[test]
+    fn test_saved_search() {
+        let mut service = ServiceMock::new(KakisuteList::new());
+        service.saved_searches = BTreeMap::from([
+            ("broken".to_string(), "prod OR".to_string()),
+            ("prod".to_string(), "prod".to_string()),
+        ]);
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.switch_to_next_notebook().unwrap();
+        app_interactor.switch_to_next_notebook().unwrap();
+        app_interactor.switch_to_next_notebook().unwrap();
+        assert_eq!(service.get_notebook(), None);
+        assert_eq!(
+            app_interactor.generate_info().saved_search.as_deref(),
+            Some("prod")
+        );
+        assert!(app_interactor
+            .status_message
+            .as_ref()
+            .is_some_and(|(message, _)| message.starts_with("Skipped saved search broken")));
+        app_interactor.search_query.set("a");
+        app_interactor.filter().unwrap();
+        app_interactor.switch_to_next_notebook().unwrap();
+        assert_eq!(service.get_notebook(), None);
+        assert_eq!(app_interactor.saved_search, None);
+    }
+
+    #[test]
+    fn test_search_history() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.load_search_history().unwrap();
+        app_interactor.enter_mode(Mode::Search);
+        assert!(app_interactor.edit_text_input(Action::HistoryPrevious));
+        assert_eq!(app_interactor.search_query.get(), "select");
+        app_interactor.record_search().unwrap();
+        assert_eq!(*service.calls.borrow(), ["history select"]);
+    }
+
+    #[test]
+    fn test_invalid_search_query() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.enter_mode(Mode::Search);
+        app_interactor.paste_text_input("\"select");
+        app_interactor.filter().unwrap();
+        assert_eq!(
+            app_interactor.generate_info().search_error.as_deref(),
+            Some("Unclosed quote at column 1")
+        );
+        assert!(app_interactor.record_search().is_err());
+        assert!(service.calls.borrow().is_empty());
+        app_interactor.push_text_input('"');
+        app_interactor.filter().unwrap();
+        let info = app_interactor.generate_info();
+        assert_eq!(info.search_error, None);
+        assert_eq!(info.content_highlight.as_deref(), Some("(?:select)"));
+    }
+
+    #[test]
+    fn test_help_and_command_palette() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.enter_mode(Mode::SelectWorkspace);
+        app_interactor.open_help();
+        assert_eq!(app_interactor.mode, Mode::Help);
+        assert_eq!(
+            app_interactor.generate_info().help_entries[0].action,
+            Action::Help
+        );
+        app_interactor.close_help();
+        assert_eq!(app_interactor.mode, Mode::SelectWorkspace);
+
+        app_interactor.enter_mode(Mode::Normal);
+        app_interactor.open_command_palette();
+        assert_eq!(app_interactor.mode, Mode::Command);
+        for c in "swork".chars() {
+            app_interactor.push_text_input(c);
+        }
+        app_interactor.filter_commands();
+        assert_eq!(
+            app_interactor.decide_command(),
+            Some(Action::SwitchWorkspace)
+        );
+        assert_eq!(app_interactor.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn test_sort_order() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.kakisute_name_list = vec![
+            "2022_01_01_00_00_00_b.txt".to_string(),
+            "work/2022_01_02_00_00_00_c.txt".to_string(),
+            "2022_01_03_00_00_00_a.txt".to_string(),
+        ];
+        app_interactor.pinned_kakisute_name_list = vec!["2022_01_01_00_00_00_b.txt".to_string()];
+        app_interactor.filtered_list = FilteredList::new(3);
+        let names = |app_interactor: &AppInteractor| {
+            app_interactor
+                .generate_list_entries()
+                .iter()
+                .map(|entry| entry.name.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        app_interactor.set_sort_order(SortOrder::Name);
+        assert_eq!(
+            names(&app_interactor),
+            [
+                "2022_01_01_00_00_00_b.txt",
+                "2022_01_03_00_00_00_a.txt",
+                "work/2022_01_02_00_00_00_c.txt"
+            ]
+        );
+        app_interactor.set_sort_order(SortOrder::DateDesc);
+        assert_eq!(
+            names(&app_interactor),
+            [
+                "2022_01_01_00_00_00_b.txt",
+                "2022_01_03_00_00_00_a.txt",
+                "work/2022_01_02_00_00_00_c.txt"
+            ]
+        );
+        app_interactor.set_sort_order(SortOrder::Date);
+        assert_eq!(names(&app_interactor)[1], "work/2022_01_02_00_00_00_c.txt");
+    }
+
+    #[test]
+    fn test_mouse() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.kakisute_name_list = vec!["a.txt".to_string(), "b.txt".to_string()];
+        app_interactor.filtered_list = FilteredList::new(2);
+
+        assert!(!app_interactor.click_list_item(1));
+        assert_eq!(app_interactor.get_list_index(), Some(1));
+        assert!(app_interactor.click_list_item(1));
+        assert!(!app_interactor.click_list_item(5));
+        assert_eq!(app_interactor.get_list_index(), Some(1));
+
+        // The mock content has only one line
+        app_interactor.scroll_content_down(3);
+        assert_eq!(app_interactor.generate_info().content_scroll, 0);
+
+        app_interactor.set_list_width_percent(95);
+        assert_eq!(app_interactor.get_panes().list_width_percent, 80);
+        app_interactor.set_list_width_percent(0);
+        assert_eq!(app_interactor.get_panes().list_width_percent, 10);
+    }
+
+    #[test]
+    fn test_panes() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.grow_list();
+        assert_eq!(app_interactor.get_panes().list_width_percent, 25);
+        for _ in 0..10 {
+            app_interactor.shrink_list();
+        }
+        assert_eq!(app_interactor.get_panes().list_width_percent, 10);
+
+        app_interactor.toggle_zoom_preview();
+        assert!(app_interactor.get_panes().zoom_preview);
+        app_interactor.toggle_preview();
+        assert!(!app_interactor.get_panes().show_preview);
+        assert!(!app_interactor.get_panes().zoom_preview);
+        app_interactor.toggle_zoom_preview();
+        assert!(app_interactor.get_panes().show_preview);
+    }
+
+    #[test]
+    fn test_render_any_size() {
+        use crate::ui::display_data::DisplayData;
+        use crate::ui::renderer::render;
+        use crate::ui::theme::Theme;
+        use tui::{backend::TestBackend, Terminal};
+
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.kakisute_name_list = vec!["a.txt".to_string(), "b.txt".to_string()];
+        app_interactor.filtered_list = FilteredList::new(2);
+        app_interactor.show_error(&anyhow::anyhow!("error"));
+        let modes = [
+            Mode::Normal,
+            Mode::Insert,
+            Mode::DeleteConfirm,
+            Mode::Search,
+            Mode::SelectTemplate,
+            Mode::Move,
+            Mode::SelectWorkspace,
+            Mode::Help,
+            Mode::Command,
+            Mode::Prompt,
+            Mode::Edit,
+        ];
+        for (width, height) in [(0, 0), (1, 1), (5, 3), (30, 8), (79, 24), (120, 40)] {
+            for mode in modes {
+                for toggle in [false, true] {
+                    if toggle {
+                        app_interactor.toggle_zoom_preview();
+                    }
+                    app_interactor.enter_mode(mode);
+                    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+                    terminal
+                        .draw(|f| {
+                            render(
+                                f,
+                                DisplayData::new(app_interactor.generate_info()),
+                                &Theme::default(),
+                            )
+                        })
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prompt() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.start_prompt(Prompt::Export);
+        assert_eq!(app_interactor.mode, Mode::Prompt);
+        assert_eq!(app_interactor.prompt_input.get(), "kakisute.tar.gz");
+        assert_eq!(
+            app_interactor.run_prompt().unwrap(),
+            "Exported 0 kakisute to kakisute.tar.gz"
+        );
+
+        // Rename and tag need a selected kakisute
+        app_interactor.start_prompt(Prompt::Rename);
+        assert!(app_interactor.run_prompt().is_err());
+
+        assert_eq!(
+            parse_tags("a -b +c - d"),
+            (
+                vec!["a".to_string(), "c".to_string(), "d".to_string()],
+                vec!["b".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_undo() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        let select = |app_interactor: &mut AppInteractor| {
+            app_interactor.kakisute_name_list = vec!["2022_01_01_00_00_00_a.txt".to_string()];
+            app_interactor.kakisute_tags = BTreeMap::from([(
+                "2022_01_01_00_00_00_a.txt".to_string(),
+                vec!["sql".to_string()],
+            )]);
+            app_interactor.filtered_list = FilteredList::new(1);
+        };
+        assert_eq!(app_interactor.undo().unwrap(), "Nothing to undo");
+
+        select(&mut app_interactor);
+        app_interactor.delete_kakisute().unwrap();
+        select(&mut app_interactor);
+        app_interactor.start_prompt(Prompt::Rename);
+        app_interactor.prompt_input.set("b.txt");
+        app_interactor.run_prompt().unwrap();
+        assert_eq!(*service.calls.borrow(), ["trash 2022_01_01_00_00_00_a.txt"]);
+
+        assert_eq!(
+            app_interactor.undo().unwrap(),
+            "Undone: rename 2022_01_01_00_00_00_a.txt to b.txt"
+        );
+        assert_eq!(
+            app_interactor.undo().unwrap(),
+            "Undone: delete 2022_01_01_00_00_00_a.txt"
+        );
+        assert_eq!(
+            app_interactor.redo().unwrap(),
+            "Redone: delete 2022_01_01_00_00_00_a.txt"
+        );
+        assert_eq!(
+            *service.calls.borrow(),
+            [
+                "trash 2022_01_01_00_00_00_a.txt",
+                "rename b.txt 2022_01_01_00_00_00_a.txt",
+                "restore 2022_01_01_00_00_00_a.txt false sql",
+                "trash 2022_01_01_00_00_00_a.txt",
+            ]
+        );
+
+        // A new change drops the changes which could be redone
+        select(&mut app_interactor);
+        app_interactor.toggle_pin_kakisute().unwrap();
+        assert_eq!(app_interactor.redo().unwrap(), "Nothing to redo");
+        assert_eq!(
+            app_interactor.undo().unwrap(),
+            "Undone: pin 2022_01_01_00_00_00_a.txt"
+        );
+    }
+
+    #[test]
+    fn test_remove_trashed_on_drop() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let select = |app_interactor: &mut AppInteractor, name: &str| {
+            app_interactor.kakisute_name_list = vec![name.to_string()];
+            app_interactor.filtered_list = FilteredList::new(1);
+        };
+
+        let mut app_interactor = AppInteractor::new(&service);
+        select(&mut app_interactor, "2022_01_01_00_00_00_a.txt");
+        app_interactor.delete_kakisute().unwrap();
+        select(&mut app_interactor, "2022_01_01_00_00_00_b.txt");
+        app_interactor.delete_kakisute().unwrap();
+        app_interactor.undo().unwrap();
+        drop(app_interactor);
+
+        // Files trashed by other sessions and restored ones are kept
+        assert_eq!(
+            *service.calls.borrow(),
+            [
+                "trash 2022_01_01_00_00_00_a.txt",
+                "trash 2022_01_01_00_00_00_b.txt",
+                "restore 2022_01_01_00_00_00_b.txt false ",
+                "remove_from_trash 2022_01_01_00_00_00_a.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quick_edit() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.kakisute_name_list = vec!["2022_01_01_00_00_00_a.txt".to_string()];
+        app_interactor.filtered_list = FilteredList::new(1);
+
+        app_interactor.start_quick_edit().unwrap();
+        assert_eq!(app_interactor.mode, Mode::Edit);
+        app_interactor.editor_mut().move_to_line_end();
+        app_interactor.editor_mut().insert('!');
+        assert!(app_interactor.cancel_quick_edit());
+        assert_eq!(app_interactor.mode, Mode::Normal);
+        assert!(service.calls.borrow().is_empty());
+
+        app_interactor.start_quick_edit().unwrap();
+        assert!(!app_interactor.editor_mut().is_modified());
+        app_interactor.editor_mut().move_to_line_end();
+        app_interactor.editor_mut().new_line();
+        app_interactor.editor_mut().insert('x');
+        assert_eq!(
+            app_interactor.save_quick_edit().unwrap().as_deref(),
+            Some("2022_01_01_00_00_00_a.txt")
+        );
+        assert_eq!(app_interactor.mode, Mode::Normal);
+        assert_eq!(*service.calls.borrow(), ["update 0 Ok\nx"]);
+    }
+
+    #[test]
+    fn test_quick_edit_changed_outside() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        let select = |app_interactor: &mut AppInteractor| {
+            app_interactor.kakisute_name_list = vec!["2022_01_01_00_00_00_a.txt".to_string()];
+            app_interactor.filtered_list = FilteredList::new(1);
+        };
+
+        // Changed before the watcher tells
+        select(&mut app_interactor);
+        app_interactor.start_quick_edit().unwrap();
+        app_interactor.editor_mut().insert('x');
+        *service.content.borrow_mut() = "Changed".to_string();
+        assert_eq!(app_interactor.save_quick_edit().unwrap(), None);
+        assert_eq!(app_interactor.mode, Mode::Edit);
+        assert!(service.calls.borrow().is_empty());
+        assert_eq!(
+            app_interactor.save_quick_edit().unwrap().as_deref(),
+            Some("2022_01_01_00_00_00_a.txt")
+        );
+        assert_eq!(*service.calls.borrow(), ["update 0 xOk"]);
+
+        // Told by the watcher
+        select(&mut app_interactor);
+        app_interactor.start_quick_edit().unwrap();
+        app_interactor
+            .apply_file_changes(&["2022_01_01_00_00_00_b.txt".to_string()])
+            .unwrap();
+        select(&mut app_interactor);
+        assert_eq!(
+            app_interactor.save_quick_edit().unwrap().as_deref(),
+            Some("2022_01_01_00_00_00_a.txt")
+        );
+
+        select(&mut app_interactor);
+        app_interactor.start_quick_edit().unwrap();
+        app_interactor
+            .apply_file_changes(&["2022_01_01_00_00_00_a.txt".to_string()])
+            .unwrap();
+        assert!(app_interactor.status_message.is_some());
+        select(&mut app_interactor);
+        assert_eq!(app_interactor.save_quick_edit().unwrap(), None);
+        assert!(app_interactor.save_quick_edit().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_skip_template_picker_without_template() {
+        let service = ServiceMock::new(KakisuteList::new());
+        let mut app_interactor = AppInteractor::new(&service);
+        app_interactor.start_creating_with_file_name().unwrap();
+        assert_eq!(app_interactor.mode, Mode::Insert);
+        assert_eq!(app_interactor.selected_template, None);
+    }
 }
@@ -1,3 +1,8 @@
+pub mod command_palette;
+pub mod content_loader;
 pub mod filtered_list;
-mod list_index;
+pub mod history;
+pub mod list_index;
+pub mod text_editor;
 pub mod text_input;
+pub mod workspace_loader;
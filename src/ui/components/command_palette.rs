@@ -0,0 +1,133 @@
+use std::cmp::Reverse;
+
+use crate::ui::components::list_index::ListIndex;
+use crate::ui::components::text_input::TextInput;
+use crate::ui::keymap::{Action, KeymapEntry};
+
+/// Actions narrowed down by a fuzzy query, best match first
+pub struct CommandPalette {
+    query: TextInput,
+    entries: Vec<KeymapEntry>,
+    matches: Vec<usize>,
+    index: ListIndex,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        CommandPalette {
+            query: TextInput::new(),
+            entries: vec![],
+            matches: vec![],
+            index: ListIndex::new(0),
+        }
+    }
+
+    pub fn open(&mut self, entries: Vec<KeymapEntry>) {
+        self.entries = entries;
+        self.query.clear();
+        self.update();
+    }
+
+    pub fn query(&self) -> &TextInput {
+        &self.query
+    }
+
+    /// Called whenever the query is changed
+    pub fn update(&mut self) {
+        let query = self.query.get();
+        let mut scored = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| fuzzy_score(&query, entry.description).map(|score| (i, score)))
+            .collect::<Vec<(usize, usize)>>();
+        // Stable sort keeps the keymap order among the same scores
+        scored.sort_by_key(|(_, score)| Reverse(*score));
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.index = ListIndex::new(self.matches.len());
+    }
+
+    pub fn select_next(&mut self) {
+        self.index.increment();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.index.decrement();
+    }
+
+    pub fn get_index(&self) -> Option<usize> {
+        self.index.get_index().ok()
+    }
+
+    pub fn get_selected_action(&self) -> Option<Action> {
+        let index = self.get_index()?;
+        Some(self.entries[self.matches[index]].action)
+    }
+
+    pub fn get_matched_entries(&self) -> Vec<&KeymapEntry> {
+        self.matches.iter().map(|&i| &self.entries[i]).collect()
+    }
+}
+
+/// Characters of the query must appear in order, ignoring case.
+/// Consecutive characters and characters at the beginning of words score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase().chars().collect::<Vec<char>>();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|&t| t == c)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if previous_match == Some(found.wrapping_sub(1)) {
+            score += 2;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use crate::ui::app_interactor::Mode;
+    use crate::ui::keymap::Keymap;
+
+    describe "fuzzy_score" {
+        it "matches characters in order" {
+            assert!(fuzzy_score("swk", "Switch workspace").is_some());
+            assert!(fuzzy_score("SW", "Switch workspace").is_some());
+            assert!(fuzzy_score("kws", "Switch workspace").is_none());
+            assert_eq!(fuzzy_score("", "Edit"), Some(0));
+        }
+
+        it "prefers the beginning of words and consecutive characters" {
+            assert!(fuzzy_score("ren", "Rename") > fuzzy_score("ren", "Create new"));
+            assert!(fuzzy_score("de", "Delete") > fuzzy_score("de", "Down 1/2 screen"));
+        }
+    }
+
+    describe "command_palette" {
+        it "selects the best match" {
+            let mut palette = CommandPalette::new();
+            palette.open(Keymap::default().entries(Mode::Normal));
+            assert_eq!(palette.get_selected_action(), Some(Action::Help));
+
+            for c in "sort name".chars() {
+                palette.query().push(c);
+            }
+            palette.update();
+            assert_eq!(palette.get_selected_action(), Some(Action::SortByName));
+
+            palette.query().push('!');
+            palette.update();
+            assert_eq!(palette.get_selected_action(), None);
+        }
+    }
+}
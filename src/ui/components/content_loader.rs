@@ -0,0 +1,81 @@
+use std::fs;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Read files on a worker thread so that the UI keeps responding.
+/// The worker stops early when the loader is dropped.
+pub struct ContentLoader {
+    receiver: Receiver<(String, Option<String>)>,
+    total: usize,
+    loaded: usize,
+}
+
+impl ContentLoader {
+    /// files are pairs of the kakisute name and its path
+    pub fn start(files: Vec<(String, String)>) -> Self {
+        let total = files.len();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (name, path) in files {
+                let content = fs::read_to_string(path).ok();
+                if sender.send((name, content)).is_err() {
+                    break;
+                }
+            }
+        });
+        ContentLoader {
+            receiver,
+            total,
+            loaded: 0,
+        }
+    }
+
+    /// Contents loaded since the last call. Unreadable files have None.
+    pub fn poll(&mut self) -> Vec<(String, Option<String>)> {
+        let loaded = self.receiver.try_iter().collect::<Vec<_>>();
+        self.loaded += loaded.len();
+        loaded
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded >= self.total
+    }
+
+    /// (loaded, total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.loaded, self.total)
+    }
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use std::time::{Duration, Instant};
+
+    use crate::test_dir::TestDir;
+
+    describe "content_loader" {
+        it "loads every file in the background" {
+            let dir = TestDir::new("content_loader");
+            fs::write(dir.join("a.txt"), "a").unwrap();
+            let files = vec![
+                ("a.txt".to_string(), dir.join("a.txt").to_string_lossy().to_string()),
+                ("none.txt".to_string(), dir.join("none.txt").to_string_lossy().to_string()),
+            ];
+
+            let mut loader = ContentLoader::start(files);
+            let mut loaded = vec![];
+            let started_at = Instant::now();
+            while !loader.is_done() && started_at.elapsed() < Duration::from_secs(5) {
+                loaded.extend(loader.poll());
+            }
+            assert_eq!(loader.progress(), (2, 2));
+            assert_eq!(loaded, vec![
+                ("a.txt".to_string(), Some("a".to_string())),
+                ("none.txt".to_string(), None),
+            ]);
+        }
+    }
+}
@@ -1,8 +1,4 @@
 use anyhow::{anyhow, Result};
-use grep::matcher::LineTerminator;
-use grep::regex::RegexMatcher;
-use grep::searcher::sinks::UTF8;
-use grep::searcher::SearcherBuilder;
 
 use crate::ui::components::list_index::ListIndex;
 
@@ -37,6 +33,35 @@ impl FilteredList {
         self.list_index.decrement_n(n);
     }
 
+    /// Select by the position in the filtered list. Out of range is ignored.
+    pub fn select(&mut self, position: usize) {
+        if position < self.filtered_indexes.len() {
+            self.list_index = ListIndex::new(self.filtered_indexes.len());
+            self.list_index.increment_n(position as u16);
+        }
+    }
+
+    /// Keep the current selection if the item is filtered out
+    pub fn select_original_index(&mut self, original_index: usize) {
+        if let Some(position) = self
+            .filtered_indexes
+            .iter()
+            .position(|&index| index == original_index)
+        {
+            self.list_index = ListIndex::new(self.filtered_indexes.len());
+            self.list_index.increment_n(position as u16);
+        }
+    }
+
+    /// Reorder the items by the original index, keeping the selected item
+    pub fn sort_by_key<K: Ord>(&mut self, key: impl Fn(usize) -> K) {
+        let selected = self.get_original_index().ok();
+        self.filtered_indexes.sort_by_key(|&index| key(index));
+        if let Some(selected) = selected {
+            self.select_original_index(selected);
+        }
+    }
+
     pub fn get_index(&self) -> Result<usize> {
         self.list_index.get_index()
     }
@@ -57,35 +82,19 @@ impl FilteredList {
     pub fn get_kakisute_file_name_list<'a>(
         &'a self,
         kakisute_name_list: Vec<&'a str>,
-    ) -> Vec<&str> {
+    ) -> Vec<&'a str> {
         self.filtered_indexes
             .iter()
             .filter_map(|&index| kakisute_name_list.get(index).copied())
             .collect()
     }
 
-    pub fn filter(&mut self, user_input: &str, kakisute_content_list: Vec<String>) -> Result<()> {
-        let matcher = RegexMatcher::new(user_input);
-        if matcher.is_err() {
-            return Ok(());
-        }
-        let mut matches: Vec<u64> = vec![];
-        let mut searcher = SearcherBuilder::new()
-            .line_terminator(LineTerminator::byte(b'\0'))
-            .build();
-        searcher.search_slice(
-            &matcher?,
-            kakisute_content_list.join("\0").as_bytes(),
-            UTF8(|lnum, _| {
-                matches.push(lnum - 1);
-                Ok(true)
-            }),
-        )?;
+    /// Keep the items of the whole list for which is_match returns true
+    pub fn filter(&mut self, list_size: usize, is_match: impl Fn(usize) -> bool) {
         let old_index = self.list_index.get_index().unwrap_or(0);
-        self.filtered_indexes = matches.iter().map(|x| *x as usize).collect();
+        self.filtered_indexes = (0..list_size).filter(|&index| is_match(index)).collect();
         self.list_index = ListIndex::new(self.filtered_indexes.len());
         self.list_index.increment_n(old_index as u16);
-        Ok(())
     }
 }
 
@@ -107,4 +116,36 @@ speculate! {
             assert_eq!(filtered_list.filtered_indexes.len(), 0)
         }
     }
+
+    describe "select_original_index" {
+        it "selects the item when it is in the filtered list" {
+            let mut filtered_list = FilteredList::new(3);
+            filtered_list.filter(3, |index| index > 0);
+            filtered_list.select_original_index(2);
+            assert_eq!(filtered_list.get_original_index().unwrap(), 2);
+            filtered_list.select_original_index(0);
+            assert_eq!(filtered_list.get_original_index().unwrap(), 2);
+        }
+    }
+
+    describe "filter" {
+        it "keeps the matching items of the whole list" {
+            let mut filtered_list = FilteredList::new(3);
+            filtered_list.filter(3, |index| index != 1);
+            assert_eq!(filtered_list.get_kakisute_file_name_list(vec!["a", "b", "c"]), vec!["a", "c"]);
+            filtered_list.filter(3, |index| index == 1);
+            assert_eq!(filtered_list.get_kakisute_file_name_list(vec!["a", "b", "c"]), vec!["b"]);
+            assert_eq!(filtered_list.get_original_index().unwrap(), 1);
+        }
+    }
+
+    describe "sort_by_key" {
+        it "reorders the items keeping the selection" {
+            let mut filtered_list = FilteredList::new(3);
+            filtered_list.select_next();
+            filtered_list.sort_by_key(std::cmp::Reverse);
+            assert_eq!(filtered_list.get_kakisute_file_name_list(vec!["a", "b", "c"]), vec!["c", "b", "a"]);
+            assert_eq!(filtered_list.get_original_index().unwrap(), 1);
+        }
+    }
 }
@@ -0,0 +1,103 @@
+use anyhow::Result;
+
+/// Older changes are forgotten beyond this
+const MAX_HISTORY: usize = 100;
+
+/// Undo and redo stacks. A change which fails to be applied stays where it was.
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> History<T> {
+    pub fn new() -> Self {
+        History {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    /// Called after a change is made. Changes undone so far can not be redone any more.
+    pub fn record(&mut self, change: T) {
+        if self.undo.len() == MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// Returns the undone change, or None if there is nothing to undo
+    pub fn undo(&mut self, apply: impl FnOnce(&T) -> Result<()>) -> Result<Option<&T>> {
+        Self::transfer(&mut self.undo, &mut self.redo, apply)
+    }
+
+    /// Returns the redone change, or None if there is nothing to redo
+    pub fn redo(&mut self, apply: impl FnOnce(&T) -> Result<()>) -> Result<Option<&T>> {
+        Self::transfer(&mut self.redo, &mut self.undo, apply)
+    }
+
+    fn transfer<'a>(
+        from: &mut Vec<T>,
+        to: &'a mut Vec<T>,
+        apply: impl FnOnce(&T) -> Result<()>,
+    ) -> Result<Option<&'a T>> {
+        let change = match from.pop() {
+            Some(change) => change,
+            None => return Ok(None),
+        };
+        if let Err(error) = apply(&change) {
+            from.push(change);
+            return Err(error);
+        }
+        to.push(change);
+        Ok(to.last())
+    }
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use anyhow::anyhow;
+
+    describe "history" {
+        before {
+            let mut history = History::new();
+            history.record(1);
+            history.record(2);
+        }
+
+        it "undoes and redoes in order" {
+            assert_eq!(history.undo(|_| Ok(())).unwrap(), Some(&2));
+            assert_eq!(history.undo(|_| Ok(())).unwrap(), Some(&1));
+            assert_eq!(history.undo(|_| Ok(())).unwrap(), None);
+            assert_eq!(history.redo(|_| Ok(())).unwrap(), Some(&1));
+            assert_eq!(history.redo(|_| Ok(())).unwrap(), Some(&2));
+            assert_eq!(history.redo(|_| Ok(())).unwrap(), None);
+        }
+
+        it "forgets redo after a new change" {
+            history.undo(|_| Ok(())).unwrap();
+            history.record(3);
+            assert_eq!(history.redo(|_| Ok(())).unwrap(), None);
+            assert_eq!(history.undo(|_| Ok(())).unwrap(), Some(&3));
+        }
+
+        it "keeps the change which failed" {
+            assert!(history.undo(|_| Err(anyhow!("failed"))).is_err());
+            assert_eq!(history.undo(|_| Ok(())).unwrap(), Some(&2));
+        }
+
+        it "forgets old changes" {
+            for i in 0..MAX_HISTORY {
+                history.record(i + 10);
+            }
+            let mut count = 0;
+            while history.undo(|_| Ok(())).unwrap().is_some() {
+                count += 1;
+            }
+            assert_eq!(count, MAX_HISTORY);
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use anyhow::{anyhow, Ok, Result};
-use std::usize;
 
 pub struct ListIndex {
     index: Option<usize>,
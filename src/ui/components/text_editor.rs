@@ -0,0 +1,275 @@
+use crate::ui::components::text_input::TextInput;
+
+/// Older snapshots are forgotten beyond this
+const MAX_UNDO: usize = 100;
+
+/// Lines and cursor to go back to
+type Snapshot = (Vec<String>, (usize, usize));
+
+/// Multi-line text made of a TextInput per line
+pub struct TextEditor {
+    lines: Vec<TextInput>,
+    /// Line and character index in the line
+    cursor: (usize, usize),
+    original: String,
+    undo: Vec<Snapshot>,
+    /// Characters typed in a row are undone at once
+    is_typing: bool,
+}
+
+impl TextEditor {
+    pub fn new() -> Self {
+        let mut editor = TextEditor {
+            lines: vec![],
+            cursor: (0, 0),
+            original: String::new(),
+            undo: vec![],
+            is_typing: false,
+        };
+        editor.open("");
+        editor
+    }
+
+    pub fn open(&mut self, content: &str) {
+        self.set_lines(content.split('\n').map(|line| line.to_string()).collect());
+        self.cursor = (0, 0);
+        self.original = content.to_string();
+        self.undo.clear();
+        self.is_typing = false;
+    }
+
+    /// Lines are joined with "\n", so the trailing newline is kept as it was
+    pub fn get(&self) -> String {
+        self.get_lines().join("\n")
+    }
+
+    pub fn get_lines(&self) -> Vec<String> {
+        self.lines.iter().map(|line| line.get()).collect()
+    }
+
+    pub fn get_cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.get() != self.original
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.save_snapshot(true);
+        self.insert_char(c);
+    }
+
+    pub fn new_line(&mut self) {
+        self.save_snapshot(false);
+        self.split_line();
+    }
+
+    /// Undone at once
+    pub fn paste(&mut self, text: &str) {
+        self.save_snapshot(false);
+        for c in text.chars() {
+            match c {
+                '\n' => self.split_line(),
+                '\r' => {}
+                c => self.insert_char(c),
+            }
+        }
+    }
+
+    /// Backspace. Joins the line to the previous one at the beginning of the line.
+    pub fn delete_previous(&mut self) {
+        let (row, column) = self.cursor;
+        if column > 0 {
+            self.save_snapshot(false);
+            self.lines[row].remove(column - 1);
+            self.cursor = (row, column - 1);
+        } else if row > 0 {
+            self.save_snapshot(false);
+            let line = self.lines.remove(row).get();
+            let previous = &self.lines[row - 1];
+            self.cursor = (row - 1, previous.len());
+            previous.set(&(previous.get() + &line));
+        }
+    }
+
+    /// Delete key. Joins the next line at the end of the line.
+    pub fn delete_next(&mut self) {
+        let (row, column) = self.cursor;
+        if column < self.lines[row].len() {
+            self.save_snapshot(false);
+            self.lines[row].remove(column);
+        } else if row + 1 < self.lines.len() {
+            self.save_snapshot(false);
+            let next = self.lines.remove(row + 1).get();
+            let line = &self.lines[row];
+            line.set(&(line.get() + &next));
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.is_typing = false;
+        let (row, column) = self.cursor;
+        if column > 0 {
+            self.cursor = (row, column - 1);
+        } else if row > 0 {
+            self.cursor = (row - 1, self.lines[row - 1].len());
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.is_typing = false;
+        let (row, column) = self.cursor;
+        if column < self.lines[row].len() {
+            self.cursor = (row, column + 1);
+        } else if row + 1 < self.lines.len() {
+            self.cursor = (row + 1, 0);
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.cursor.0 > 0 {
+            self.move_to_row(self.cursor.0 - 1);
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.cursor.0 + 1 < self.lines.len() {
+            self.move_to_row(self.cursor.0 + 1);
+        }
+    }
+
+    pub fn move_to_line_start(&mut self) {
+        self.is_typing = false;
+        self.cursor.1 = 0;
+    }
+
+    pub fn move_to_line_end(&mut self) {
+        self.is_typing = false;
+        self.cursor.1 = self.lines[self.cursor.0].len();
+    }
+
+    /// Returns false if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        self.is_typing = false;
+        match self.undo.pop() {
+            Some((lines, cursor)) => {
+                self.set_lines(lines);
+                self.cursor = cursor;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let (row, column) = self.cursor;
+        self.lines[row].insert(column, c);
+        self.cursor = (row, column + 1);
+    }
+
+    fn split_line(&mut self) {
+        let (row, column) = self.cursor;
+        let rest = self.lines[row].split_off(column);
+        let line = TextInput::new();
+        line.set(&rest);
+        self.lines.insert(row + 1, line);
+        self.cursor = (row + 1, 0);
+    }
+
+    /// The column is kept as long as the line is long enough
+    fn move_to_row(&mut self, row: usize) {
+        self.is_typing = false;
+        self.cursor = (row, self.cursor.1.min(self.lines[row].len()));
+    }
+
+    fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines
+            .iter()
+            .map(|line| {
+                let text_input = TextInput::new();
+                text_input.set(line);
+                text_input
+            })
+            .collect();
+    }
+
+    fn save_snapshot(&mut self, is_typing: bool) {
+        if !(is_typing && self.is_typing) {
+            if self.undo.len() == MAX_UNDO {
+                self.undo.remove(0);
+            }
+            self.undo.push((self.get_lines(), self.cursor));
+        }
+        self.is_typing = is_typing;
+    }
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "text_editor" {
+        before {
+            let mut editor = TextEditor::new();
+            editor.open("ab\ncd\n");
+        }
+
+        it "inserts and splits lines" {
+            editor.move_right();
+            editor.insert('x');
+            editor.new_line();
+            assert_eq!(editor.get(), "ax\nb\ncd\n");
+            assert_eq!(editor.get_cursor(), (1, 0));
+            assert!(editor.is_modified());
+        }
+
+        it "joins lines on deletion" {
+            editor.move_down();
+            editor.delete_previous();
+            assert_eq!(editor.get(), "abcd\n");
+            assert_eq!(editor.get_cursor(), (0, 2));
+            editor.move_to_line_end();
+            editor.delete_next();
+            assert_eq!(editor.get(), "abcd");
+            editor.delete_next();
+            assert_eq!(editor.get(), "abcd");
+        }
+
+        it "moves across lines" {
+            editor.move_to_line_end();
+            editor.move_right();
+            assert_eq!(editor.get_cursor(), (1, 0));
+            editor.move_left();
+            assert_eq!(editor.get_cursor(), (0, 2));
+            editor.move_down();
+            editor.move_down();
+            assert_eq!(editor.get_cursor(), (2, 0));
+            editor.move_down();
+            assert_eq!(editor.get_cursor(), (2, 0));
+        }
+
+        it "undoes typed characters at once" {
+            editor.insert('x');
+            editor.insert('y');
+            editor.new_line();
+            assert!(editor.undo());
+            assert_eq!(editor.get(), "xyab\ncd\n");
+            assert!(editor.undo());
+            assert_eq!(editor.get(), "ab\ncd\n");
+            assert_eq!(editor.get_cursor(), (0, 0));
+            assert!(!editor.undo());
+            assert!(!editor.is_modified());
+        }
+
+        it "pastes lines" {
+            editor.move_right();
+            editor.paste("x\r\ny");
+            assert_eq!(editor.get(), "ax\nyb\ncd\n");
+            assert_eq!(editor.get_cursor(), (1, 1));
+            assert!(editor.undo());
+            assert_eq!(editor.get(), "ab\ncd\n");
+        }
+    }
+}
@@ -1,14 +1,34 @@
 use std::cell::RefCell;
 
+use unicode_width::UnicodeWidthStr;
+
+/// Older entries are forgotten beyond this
+const MAX_HISTORY: usize = 100;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TextInput {
     value: RefCell<String>,
+    /// Character index in the value
+    cursor: RefCell<usize>,
+    history: RefCell<InputHistory>,
+}
+
+/// Values decided in the same input box, oldest first
+#[derive(Debug, Clone, PartialEq, Default)]
+struct InputHistory {
+    entries: Vec<String>,
+    /// Entry shown while browsing the history
+    position: Option<usize>,
+    /// Value typed before browsing the history
+    draft: String,
 }
 
 impl TextInput {
     pub fn new() -> Self {
         TextInput {
             value: RefCell::new(String::new()),
+            cursor: RefCell::new(0),
+            history: RefCell::new(InputHistory::default()),
         }
     }
 
@@ -16,15 +36,230 @@ impl TextInput {
         self.value.borrow().clone()
     }
 
+    /// The cursor is moved to the end
+    pub fn set(&self, value: &str) {
+        *self.value.borrow_mut() = value.to_string();
+        *self.cursor.borrow_mut() = self.len();
+    }
+
     pub fn clear(&self) {
-        self.value.borrow_mut().clear()
+        self.value.borrow_mut().clear();
+        *self.cursor.borrow_mut() = 0;
+        self.history.borrow_mut().position = None;
     }
+
+    /// Type the character at the cursor
     pub fn push(&self, c: char) {
-        self.value.borrow_mut().push(c)
+        let cursor = self.cursor();
+        self.insert(cursor, c);
+        *self.cursor.borrow_mut() = cursor + 1;
     }
 
+    /// Delete the character before the cursor like backspace
     pub fn pop(&self) -> Option<char> {
-        self.value.borrow_mut().pop()
+        let cursor = self.cursor();
+        if cursor == 0 {
+            return None;
+        }
+        *self.cursor.borrow_mut() = cursor - 1;
+        self.remove(cursor - 1)
+    }
+
+    /// Delete the character at the cursor like the delete key
+    pub fn delete_next(&self) -> Option<char> {
+        self.remove(self.cursor())
+    }
+
+    /// Line breaks are typed as spaces since the input is a single line
+    pub fn paste(&self, text: &str) {
+        let text = text.replace("\r\n", "\n");
+        for c in text.trim_end_matches('\n').chars() {
+            match c {
+                '\n' | '\r' | '\t' => self.push(' '),
+                c if c.is_control() => {}
+                c => self.push(c),
+            }
+        }
+    }
+
+    /// Character index of the cursor
+    pub fn cursor(&self) -> usize {
+        (*self.cursor.borrow()).min(self.len())
+    }
+
+    /// Display width of the text before the cursor
+    pub fn cursor_width(&self) -> usize {
+        self.value
+            .borrow()
+            .chars()
+            .take(self.cursor())
+            .collect::<String>()
+            .width()
+    }
+
+    pub fn move_left(&self) {
+        let cursor = self.cursor();
+        *self.cursor.borrow_mut() = cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&self) {
+        let cursor = self.cursor();
+        *self.cursor.borrow_mut() = (cursor + 1).min(self.len());
+    }
+
+    pub fn move_to_start(&self) {
+        *self.cursor.borrow_mut() = 0;
+    }
+
+    pub fn move_to_end(&self) {
+        *self.cursor.borrow_mut() = self.len();
+    }
+
+    /// To the beginning of the word before the cursor
+    pub fn move_word_left(&self) {
+        *self.cursor.borrow_mut() = self.word_start(|c| c.is_alphanumeric());
+    }
+
+    /// To the end of the word after the cursor
+    pub fn move_word_right(&self) {
+        let chars = self.value.borrow().chars().collect::<Vec<char>>();
+        let mut cursor = self.cursor();
+        while cursor < chars.len() && !chars[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        while cursor < chars.len() && chars[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        *self.cursor.borrow_mut() = cursor;
+    }
+
+    /// Delete the whitespace-separated word before the cursor like Ctrl-w of shells
+    pub fn delete_word(&self) {
+        let start = self.word_start(|c| !c.is_whitespace());
+        self.delete_range(start, self.cursor());
+    }
+
+    /// Delete the text before the cursor like Ctrl-u of shells
+    pub fn delete_to_start(&self) {
+        self.delete_range(0, self.cursor());
+    }
+
+    /// Keep the value to be recalled later. Empty values and duplicates are not kept twice.
+    pub fn remember(&self) {
+        let value = self.get();
+        let mut history = self.history.borrow_mut();
+        history.position = None;
+        if value.is_empty() {
+            return;
+        }
+        history.entries.retain(|entry| *entry != value);
+        if history.entries.len() == MAX_HISTORY {
+            history.entries.remove(0);
+        }
+        history.entries.push(value);
+    }
+
+    /// Replace the history such as with the one saved before. Oldest first.
+    pub fn set_history(&self, entries: Vec<String>) {
+        let mut history = self.history.borrow_mut();
+        history.entries = entries;
+        history.position = None;
+    }
+
+    /// Show the older entry. Returns false if there is none.
+    pub fn previous_history(&self) -> bool {
+        let entry = {
+            let mut history = self.history.borrow_mut();
+            let position = match history.position {
+                Some(0) => return false,
+                Some(position) => position - 1,
+                None if history.entries.is_empty() => return false,
+                None => {
+                    history.draft = self.get();
+                    history.entries.len() - 1
+                }
+            };
+            history.position = Some(position);
+            history.entries[position].clone()
+        };
+        self.set(&entry);
+        true
+    }
+
+    /// Show the newer entry, or the value typed before browsing the history
+    pub fn next_history(&self) -> bool {
+        let entry = {
+            let mut history = self.history.borrow_mut();
+            match history.position {
+                None => return false,
+                Some(position) if position + 1 < history.entries.len() => {
+                    history.position = Some(position + 1);
+                    history.entries[position + 1].clone()
+                }
+                Some(_) => {
+                    history.position = None;
+                    history.draft.clone()
+                }
+            }
+        };
+        self.set(&entry);
+        true
+    }
+
+    /// Number of characters
+    pub fn len(&self) -> usize {
+        self.value.borrow().chars().count()
+    }
+
+    /// Insert at the character index
+    pub fn insert(&self, index: usize, c: char) {
+        let byte_index = self.byte_index(index);
+        self.value.borrow_mut().insert(byte_index, c)
+    }
+
+    /// Remove the character at the character index
+    pub fn remove(&self, index: usize) -> Option<char> {
+        if index >= self.len() {
+            return None;
+        }
+        let byte_index = self.byte_index(index);
+        Some(self.value.borrow_mut().remove(byte_index))
+    }
+
+    /// Cut off the text after the character index
+    pub fn split_off(&self, index: usize) -> String {
+        let byte_index = self.byte_index(index);
+        self.value.borrow_mut().split_off(byte_index)
+    }
+
+    /// Skips the characters not in the word, then the word before the cursor
+    fn word_start(&self, is_word: impl Fn(char) -> bool) -> usize {
+        let chars = self.value.borrow().chars().collect::<Vec<char>>();
+        let mut cursor = self.cursor();
+        while cursor > 0 && !is_word(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        while cursor > 0 && is_word(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        cursor
+    }
+
+    /// The cursor is moved to the start of the range
+    fn delete_range(&self, start: usize, end: usize) {
+        let (start_byte, end_byte) = (self.byte_index(start), self.byte_index(end));
+        self.value
+            .borrow_mut()
+            .replace_range(start_byte..end_byte, "");
+        *self.cursor.borrow_mut() = start;
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        let value = self.value.borrow();
+        value
+            .char_indices()
+            .nth(index)
+            .map_or(value.len(), |(byte_index, _)| byte_index)
     }
 }
 
@@ -45,4 +280,88 @@ mod tests {
         text_input.clear();
         assert_eq!(text_input.get(), "");
     }
+
+    #[test]
+    fn test_edit_at_index() {
+        let text_input = TextInput::new();
+        text_input.set("日本");
+        text_input.insert(1, 'a');
+        assert_eq!(text_input.get(), "日a本");
+        assert_eq!(text_input.len(), 3);
+        assert_eq!(text_input.remove(0), Some('日'));
+        assert_eq!(text_input.remove(5), None);
+        assert_eq!(text_input.split_off(1), "本");
+        assert_eq!(text_input.get(), "a");
+    }
+
+    #[test]
+    fn test_cursor() {
+        let text_input = TextInput::new();
+        text_input.set("ac");
+        text_input.move_left();
+        text_input.push('b');
+        assert_eq!(text_input.get(), "abc");
+        assert_eq!(text_input.cursor(), 2);
+        text_input.move_to_start();
+        assert_eq!(text_input.pop(), None);
+        assert_eq!(text_input.delete_next(), Some('a'));
+        text_input.move_to_end();
+        text_input.push('日');
+        assert_eq!(text_input.get(), "bc日");
+        assert_eq!(text_input.cursor_width(), 4);
+        text_input.move_left();
+        assert_eq!(text_input.cursor_width(), 2);
+    }
+
+    #[test]
+    fn test_words() {
+        let text_input = TextInput::new();
+        text_input.set("select * from_table where");
+        text_input.move_word_left();
+        assert_eq!(text_input.cursor(), 20);
+        text_input.move_word_left();
+        text_input.move_word_left();
+        assert_eq!(text_input.cursor(), 9);
+        text_input.move_word_right();
+        assert_eq!(text_input.cursor(), 13);
+        text_input.move_to_end();
+        text_input.delete_word();
+        assert_eq!(text_input.get(), "select * from_table ");
+        text_input.delete_word();
+        assert_eq!(text_input.get(), "select * ");
+        text_input.move_left();
+        text_input.delete_to_start();
+        assert_eq!(text_input.get(), " ");
+        assert_eq!(text_input.cursor(), 0);
+    }
+
+    #[test]
+    fn test_paste() {
+        let text_input = TextInput::new();
+        text_input.set("[]");
+        text_input.move_left();
+        text_input.paste("a\r\nb\n");
+        assert_eq!(text_input.get(), "[a b]");
+        assert_eq!(text_input.cursor(), 4);
+    }
+
+    #[test]
+    fn test_history() {
+        let text_input = TextInput::new();
+        assert!(!text_input.previous_history());
+        for value in ["a", "b", "", "a"] {
+            text_input.set(value);
+            text_input.remember();
+        }
+        text_input.set("draft");
+        assert!(text_input.previous_history());
+        assert_eq!(text_input.get(), "a");
+        assert!(text_input.previous_history());
+        assert_eq!(text_input.get(), "b");
+        assert!(!text_input.previous_history());
+        assert!(text_input.next_history());
+        assert!(text_input.next_history());
+        assert_eq!(text_input.get(), "draft");
+        assert!(!text_input.next_history());
+    }
 }
@@ -0,0 +1,103 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::service::{Workspace, WorkspaceSearchResult};
+
+/// Load every kakisute of the workspaces on a worker thread for the cross-workspace search.
+/// The worker stops early when the loader is dropped.
+pub struct WorkspaceLoader {
+    receiver: Receiver<Result<Vec<WorkspaceSearchResult>, String>>,
+    total: usize,
+    loaded: usize,
+}
+
+impl WorkspaceLoader {
+    pub fn start(workspaces: Vec<Workspace>) -> Self {
+        let total = workspaces.len();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for workspace in workspaces {
+                let loaded = workspace
+                    .load()
+                    .map_err(|err| format!("{}: {:#}", workspace.name, err));
+                if sender.send(loaded).is_err() {
+                    break;
+                }
+            }
+        });
+        WorkspaceLoader {
+            receiver,
+            total,
+            loaded: 0,
+        }
+    }
+
+    /// Workspaces loaded since the last call. Workspaces which can not be opened have the reason.
+    pub fn poll(&mut self) -> Vec<Result<Vec<WorkspaceSearchResult>, String>> {
+        let loaded = self.receiver.try_iter().collect::<Vec<_>>();
+        self.loaded += loaded.len();
+        loaded
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded >= self.total
+    }
+
+    /// (loaded, total) in workspaces
+    pub fn progress(&self) -> (usize, usize) {
+        (self.loaded, self.total)
+    }
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use anyhow::anyhow;
+
+    use crate::repository::Repository;
+    use crate::service::interface::IRepository;
+    use crate::test_dir::TestDir;
+
+    describe "workspace_loader" {
+        it "loads every workspace in the background" {
+            let dir = TestDir::new("workspace_loader");
+            fs::write(dir.join("2022_01_10_16_30_15.txt"), "content").unwrap();
+            let data_dir = dir.to_string_lossy().to_string();
+            let workspaces = vec![
+                Workspace {
+                    name: "personal".to_string(),
+                    open: Arc::new(move || {
+                        Ok(Box::new(Repository::open(&data_dir)?) as Box<dyn IRepository>)
+                    }),
+                },
+                Workspace {
+                    name: "broken".to_string(),
+                    open: Arc::new(|| Err(anyhow!("Directory does not exist"))),
+                },
+            ];
+
+            let mut loader = WorkspaceLoader::start(workspaces);
+            let mut loaded = vec![];
+            let started_at = Instant::now();
+            while !loader.is_done() && started_at.elapsed() < Duration::from_secs(5) {
+                loaded.extend(loader.poll());
+            }
+            assert_eq!(loader.progress(), (2, 2));
+            assert_eq!(loaded, vec![
+                Ok(vec![WorkspaceSearchResult {
+                    workspace: "personal".to_string(),
+                    file_name: "2022_01_10_16_30_15.txt".to_string(),
+                    content: "content".to_string(),
+                    tags: vec![],
+                }]),
+                Err("broken: Directory does not exist".to_string()),
+            ]);
+        }
+    }
+}
@@ -1,55 +1,200 @@
-use crate::ui::app_interactor::Mode;
+use crate::datetime_helper::{humanize_elapsed, strip_datetime};
+use crate::domain::notebook;
+use crate::ui::app_interactor::{Mode, Prompt};
+use crate::ui::keymap::KeymapEntry;
+use crate::ui::list_columns::ListColumn;
+use crate::ui::renderer::PaneSettings;
+use chrono::{DateTime, Local};
+use unicode_width::UnicodeWidthStr;
 
 const DELETE_MODAL_BODY: &str = "Are you sure you want to delete? (Y/n)";
+const ERROR_MODAL_TITLE: &str = "Error (press any key to close)";
 const DELETE_MODAL_TITLE: &str = "Confirm Modal";
 const KAKISUTE_LIST_TITLE: &str = "List";
 const NO_FILE_BODY: &str = "<No file is selected>";
 const CONTENT_TITLE: &str = "Content";
+const EDITOR_TITLE: &str = "Quick edit";
+const MODIFIED_LABEL: &str = "[+]";
 const NEW_FILE_NAME_MODAL_TITLE: &str = "Input new file name";
 const SEARCH_MODAL_TITLE: &str = "Input search query";
-const HELP_NORMAL_BODY: &str =
-    "esc/q: Quit, j: Down, k: Up, ^d: Down 1/2 screen, ^u: Up 1/2 screen, e: Edit, n: Create new, N: Create new with file name, d: Delete";
-
-const HELP_INSERT_BODY: &str = "esc: Enter normal mode, Enter: Open editor";
-const HELP_DELETE_BODY: &str = "esc/n: Cancel, Y: delete";
-const HELP_SEARCH_BODY: &str = "esc/n: Cancel, Y: delete";
+const SEARCH_ALL_WORKSPACES_MODAL_TITLE: &str = "Input search query (all workspaces)";
+const MOVE_MODAL_TITLE: &str = "Input destination notebook (empty for top level)";
+const LOADING_LABEL: &str = "Loading";
+const ALL_NOTEBOOKS: &str = "All";
+const TOP_LEVEL_NOTEBOOK: &str = "/";
+const SAVED_SEARCH_LABEL: &str = "saved search";
+const WORKSPACE_MODAL_TITLE: &str = "Select workspace";
+const TEMPLATE_MODAL_TITLE: &str = "Select template";
+const NO_TEMPLATE_ITEM: &str = "<Empty>";
 const HELP_TITLE: &str = "Help";
+const HELP_OVERLAY_TITLE: &str = "Keybindings";
+/// Shown instead of keys for actions only run from the command palette
+const UNBOUND_KEYS: &str = "-";
+const COMMAND_PALETTE_TITLE: &str = "Command";
+const COMMAND_LIST_TITLE: &str = "Commands";
+const RENAME_MODAL_TITLE: &str = "Input new name (empty for default)";
+const TAG_MODAL_TITLE: &str = "Input tags to add (-tag to remove)";
+const EXPORT_MODAL_TITLE: &str = "Input archive path";
 
 pub struct DisplayData<'a> {
     pub index: Option<usize>,
     pub mode: Mode,
-    pub kakisute_list: BlockData<Vec<&'a str>>,
+    pub kakisute_list: BlockData<Vec<ListRow>>,
     pub content: BlockData<String>,
     pub new_filename: BlockData<String>,
     pub search_query: BlockData<String>,
+    pub move_destination: BlockData<String>,
     pub help: BlockData<String>,
     pub delete_modal: BlockData<&'a str>,
+    pub template_list: BlockData<Vec<String>>,
+    pub template_index: Option<usize>,
+    pub workspace_list: BlockData<Vec<String>>,
+    pub workspace_index: Option<usize>,
     pub need_search_box: bool,
+    pub panes: PaneSettings,
+    pub content_scroll: u16,
+    /// Regex highlighted in the content, empty for none
+    pub content_highlight: String,
+    /// Line and display column of the cursor while editing the content
+    pub editor_cursor: Option<(usize, usize)>,
+    pub status_message: String,
+    pub error_modal: Option<BlockData<String>>,
+    pub help_overlay: BlockData<Vec<String>>,
+    pub help_scroll: u16,
+    pub command_query: BlockData<String>,
+    pub command_list: BlockData<Vec<String>>,
+    pub command_index: Option<usize>,
+    pub prompt_input: BlockData<String>,
+    /// Display width before the cursor in the input box of the mode
+    pub input_cursor: usize,
+}
+
+pub struct ListEntry<'a> {
+    pub name: &'a str,
+    /// Set for results of the search across workspaces
+    pub workspace: Option<&'a str>,
+    pub pinned: bool,
+    pub created_at: Option<DateTime<Local>>,
+    /// First non-empty line of the content, None until the content is loaded
+    pub first_line: Option<String>,
+    /// Bytes of the content, None until the content is loaded
+    pub size: Option<usize>,
+    pub tags: Vec<String>,
+}
+
+/// Texts of the columns of a list row. The renderer aligns them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ListRow {
+    pub pinned: bool,
+    /// Workspace and notebook shown before the name or the title
+    pub prefix: String,
+    pub cells: Vec<(ListColumn, String)>,
 }
 
 pub struct Info<'a> {
     pub index: Option<usize>,
     pub mode: Mode,
-    pub kakisute_list: Vec<&'a str>,
+    pub kakisute_list: Vec<ListEntry<'a>>,
+    pub list_columns: Vec<ListColumn>,
     pub content: Option<String>,
     pub new_filename: String,
     pub search_query: String,
+    /// Why the search query can not be parsed
+    pub search_error: Option<String>,
+    /// Regex of the text the search matched in contents
+    pub content_highlight: Option<String>,
+    pub template_list: Vec<String>,
+    pub template_index: Option<usize>,
+    pub selected_template: Option<String>,
+    pub move_destination: String,
+    pub notebook: Option<Option<String>>,
+    /// Name of the saved search shown instead of the notebook
+    pub saved_search: Option<String>,
+    pub workspace_list: Vec<String>,
+    pub workspace_index: Option<usize>,
+    pub workspace: Option<String>,
+    pub search_all_workspaces: bool,
+    /// (loaded, total) while contents are loaded in the background
+    pub loading: Option<(usize, usize)>,
+    pub status_message: Option<String>,
+    pub error_message: Option<String>,
+    /// Generated from the keymap of the current mode
+    pub help: String,
+    /// Keybindings shown in the help overlay
+    pub help_entries: Vec<KeymapEntry>,
+    pub help_scroll: u16,
+    pub command_query: String,
+    /// Commands matching the query, best match first
+    pub command_list: Vec<KeymapEntry>,
+    pub command_index: Option<usize>,
+    pub prompt: Prompt,
+    pub prompt_input: String,
+    /// Display width before the cursor in the input box of the mode
+    pub input_cursor: usize,
+    pub need_search_box: bool,
+    pub panes: PaneSettings,
+    /// Lines scrolled in the content pane
+    pub content_scroll: u16,
+    /// File name while editing in the content pane
+    pub editing: Option<String>,
+    pub editor_lines: Vec<String>,
+    /// Line and character index in the line
+    pub editor_cursor: (usize, usize),
+    pub editor_modified: bool,
 }
 
 impl<'a> DisplayData<'a> {
     pub fn new(info: Info<'a>) -> Self {
-        let kakisute_list = DisplayData::create_kakisute_list(info.kakisute_list, info.index);
+        let kakisute_list = DisplayData::create_kakisute_list(
+            info.kakisute_list,
+            &info.list_columns,
+            info.index,
+            info.workspace.as_deref(),
+            &info.notebook,
+            info.saved_search.as_deref(),
+            info.loading,
+        );
+
+        let (content, editor_cursor) = match &info.editing {
+            Some(file_name) => (
+                DisplayData::create_editor(file_name, &info.editor_lines, info.editor_modified),
+                Some(DisplayData::editor_cursor(
+                    &info.editor_lines,
+                    info.editor_cursor,
+                )),
+            ),
+            None => (DisplayData::create_content(info.content), None),
+        };
 
-        let content = DisplayData::create_content(info.content);
+        let new_filename = DisplayData::create_new_filename_modal(
+            &info.new_filename,
+            info.selected_template.as_deref(),
+        );
 
-        let new_filename = DisplayData::create_new_filename_modal(&info.new_filename);
+        let search_query = DisplayData::create_search_query_modal(
+            &info.search_query,
+            info.search_all_workspaces,
+            info.search_error.as_deref(),
+        );
 
-        let search_query = DisplayData::create_search_query_modal(&info.search_query);
+        let move_destination = BlockData::new(info.move_destination.to_string(), MOVE_MODAL_TITLE);
 
-        let help = DisplayData::create_help(&info.mode);
+        let help = DisplayData::create_help(info.help);
 
         let delete_modal = BlockData::new(DELETE_MODAL_BODY, DELETE_MODAL_TITLE);
 
+        let template_list = DisplayData::create_template_list(info.template_list);
+
+        let workspace_list = BlockData::new(info.workspace_list, WORKSPACE_MODAL_TITLE);
+
+        let help_overlay = DisplayData::create_help_overlay(&info.help_entries);
+
+        let command_query = BlockData::new(info.command_query, COMMAND_PALETTE_TITLE);
+
+        let command_list = DisplayData::create_command_list(&info.command_list);
+
+        let prompt_input = DisplayData::create_prompt_modal(info.prompt, info.prompt_input);
+
         Self {
             index: info.index,
             mode: info.mode,
@@ -57,27 +202,124 @@ impl<'a> DisplayData<'a> {
             content,
             new_filename,
             search_query,
+            move_destination,
             help,
             delete_modal,
-            need_search_box: !info.search_query.is_empty() || info.mode == Mode::Search,
+            template_list,
+            template_index: info.template_index,
+            workspace_list,
+            workspace_index: info.workspace_index,
+            need_search_box: info.need_search_box,
+            panes: info.panes,
+            content_scroll: info.content_scroll,
+            content_highlight: info.content_highlight.unwrap_or_default(),
+            editor_cursor,
+            status_message: info.status_message.unwrap_or_default(),
+            error_modal: info
+                .error_message
+                .map(|message| BlockData::new(message, ERROR_MODAL_TITLE)),
+            help_overlay,
+            help_scroll: info.help_scroll,
+            command_query,
+            command_list,
+            command_index: info.command_index,
+            prompt_input,
+            input_cursor: info.input_cursor,
         }
     }
 
     fn create_kakisute_list(
-        kakisute_list: Vec<&'a str>,
+        kakisute_list: Vec<ListEntry<'a>>,
+        list_columns: &[ListColumn],
         index: Option<usize>,
-    ) -> BlockData<Vec<&'a str>> {
+        workspace: Option<&str>,
+        notebook: &Option<Option<String>>,
+        saved_search: Option<&str>,
+        loading: Option<(usize, usize)>,
+    ) -> BlockData<Vec<ListRow>> {
+        let notebook = match (saved_search, notebook) {
+            (Some(saved_search), _) => format!("{} ({})", saved_search, SAVED_SEARCH_LABEL),
+            (None, None) => ALL_NOTEBOOKS.to_string(),
+            (None, Some(None)) => TOP_LEVEL_NOTEBOOK.to_string(),
+            (None, Some(Some(notebook))) => notebook.to_string(),
+        };
+        let notebook = match workspace {
+            Some(workspace) => format!("{}:{}", workspace, notebook),
+            None => notebook.to_string(),
+        };
         let title = if let Some(index) = index {
             format!(
-                "{} ({}/{})",
+                "{} [{}] ({}/{})",
                 KAKISUTE_LIST_TITLE.to_owned(),
+                notebook,
                 &(index + 1).to_string(),
                 &kakisute_list.len().to_string()
             )
         } else {
-            KAKISUTE_LIST_TITLE.to_string()
+            format!("{} [{}]", KAKISUTE_LIST_TITLE, notebook)
         };
-        BlockData::new(kakisute_list, &title)
+        let title = match loading {
+            Some((loaded, total)) => format!("{} {} {}/{}", title, LOADING_LABEL, loaded, total),
+            None => title,
+        };
+        let now = Local::now();
+        let rows = kakisute_list
+            .iter()
+            .map(|entry| DisplayData::create_list_row(entry, list_columns, now))
+            .collect();
+        BlockData::new(rows, &title)
+    }
+
+    fn create_list_row(
+        entry: &ListEntry,
+        list_columns: &[ListColumn],
+        now: DateTime<Local>,
+    ) -> ListRow {
+        let (notebook, file_name) = notebook::split(entry.name);
+        let prefix = match (entry.workspace, notebook) {
+            (Some(workspace), Some(notebook)) => format!("{}:{}/", workspace, notebook),
+            (Some(workspace), None) => format!("{}:", workspace),
+            (None, Some(notebook)) => format!("{}/", notebook),
+            (None, None) => String::new(),
+        };
+        let cells = list_columns
+            .iter()
+            .map(|column| {
+                let text = match column {
+                    ListColumn::Date => entry
+                        .created_at
+                        .map(|created_at| humanize_elapsed(created_at, now))
+                        .unwrap_or_default(),
+                    ListColumn::Name => file_name.to_string(),
+                    ListColumn::Title => DisplayData::create_title(file_name, &entry.first_line),
+                    ListColumn::Size => entry.size.map(format_size).unwrap_or_default(),
+                    ListColumn::Tags => entry
+                        .tags
+                        .iter()
+                        .map(|tag| format!("#{}", tag))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                };
+                (*column, text)
+            })
+            .collect();
+        ListRow {
+            pinned: entry.pinned,
+            prefix,
+            cells,
+        }
+    }
+
+    /// Unnamed kakisute like "2022_01_10_16_30_15.txt" are titled by the first line
+    fn create_title(file_name: &str, first_line: &Option<String>) -> String {
+        let name = strip_datetime(file_name);
+        if !name.is_empty() && !name.starts_with('.') {
+            return name.to_string();
+        }
+        match first_line {
+            Some(first_line) => first_line.clone(),
+            None => file_name.to_string(),
+        }
     }
 
     fn create_content(kakisute_content: Option<String>) -> BlockData<String> {
@@ -88,23 +330,104 @@ impl<'a> DisplayData<'a> {
         BlockData::new(content_body, CONTENT_TITLE)
     }
 
-    fn create_new_filename_modal(user_input: &str) -> BlockData<String> {
-        BlockData::new(user_input.to_string(), NEW_FILE_NAME_MODAL_TITLE)
+    fn create_editor(file_name: &str, lines: &[String], modified: bool) -> BlockData<String> {
+        let title = if modified {
+            format!("{}: {} {}", EDITOR_TITLE, file_name, MODIFIED_LABEL)
+        } else {
+            format!("{}: {}", EDITOR_TITLE, file_name)
+        };
+        BlockData::new(lines.join("\n"), &title)
+    }
+
+    /// Converts the character index into the width of the characters before the cursor
+    fn editor_cursor(lines: &[String], (row, column): (usize, usize)) -> (usize, usize) {
+        let before = lines
+            .get(row)
+            .map(|line| line.chars().take(column).collect::<String>())
+            .unwrap_or_default();
+        (row, before.width())
+    }
+
+    fn create_new_filename_modal(user_input: &str, template: Option<&str>) -> BlockData<String> {
+        let title = match template {
+            Some(template) => format!("{} ({})", NEW_FILE_NAME_MODAL_TITLE, template),
+            None => NEW_FILE_NAME_MODAL_TITLE.to_string(),
+        };
+        BlockData::new(user_input.to_string(), &title)
     }
 
-    fn create_search_query_modal(user_input: &str) -> BlockData<String> {
-        BlockData::new(user_input.to_string(), SEARCH_MODAL_TITLE)
+    fn create_template_list(template_list: Vec<String>) -> BlockData<Vec<String>> {
+        let mut items = vec![NO_TEMPLATE_ITEM.to_string()];
+        items.extend(template_list);
+        BlockData::new(items, TEMPLATE_MODAL_TITLE)
     }
 
-    fn create_help(mode: &Mode) -> BlockData<String> {
-        let help_body = match mode {
-            Mode::Normal => HELP_NORMAL_BODY,
-            Mode::Insert => HELP_INSERT_BODY,
-            Mode::DeleteConfirm => HELP_DELETE_BODY,
-            Mode::Search => HELP_SEARCH_BODY,
+    fn create_search_query_modal(
+        user_input: &str,
+        search_all_workspaces: bool,
+        error: Option<&str>,
+    ) -> BlockData<String> {
+        let title = if search_all_workspaces {
+            SEARCH_ALL_WORKSPACES_MODAL_TITLE
+        } else {
+            SEARCH_MODAL_TITLE
+        };
+        match error {
+            Some(error) => {
+                BlockData::new(user_input.to_string(), &format!("{} - {}", title, error))
+            }
+            None => BlockData::new(user_input.to_string(), title),
         }
-        .to_string();
-        BlockData::new(help_body, HELP_TITLE)
+    }
+
+    fn create_help(help: String) -> BlockData<String> {
+        BlockData::new(help, HELP_TITLE)
+    }
+
+    /// One action per line with keys aligned in a column
+    fn create_help_overlay(entries: &[KeymapEntry]) -> BlockData<Vec<String>> {
+        let keys_width = entries
+            .iter()
+            .map(|entry| entry.keys.width_cjk())
+            .max()
+            .unwrap_or(0)
+            .max(UNBOUND_KEYS.len());
+        let lines = entries
+            .iter()
+            .map(|entry| {
+                let keys = if entry.keys.is_empty() {
+                    UNBOUND_KEYS
+                } else {
+                    &entry.keys
+                };
+                let padding = " ".repeat(keys_width - keys.width_cjk());
+                format!("{}{}  {}", keys, padding, entry.description)
+            })
+            .collect();
+        BlockData::new(lines, HELP_OVERLAY_TITLE)
+    }
+
+    fn create_command_list(entries: &[KeymapEntry]) -> BlockData<Vec<String>> {
+        let items = entries
+            .iter()
+            .map(|entry| {
+                if entry.keys.is_empty() {
+                    entry.description.to_string()
+                } else {
+                    format!("{} ({})", entry.description, entry.keys)
+                }
+            })
+            .collect();
+        BlockData::new(items, COMMAND_LIST_TITLE)
+    }
+
+    fn create_prompt_modal(prompt: Prompt, user_input: String) -> BlockData<String> {
+        let title = match prompt {
+            Prompt::Rename => RENAME_MODAL_TITLE,
+            Prompt::Tag => TAG_MODAL_TITLE,
+            Prompt::Export => EXPORT_MODAL_TITLE,
+        };
+        BlockData::new(user_input, title)
     }
 }
 
@@ -122,25 +445,87 @@ impl<T> BlockData<T> {
     }
 }
 
+/// Bytes in the largest unit such as "1.5K"
+fn format_size(bytes: usize) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f < KIB {
+        format!("{}B", bytes)
+    } else if bytes_f < KIB * KIB {
+        format!("{:.1}K", bytes_f / KIB)
+    } else {
+        format!("{:.1}M", bytes_f / KIB / KIB)
+    }
+}
+
 #[cfg(test)]
 use speculate::speculate;
 
 #[cfg(test)]
 speculate! {
     describe "create_help" {
-        it "returns Normal help when Normal mode" {
-            let res = DisplayData::create_help(&Mode::Normal);
-            assert_eq!(res.body, HELP_NORMAL_BODY)
+        it "shows help generated from the keymap" {
+            let res = DisplayData::create_help("q: Quit".to_string());
+            assert_eq!(res.body, "q: Quit");
+            assert_eq!(res.title, HELP_TITLE)
+        }
+    }
+
+    describe "create_help_overlay" {
+        it "aligns keys and marks unbound actions" {
+            use crate::ui::keymap::{Action, Keymap};
+
+            let entries = Keymap::default().entries(Mode::Normal);
+            let res = DisplayData::create_help_overlay(&entries);
+            let width = entries.iter().map(|entry| entry.keys.len()).max().unwrap();
+            assert_eq!(res.body.len(), entries.len());
+            assert_eq!(res.body[0], format!("?{}  Help", " ".repeat(width - 1)));
+            let rename = entries.iter().position(|entry| entry.action == Action::Rename).unwrap();
+            assert!(res.body[rename].starts_with(UNBOUND_KEYS));
+        }
+    }
+
+    describe "create_list_row" {
+        before {
+            let now = Local::now();
+            let entry = ListEntry {
+                name: "work/2022_01_10_16_30_15.md",
+                workspace: None,
+                pinned: true,
+                created_at: Some(now - chrono::Duration::hours(3)),
+                first_line: Some("Meeting notes".to_string()),
+                size: Some(2048),
+                tags: vec!["a".to_string(), "b".to_string()],
+            };
         }
 
-        it "returns Insert help when Insert mode" {
-            let res = DisplayData::create_help(&Mode::Insert);
-            assert_eq!(res.body, HELP_INSERT_BODY)
+        it "formats every column" {
+            let columns = [ListColumn::Date, ListColumn::Title, ListColumn::Size, ListColumn::Tags, ListColumn::Name];
+            let row = DisplayData::create_list_row(&entry, &columns, now);
+            assert_eq!(row, ListRow {
+                pinned: true,
+                prefix: "work/".to_string(),
+                cells: vec![
+                    (ListColumn::Date, "3h ago".to_string()),
+                    (ListColumn::Title, "Meeting notes".to_string()),
+                    (ListColumn::Size, "2.0K".to_string()),
+                    (ListColumn::Tags, "#a #b".to_string()),
+                    (ListColumn::Name, "2022_01_10_16_30_15.md".to_string()),
+                ],
+            });
         }
 
-        it "returns Delete help when Delete mode" {
-            let res = DisplayData::create_help(&Mode::DeleteConfirm);
-            assert_eq!(res.body, HELP_DELETE_BODY)
+        it "prefers the name to the first line" {
+            assert_eq!(DisplayData::create_title("2022_01_10_16_30_15_todo.md", &entry.first_line), "todo.md");
+            assert_eq!(DisplayData::create_title("2022_01_10_16_30_15.md", &None), "2022_01_10_16_30_15.md");
+        }
+    }
+
+    describe "format_size" {
+        it "uses the largest unit" {
+            assert_eq!(format_size(512), "512B");
+            assert_eq!(format_size(1536), "1.5K");
+            assert_eq!(format_size(3 * 1024 * 1024), "3.0M");
         }
     }
 
@@ -1,39 +1,108 @@
 use super::app_interactor::AppInteractor;
+pub use super::app_interactor::Selection;
 use super::display_data::DisplayData;
 use super::input_handler;
+use super::keymap::Keymap;
+use super::list_columns::ListColumn;
 
 use super::terminal_manager::{TerminalManage, TerminalManager};
+use super::theme::Theme;
+use crate::repository::watcher::ChangeEvent;
 use crate::service::ServiceTrait;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyEvent};
 use std::io;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
 use tui::{backend::CrosstermBackend, Terminal};
 
-pub fn run_app(app: &mut dyn ServiceTrait) -> Result<()> {
+/// How long to wait for terminal input before running background work such as live reload
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// What the caller should do after the app is closed
+pub enum AppExit {
+    Quit,
+    /// The query and the selected kakisute are carried into the next workspace
+    SwitchWorkspace(String, Selection),
+}
+
+/// Workspaces the app can switch to, the one it is opened with and what to select in it
+pub struct Workspaces {
+    pub names: Vec<String>,
+    pub current: Option<String>,
+    pub selection: Selection,
+}
+
+pub fn run_app(
+    app: &mut dyn ServiceTrait,
+    workspaces: Workspaces,
+    changes: Receiver<ChangeEvent>,
+    keymap: Keymap,
+    theme: Theme,
+    list_columns: Vec<ListColumn>,
+) -> Result<AppExit> {
     let mut app_interactor = AppInteractor::new(app);
+    app_interactor.set_keymap(keymap);
+    app_interactor.set_list_columns(list_columns);
+    app_interactor.load_search_history()?;
+    app_interactor.set_workspaces(workspaces.names, workspaces.current);
+    app_interactor.restore_selection(workspaces.selection)?;
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let terminal = Terminal::new(backend)?;
-    let mut terminal_manager = TerminalManager::new(terminal);
+    let mut terminal_manager = TerminalManager::new(terminal, theme);
     terminal_manager.enter_app_screen()?;
 
     while !app_interactor.is_exited() {
-        render_loop(&mut terminal_manager, &mut app_interactor)?
+        render_loop(&mut terminal_manager, &mut app_interactor, &changes)?
     }
-    Ok(())
+    Ok(match app_interactor.get_next_workspace() {
+        Some(workspace) => {
+            AppExit::SwitchWorkspace(workspace.to_string(), app_interactor.get_next_selection())
+        }
+        None => AppExit::Quit,
+    })
 }
 
 fn render_loop(
     terminal_manager: &mut dyn TerminalManage,
     app_interactor: &mut AppInteractor,
+    changes: &Receiver<ChangeEvent>,
 ) -> Result<()> {
     terminal_manager.draw_frame(DisplayData::new(app_interactor.generate_info()))?;
 
-    if let Event::Key(KeyEvent {
-        code, modifiers, ..
-    }) = event::read()?
-    {
-        input_handler::handle_input(code, modifiers, terminal_manager, app_interactor)?;
-    };
+    if event::poll(TICK_RATE)? {
+        let result = match event::read()? {
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => input_handler::handle_input(code, modifiers, terminal_manager, app_interactor),
+            Event::Mouse(mouse_event) => {
+                input_handler::handle_mouse(mouse_event, terminal_manager, app_interactor)
+            }
+            Event::Paste(text) => input_handler::handle_paste(&text, app_interactor),
+            // The next frame fits the new size, but leftovers of the old one have to be cleared
+            Event::Resize(_, _) => terminal_manager.clear_app_screen(),
+            _ => Ok(()),
+        };
+        // Show the error and keep the session. The app screen may have been left for the editor.
+        if let Err(e) = result {
+            terminal_manager.enter_app_screen()?;
+            terminal_manager.clear_app_screen()?;
+            app_interactor.show_error(&e);
+        }
+    }
+
+    let file_names = changes
+        .try_iter()
+        .flat_map(|event| event.file_names)
+        .collect::<Vec<String>>();
+    if !file_names.is_empty() {
+        if let Err(e) = app_interactor.apply_file_changes(&file_names) {
+            app_interactor.show_error(&e);
+        }
+    }
+    if let Err(e) = app_interactor.tick() {
+        app_interactor.show_error(&e);
+    }
     Ok(())
 }
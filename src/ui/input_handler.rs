@@ -1,9 +1,14 @@
-use super::app_interactor::{AppInteractor, Mode};
+use super::app_interactor::{AppInteractor, Mode, Prompt, SortOrder};
 
-use super::renderer::{HELP_BOX_LENGTH, MARGIN};
+use super::keymap::{Action, Key};
+use super::renderer::{build_main_layout, list_offset, MainLayout};
 use super::terminal_manager::TerminalManage;
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use tui::layout::Rect;
+
+/// Lines scrolled in the content pane by a tick of the mouse wheel
+const MOUSE_SCROLL_LINES: u16 = 3;
 
 pub fn handle_input(
     key_code: KeyCode,
@@ -11,116 +16,495 @@ pub fn handle_input(
     terminal_manager: &mut dyn TerminalManage,
     app_interactor: &mut AppInteractor,
 ) -> Result<()> {
-    match app_interactor.get_mode() {
-        Mode::Insert => match (key_code, key_modifier) {
-            (KeyCode::Esc, KeyModifiers::NONE) => {
-                app_interactor.clear_text_input();
-                app_interactor.enter_mode(Mode::Normal);
-            }
-            (KeyCode::Char(c), KeyModifiers::NONE) => {
-                app_interactor.push_text_input(c);
+    // Any key closes the error modal
+    if app_interactor.has_error() {
+        app_interactor.dismiss_error();
+        return Ok(());
+    }
+    let key = Key::new(key_code, key_modifier);
+    let mode = *app_interactor.get_mode();
+
+    // Characters are typed into the input instead of being looked up in the keymap
+    if let (
+        Mode::Insert | Mode::Search | Mode::Move | Mode::Command | Mode::Prompt | Mode::Edit,
+        Some(c),
+        false,
+    ) = (mode, key.as_char(), app_interactor.has_pending_keys())
+    {
+        match mode {
+            Mode::Edit => app_interactor.editor_mut().insert(c),
+            _ => app_interactor.push_text_input(c),
+        }
+        return refilter(mode, app_interactor);
+    }
+
+    match app_interactor.resolve_key(key) {
+        Some(action) => handle_action(action, mode, terminal_manager, app_interactor),
+        None => Ok(()),
+    }
+}
+
+/// Mouse works on the main screen only, not in modals
+pub fn handle_mouse(
+    event: MouseEvent,
+    terminal_manager: &mut dyn TerminalManage,
+    app_interactor: &mut AppInteractor,
+) -> Result<()> {
+    if app_interactor.has_error() {
+        if let MouseEventKind::Down(_) = event.kind {
+            app_interactor.dismiss_error();
+        }
+        return Ok(());
+    }
+    let mode = *app_interactor.get_mode();
+    if !matches!(mode, Mode::Normal | Mode::Search) {
+        return Ok(());
+    }
+    let layout = current_layout(terminal_manager, app_interactor)?;
+    let (column, row) = (event.column, event.row);
+    let is_in = |area: Option<Rect>| area.is_some_and(|area| contains(area, column, row));
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if is_on_divider(&layout, column, row) {
+                app_interactor.start_dragging_divider();
+            } else if let Some(position) = layout
+                .list
+                .and_then(|list_area| list_position(list_area, column, row, app_interactor))
+            {
+                let is_double_click = app_interactor.click_list_item(position);
+                if is_double_click && mode == Mode::Normal {
+                    return handle_action(Action::Edit, mode, terminal_manager, app_interactor);
+                }
             }
-            (KeyCode::Char(c), KeyModifiers::SHIFT) => {
-                app_interactor.push_text_input(c);
+        }
+        MouseEventKind::Drag(MouseButton::Left) if app_interactor.is_dragging_divider() => {
+            if let (Some(list_area), Some(content_area)) = (layout.list, layout.content) {
+                let main_width = list_area.width + content_area.width;
+                let list_width = column.saturating_sub(list_area.x) + 1;
+                let percent = u32::from(list_width) * 100 / u32::from(main_width.max(1));
+                app_interactor.set_list_width_percent(percent as u16);
             }
-            (KeyCode::Backspace, KeyModifiers::NONE) => {
-                app_interactor.pop_text_input();
+        }
+        MouseEventKind::Up(_) => {
+            app_interactor.stop_dragging_divider();
+        }
+        MouseEventKind::ScrollDown if is_in(layout.list) => {
+            app_interactor.select_next();
+        }
+        MouseEventKind::ScrollUp if is_in(layout.list) => {
+            app_interactor.select_previous();
+        }
+        MouseEventKind::ScrollDown if is_in(layout.content) => {
+            app_interactor.scroll_content_down(MOUSE_SCROLL_LINES);
+        }
+        MouseEventKind::ScrollUp if is_in(layout.content) => {
+            app_interactor.scroll_content_up(MOUSE_SCROLL_LINES);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn current_layout(
+    terminal_manager: &dyn TerminalManage,
+    app_interactor: &AppInteractor,
+) -> Result<MainLayout> {
+    Ok(build_main_layout(
+        terminal_manager.get_terminal_size()?,
+        app_interactor.need_search_box(),
+        app_interactor.get_panes(),
+    ))
+}
+
+/// Number of items visible in the list
+fn list_height(
+    terminal_manager: &dyn TerminalManage,
+    app_interactor: &AppInteractor,
+) -> Result<u16> {
+    let layout = current_layout(terminal_manager, app_interactor)?;
+    // -2 for borders
+    Ok(layout
+        .list
+        .map_or(0, |list_area| list_area.height.saturating_sub(2)))
+}
+
+/// Either border next to the divider can be grabbed when the panes are side by side
+fn is_on_divider(layout: &MainLayout, column: u16, row: u16) -> bool {
+    match (layout.list, layout.content) {
+        (Some(list_area), Some(content_area)) if !layout.is_stacked => {
+            (column + 1 == content_area.x || column == content_area.x)
+                && row >= list_area.y
+                && row < list_area.y + list_area.height
+        }
+        _ => false,
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
+/// Position in the list of the item under the cursor. Borders are excluded.
+fn list_position(
+    list_area: Rect,
+    column: u16,
+    row: u16,
+    app_interactor: &AppInteractor,
+) -> Option<usize> {
+    let inner = Rect::new(
+        list_area.x + 1,
+        list_area.y + 1,
+        list_area.width.saturating_sub(2),
+        list_area.height.saturating_sub(2),
+    );
+    if !contains(inner, column, row) {
+        return None;
+    }
+    let offset = list_offset(app_interactor.get_list_index(), list_area);
+    Some(offset + usize::from(row - inner.y))
+}
+
+/// Bracketed paste. Only the input boxes and the editor take the text.
+pub fn handle_paste(text: &str, app_interactor: &mut AppInteractor) -> Result<()> {
+    if app_interactor.has_error() {
+        return Ok(());
+    }
+    let mode = *app_interactor.get_mode();
+    match mode {
+        Mode::Edit => app_interactor.editor_mut().paste(text),
+        _ => app_interactor.paste_text_input(text),
+    }
+    refilter(mode, app_interactor)
+}
+
+/// The list and the commands follow the query as it is edited
+fn refilter(mode: Mode, app_interactor: &mut AppInteractor) -> Result<()> {
+    match mode {
+        Mode::Search => app_interactor.filter()?,
+        Mode::Command => app_interactor.filter_commands(),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_action(
+    action: Action,
+    mode: Mode,
+    terminal_manager: &mut dyn TerminalManage,
+    app_interactor: &mut AppInteractor,
+) -> Result<()> {
+    // Global bindings work in every mode
+    if action == Action::Suspend {
+        return terminal_manager.suspend();
+    }
+    if app_interactor.edit_text_input(action) {
+        return refilter(mode, app_interactor);
+    }
+    if action == Action::Decide {
+        app_interactor.remember_text_input();
+    }
+    match mode {
+        Mode::Insert => match action {
+            Action::Cancel => {
+                app_interactor.clear_text_input();
+                app_interactor.enter_mode(Mode::Normal);
             }
-            (KeyCode::Enter, KeyModifiers::NONE) => {
+            Action::Decide => {
                 terminal_manager.exit_app_screen()?;
-                app_interactor.create_new_kakisute_with_file_name()?;
+                let file_name = app_interactor.create_new_kakisute_with_file_name()?;
                 terminal_manager.enter_app_screen()?;
                 app_interactor.reload()?;
+                app_interactor.show_status(format!("Created: {}", file_name));
             }
             _ => {}
         },
-        Mode::Normal => match (key_code, key_modifier) {
-            (KeyCode::Char('q'), KeyModifiers::NONE) => {
+        Mode::Normal => match action {
+            Action::Help => {
+                app_interactor.open_help();
+            }
+            Action::CommandPalette => {
+                app_interactor.open_command_palette();
+            }
+            Action::Quit => {
                 terminal_manager.exit_app_screen()?;
                 app_interactor.exit();
             }
-            (KeyCode::Char('N'), KeyModifiers::SHIFT) => {
-                app_interactor.enter_mode(Mode::Insert);
+            Action::NewWithName => {
+                app_interactor.start_creating_with_file_name()?;
             }
-            (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, KeyModifiers::NONE) => {
+            Action::Down => {
                 app_interactor.select_next();
             }
-            (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
-                let terminal_height = terminal_manager.get_terminal_height()?;
-                let list_height = terminal_height - HELP_BOX_LENGTH - MARGIN * 4;
-                app_interactor.select_previous_n(list_height / 2);
+            Action::HalfPageUp => {
+                let list_height = list_height(terminal_manager, app_interactor)?;
+                app_interactor.select_previous_n((list_height / 2).max(1));
             }
-            (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, KeyModifiers::NONE) => {
+            Action::Up => {
                 app_interactor.select_previous();
             }
-            (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
-                let terminal_height = terminal_manager.get_terminal_height()?;
-                let list_height = terminal_height - HELP_BOX_LENGTH - MARGIN * 4;
-                app_interactor.select_next_n(list_height / 2);
+            Action::HalfPageDown => {
+                let list_height = list_height(terminal_manager, app_interactor)?;
+                app_interactor.select_next_n((list_height / 2).max(1));
             }
-            (KeyCode::Char('e'), KeyModifiers::NONE) => {
-                if app_interactor.is_kakisute_selected() {
-                    terminal_manager.exit_app_screen()?;
-                    app_interactor.edit_kakisute()?;
-                    terminal_manager.enter_app_screen()?;
-                    terminal_manager.clear_app_screen()?;
-                    app_interactor.reload()?;
-                }
+            Action::Top => {
+                app_interactor.select_first();
+            }
+            Action::Bottom => {
+                app_interactor.select_last();
+            }
+            Action::Edit if app_interactor.is_kakisute_selected() => {
+                terminal_manager.exit_app_screen()?;
+                let file_name = app_interactor.edit_kakisute()?;
+                terminal_manager.enter_app_screen()?;
+                terminal_manager.clear_app_screen()?;
+                app_interactor.reload()?;
+                app_interactor.show_status(format!("Edited: {}", file_name));
             }
-            (KeyCode::Char('n'), KeyModifiers::NONE) => {
+            Action::QuickEdit if app_interactor.is_kakisute_selected() => {
+                app_interactor.start_quick_edit()?;
+            }
+            Action::New => {
                 terminal_manager.exit_app_screen()?;
-                app_interactor.create_new_kakisute()?;
+                let file_name = app_interactor.create_new_kakisute()?;
                 terminal_manager.enter_app_screen()?;
                 terminal_manager.clear_app_screen()?;
                 app_interactor.reload()?;
+                app_interactor.show_status(format!("Created: {}", file_name));
             }
-            (KeyCode::Char('d'), KeyModifiers::NONE) => {
+            Action::Delete => {
                 app_interactor.enter_mode(Mode::DeleteConfirm);
             }
-            (KeyCode::Char('/'), KeyModifiers::NONE) => {
+            Action::Search => {
                 app_interactor.enter_mode(Mode::Search);
             }
+            Action::Move if app_interactor.is_kakisute_selected() => {
+                app_interactor.enter_mode(Mode::Move);
+            }
+            Action::SwitchWorkspace => {
+                app_interactor.start_switching_workspace();
+            }
+            Action::SwitchNotebook => {
+                app_interactor.switch_to_next_notebook()?;
+            }
+            Action::Pin if app_interactor.is_kakisute_selected() => {
+                let file_name = app_interactor.toggle_pin_kakisute()?;
+                app_interactor.reload()?;
+                let action = if app_interactor.is_pinned(&file_name) {
+                    "Pinned"
+                } else {
+                    "Unpinned"
+                };
+                app_interactor.show_status(format!("{}: {}", action, file_name));
+            }
+            Action::Rename if app_interactor.is_kakisute_selected() => {
+                app_interactor.start_prompt(Prompt::Rename);
+            }
+            Action::Tag if app_interactor.is_kakisute_selected() => {
+                app_interactor.start_prompt(Prompt::Tag);
+            }
+            Action::Export => {
+                app_interactor.start_prompt(Prompt::Export);
+            }
+            Action::TogglePreview => {
+                app_interactor.toggle_preview();
+            }
+            Action::ZoomPreview => {
+                app_interactor.toggle_zoom_preview();
+            }
+            Action::GrowList => {
+                app_interactor.grow_list();
+            }
+            Action::ShrinkList => {
+                app_interactor.shrink_list();
+            }
+            Action::Undo => {
+                let message = app_interactor.undo()?;
+                app_interactor.show_status(message);
+            }
+            Action::Redo => {
+                let message = app_interactor.redo()?;
+                app_interactor.show_status(message);
+            }
+            Action::SortByDate => {
+                app_interactor.set_sort_order(SortOrder::Date);
+                app_interactor.show_status("Sorted by date".to_string());
+            }
+            Action::SortByDateDesc => {
+                app_interactor.set_sort_order(SortOrder::DateDesc);
+                app_interactor.show_status("Sorted by date (newest first)".to_string());
+            }
+            Action::SortByName => {
+                app_interactor.set_sort_order(SortOrder::Name);
+                app_interactor.show_status("Sorted by name".to_string());
+            }
             _ => {}
         },
-        Mode::DeleteConfirm => match (key_code, key_modifier) {
-            (KeyCode::Esc, KeyModifiers::NONE) | (KeyCode::Char('n'), KeyModifiers::NONE) => {
+        Mode::DeleteConfirm => match action {
+            Action::Cancel => {
                 app_interactor.enter_mode(Mode::Normal);
             }
-            (KeyCode::Char('Y'), KeyModifiers::SHIFT) => {
-                app_interactor.delete_kakisute()?;
+            Action::ConfirmDelete => {
+                let file_name = app_interactor.delete_kakisute()?;
                 app_interactor.reload()?;
+                app_interactor.show_status(format!("Deleted: {}", file_name));
             }
             _ => {}
         },
-        Mode::Search => match (key_code, key_modifier) {
-            (KeyCode::Enter, KeyModifiers::NONE) => {
+        Mode::Move => match action {
+            Action::Cancel => {
+                app_interactor.clear_text_input();
                 app_interactor.enter_mode(Mode::Normal);
             }
-            (KeyCode::Esc, KeyModifiers::NONE) => {
-                app_interactor.clear_text_input();
-                app_interactor.filter()?;
+            Action::Decide => {
+                let file_name = app_interactor.move_kakisute()?;
+                app_interactor.reload()?;
+                app_interactor.show_status(format!("Moved: {}", file_name));
+            }
+            _ => {}
+        },
+        Mode::SelectWorkspace => match action {
+            Action::Help => {
+                app_interactor.open_help();
+            }
+            Action::Cancel => {
+                app_interactor.enter_mode(Mode::Normal);
+            }
+            Action::Down => {
+                app_interactor.select_next_workspace();
+            }
+            Action::Up => {
+                app_interactor.select_previous_workspace();
+            }
+            Action::Decide => {
+                app_interactor.decide_workspace();
+                if app_interactor.is_exited() {
+                    terminal_manager.exit_app_screen()?;
+                }
+            }
+            _ => {}
+        },
+        Mode::SelectTemplate => match action {
+            Action::Help => {
+                app_interactor.open_help();
+            }
+            Action::Cancel => {
                 app_interactor.enter_mode(Mode::Normal);
             }
-            (KeyCode::Char(c), KeyModifiers::NONE) => {
-                app_interactor.push_text_input(c);
-                app_interactor.filter()?
+            Action::Down => {
+                app_interactor.select_next_template();
+            }
+            Action::Up => {
+                app_interactor.select_previous_template();
+            }
+            Action::Decide => {
+                app_interactor.decide_template();
+            }
+            _ => {}
+        },
+        Mode::Search => match action {
+            Action::Decide => {
+                app_interactor.record_search()?;
+                if app_interactor.is_searching_all_workspaces() {
+                    app_interactor.open_workspace_search_result()?;
+                    if app_interactor.is_exited() {
+                        terminal_manager.exit_app_screen()?;
+                    }
+                } else {
+                    app_interactor.enter_mode(Mode::Normal);
+                }
             }
-            (KeyCode::Char(c), KeyModifiers::SHIFT) => {
-                app_interactor.push_text_input(c);
-                app_interactor.filter()?
+            Action::Cancel => {
+                app_interactor.clear_text_input();
+                if app_interactor.is_searching_all_workspaces() {
+                    app_interactor.toggle_search_all_workspaces()?;
+                }
+                app_interactor.filter()?;
+                app_interactor.enter_mode(Mode::Normal);
             }
-            (KeyCode::Backspace, KeyModifiers::NONE) => {
-                app_interactor.pop_text_input();
-                app_interactor.filter()?
+            Action::ToggleAllWorkspaces => {
+                app_interactor.toggle_search_all_workspaces()?;
             }
-            (KeyCode::Char('j'), KeyModifiers::CONTROL) => {
+            Action::Down => {
                 app_interactor.select_next();
             }
-            (KeyCode::Char('k'), KeyModifiers::CONTROL) => {
+            Action::Up => {
                 app_interactor.select_previous();
             }
             _ => {}
         },
+        Mode::Help => match action {
+            Action::Cancel => {
+                app_interactor.close_help();
+            }
+            Action::Down => {
+                app_interactor.scroll_help_down();
+            }
+            Action::Up => {
+                app_interactor.scroll_help_up();
+            }
+            _ => {}
+        },
+        Mode::Command => match action {
+            Action::Cancel => {
+                app_interactor.clear_text_input();
+                app_interactor.enter_mode(Mode::Normal);
+            }
+            Action::Down => {
+                app_interactor.select_next_command();
+            }
+            Action::Up => {
+                app_interactor.select_previous_command();
+            }
+            Action::Decide => {
+                if let Some(action) = app_interactor.decide_command() {
+                    return handle_action(action, Mode::Normal, terminal_manager, app_interactor);
+                }
+            }
+            _ => {}
+        },
+        Mode::Prompt => match action {
+            Action::Cancel => {
+                app_interactor.clear_text_input();
+                app_interactor.enter_mode(Mode::Normal);
+            }
+            Action::Decide => {
+                let message = app_interactor.run_prompt()?;
+                app_interactor.reload()?;
+                app_interactor.show_status(message);
+            }
+            _ => {}
+        },
+        Mode::Edit => match action {
+            Action::Cancel => {
+                let modified = app_interactor.cancel_quick_edit();
+                if modified {
+                    app_interactor.show_status("Discarded changes".to_string());
+                }
+            }
+            Action::Save => {
+                let message = match app_interactor.save_quick_edit()? {
+                    Some(file_name) => format!("Saved: {}", file_name),
+                    None => "Changed outside of the app. Save again to overwrite".to_string(),
+                };
+                app_interactor.show_status(message);
+            }
+            Action::Undo => {
+                let undone = app_interactor.editor_mut().undo();
+                if !undone {
+                    app_interactor.show_status("Nothing to undo".to_string());
+                }
+            }
+            Action::Up => app_interactor.editor_mut().move_up(),
+            Action::Down => app_interactor.editor_mut().move_down(),
+            Action::Left => app_interactor.editor_mut().move_left(),
+            Action::Right => app_interactor.editor_mut().move_right(),
+            Action::LineStart => app_interactor.editor_mut().move_to_line_start(),
+            Action::LineEnd => app_interactor.editor_mut().move_to_line_end(),
+            Action::NewLine => app_interactor.editor_mut().new_line(),
+            Action::DeleteChar => app_interactor.editor_mut().delete_previous(),
+            Action::DeleteNextChar => app_interactor.editor_mut().delete_next(),
+            _ => {}
+        },
     }
     Ok(())
 }
@@ -0,0 +1,768 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use super::app_interactor::Mode;
+
+/// Mode name to action name to key sequences, as written in the config file
+pub type KeymapConfig = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+/// Section of the config for bindings available in every mode
+const GLOBAL: &str = "global";
+
+const MODE_NAMES: &[(Mode, &str)] = &[
+    (Mode::Normal, "normal"),
+    (Mode::Insert, "insert"),
+    (Mode::DeleteConfirm, "delete-confirm"),
+    (Mode::Search, "search"),
+    (Mode::SelectTemplate, "select-template"),
+    (Mode::Move, "move"),
+    (Mode::SelectWorkspace, "select-workspace"),
+    (Mode::Help, "help"),
+    (Mode::Command, "command"),
+    (Mode::Prompt, "prompt"),
+    (Mode::Edit, "edit"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Down,
+    Up,
+    HalfPageDown,
+    HalfPageUp,
+    Top,
+    Bottom,
+    Edit,
+    New,
+    NewWithName,
+    Delete,
+    ConfirmDelete,
+    Search,
+    Pin,
+    Move,
+    SwitchNotebook,
+    SwitchWorkspace,
+    ToggleAllWorkspaces,
+    Decide,
+    Cancel,
+    DeleteChar,
+    Suspend,
+    Help,
+    CommandPalette,
+    Rename,
+    Tag,
+    Export,
+    SortByDate,
+    SortByDateDesc,
+    SortByName,
+    TogglePreview,
+    ZoomPreview,
+    GrowList,
+    ShrinkList,
+    Undo,
+    Redo,
+    QuickEdit,
+    Save,
+    Left,
+    Right,
+    LineStart,
+    LineEnd,
+    NewLine,
+    DeleteNextChar,
+    WordLeft,
+    WordRight,
+    DeleteWord,
+    DeleteToStart,
+    HistoryPrevious,
+    HistoryNext,
+}
+
+/// Action, name in the config file and description in the help
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "Quit"),
+    (Action::Down, "down", "Down"),
+    (Action::Up, "up", "Up"),
+    (Action::HalfPageDown, "half-page-down", "Down 1/2 screen"),
+    (Action::HalfPageUp, "half-page-up", "Up 1/2 screen"),
+    (Action::Top, "top", "Top"),
+    (Action::Bottom, "bottom", "Bottom"),
+    (Action::Edit, "edit", "Edit"),
+    (Action::New, "new", "Create new"),
+    (
+        Action::NewWithName,
+        "new-with-name",
+        "Create new with file name",
+    ),
+    (Action::Delete, "delete", "Delete"),
+    (Action::ConfirmDelete, "confirm-delete", "Delete"),
+    (Action::Search, "search", "Search"),
+    (Action::Pin, "pin", "Pin/Unpin"),
+    (Action::Move, "move", "Move"),
+    (Action::SwitchNotebook, "switch-notebook", "Switch notebook"),
+    (
+        Action::SwitchWorkspace,
+        "switch-workspace",
+        "Switch workspace",
+    ),
+    (
+        Action::ToggleAllWorkspaces,
+        "toggle-all-workspaces",
+        "Toggle searching all workspaces",
+    ),
+    (Action::Decide, "decide", "Decide"),
+    (Action::Cancel, "cancel", "Cancel"),
+    (Action::DeleteChar, "delete-char", "Delete character"),
+    (Action::Suspend, "suspend", "Suspend"),
+    (Action::Help, "help", "Help"),
+    (Action::CommandPalette, "command-palette", "Command palette"),
+    (Action::Rename, "rename", "Rename"),
+    (Action::Tag, "tag", "Tag"),
+    (Action::Export, "export", "Export"),
+    (Action::SortByDate, "sort-by-date", "Sort by date"),
+    (
+        Action::SortByDateDesc,
+        "sort-by-date-desc",
+        "Sort by date (newest first)",
+    ),
+    (Action::SortByName, "sort-by-name", "Sort by name"),
+    (Action::TogglePreview, "toggle-preview", "Show/Hide preview"),
+    (Action::ZoomPreview, "zoom-preview", "Zoom preview"),
+    (Action::GrowList, "grow-list", "Widen list"),
+    (Action::ShrinkList, "shrink-list", "Narrow list"),
+    (Action::Undo, "undo", "Undo"),
+    (Action::Redo, "redo", "Redo"),
+    (Action::QuickEdit, "quick-edit", "Quick edit"),
+    (Action::Save, "save", "Save"),
+    (Action::Left, "left", "Left"),
+    (Action::Right, "right", "Right"),
+    (Action::LineStart, "line-start", "Line start"),
+    (Action::LineEnd, "line-end", "Line end"),
+    (Action::NewLine, "new-line", "New line"),
+    (
+        Action::DeleteNextChar,
+        "delete-next-char",
+        "Delete next character",
+    ),
+    (Action::WordLeft, "word-left", "Word left"),
+    (Action::WordRight, "word-right", "Word right"),
+    (Action::DeleteWord, "delete-word", "Delete word"),
+    (
+        Action::DeleteToStart,
+        "delete-to-start",
+        "Delete to line start",
+    ),
+    (
+        Action::HistoryPrevious,
+        "history-previous",
+        "Previous input",
+    ),
+    (Action::HistoryNext, "history-next", "Next input"),
+];
+
+impl Action {
+    fn name(&self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| action == self)
+            .map(|(_, name, _)| *name)
+            .unwrap()
+    }
+
+    fn description(&self, mode: Mode) -> &'static str {
+        match (mode, self) {
+            (Mode::Insert, Action::Cancel) => "Enter normal mode",
+            (Mode::Insert, Action::Decide) => "Open editor",
+            (Mode::Move, Action::Decide) => "Move",
+            (Mode::SelectTemplate, Action::Decide) => "Select template",
+            (Mode::SelectWorkspace, Action::Decide) => "Switch workspace",
+            (Mode::Help, Action::Cancel) => "Close help",
+            (Mode::Command, Action::Decide) => "Run command",
+            (Mode::Prompt, Action::Decide) => "Run command",
+            (Mode::Edit, Action::Cancel) => "Discard changes",
+            _ => ACTIONS
+                .iter()
+                .find(|(action, _, _)| action == self)
+                .map(|(_, _, description)| *description)
+                .unwrap(),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, name, _)| *name == s)
+            .map(|(action, _, _)| *action)
+            .ok_or_else(|| anyhow!("Unknown action: {}", s))
+    }
+}
+
+/// Key press. Shift is dropped for characters since the character itself tells it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+const NAMED_KEYS: &[(KeyCode, &str)] = &[
+    (KeyCode::Esc, "esc"),
+    (KeyCode::Enter, "enter"),
+    (KeyCode::Tab, "tab"),
+    (KeyCode::BackTab, "backtab"),
+    (KeyCode::Backspace, "backspace"),
+    (KeyCode::Delete, "delete"),
+    (KeyCode::Up, "up"),
+    (KeyCode::Down, "down"),
+    (KeyCode::Left, "left"),
+    (KeyCode::Right, "right"),
+    (KeyCode::Home, "home"),
+    (KeyCode::End, "end"),
+    (KeyCode::PageUp, "pageup"),
+    (KeyCode::PageDown, "pagedown"),
+    (KeyCode::Char(' '), "space"),
+];
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Key { code, modifiers }
+    }
+
+    /// Character typed into text inputs
+    pub fn as_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    /// e.g. "j", "G", "ctrl-d", "alt-x", "esc", "shift-tab"
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_lowercase();
+            if lower.starts_with("ctrl-") && rest.len() > 5 {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[5..];
+            } else if lower.starts_with("alt-") && rest.len() > 4 {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[4..];
+            } else if lower.starts_with("shift-") && rest.len() > 6 {
+                modifiers |= KeyModifiers::SHIFT;
+                rest = &rest[6..];
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(rest))
+                .map(|(code, _)| *code)
+                .ok_or_else(|| anyhow!("Unknown key: {}", s))?,
+        };
+        let code = match (code, modifiers.contains(KeyModifiers::SHIFT)) {
+            (KeyCode::Tab, true) => KeyCode::BackTab,
+            (KeyCode::Char(c), true) => KeyCode::Char(c.to_ascii_uppercase()),
+            _ => code,
+        };
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "^")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::BackTab => write!(f, "S-Tab"),
+            code => {
+                let name = NAMED_KEYS
+                    .iter()
+                    .find(|(named, _)| *named == code)
+                    .map(|(_, name)| *name)
+                    .unwrap_or("?");
+                // Capitalized like "Enter" and "Tab"
+                let mut chars = name.chars();
+                match chars.next() {
+                    Some(first) => write!(f, "{}{}", first.to_ascii_uppercase(), chars.as_str()),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// "g g" and "gg" are the sequence of g and g. Named keys like "esc" are a single key.
+fn parse_sequence(s: &str) -> Result<Vec<Key>> {
+    let mut keys = vec![];
+    for token in s.split_whitespace() {
+        match token.parse::<Key>() {
+            Ok(key) => keys.push(key),
+            Err(_) if !token.contains('-') => keys.extend(
+                token
+                    .chars()
+                    .map(|c| Key::new(KeyCode::Char(c), KeyModifiers::NONE)),
+            ),
+            Err(e) => return Err(e),
+        }
+    }
+    if keys.is_empty() {
+        return Err(anyhow!("Empty key sequence"));
+    }
+    Ok(keys)
+}
+
+fn format_sequence(keys: &[Key]) -> String {
+    keys.iter().map(|key| key.to_string()).collect::<String>()
+}
+
+pub enum KeyResolution {
+    Action(Action),
+    /// Keys so far are the beginning of a longer sequence
+    Pending,
+    Unbound,
+}
+
+/// Bindings of one mode in the order shown in the help
+type Bindings = Vec<(Action, Vec<Vec<Key>>)>;
+
+#[derive(Clone)]
+pub struct Keymap {
+    modes: Vec<(Mode, Bindings)>,
+    global: Bindings,
+}
+
+fn bindings(list: &[(Action, &[&str])]) -> Bindings {
+    list.iter()
+        .map(|(action, keys)| {
+            (
+                *action,
+                keys.iter()
+                    .map(|keys| parse_sequence(keys).unwrap())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let picker = bindings(&[
+            (Action::Help, &["?"]),
+            (Action::Cancel, &["esc"]),
+            (Action::Down, &["j", "down"]),
+            (Action::Up, &["k", "up"]),
+            (Action::Decide, &["enter"]),
+        ]);
+        // Cursor motions and deletions shared by the input boxes
+        let editing = |line_start: &[&str]| {
+            bindings(&[
+                (Action::DeleteChar, &["backspace"]),
+                (Action::DeleteNextChar, &["delete"]),
+                (Action::Left, &["left"]),
+                (Action::Right, &["right"]),
+                (Action::LineStart, line_start),
+                (Action::LineEnd, &["end", "ctrl-e"]),
+                (Action::WordLeft, &["alt-b", "ctrl-left"]),
+                (Action::WordRight, &["alt-f", "ctrl-right"]),
+                (Action::DeleteWord, &["ctrl-w"]),
+                (Action::DeleteToStart, &["ctrl-u"]),
+            ])
+        };
+        let history = bindings(&[
+            (Action::HistoryPrevious, &["up"]),
+            (Action::HistoryNext, &["down"]),
+        ]);
+        let text_input = [
+            bindings(&[(Action::Cancel, &["esc"]), (Action::Decide, &["enter"])]),
+            editing(&["home", "ctrl-a"]),
+            history.clone(),
+        ]
+        .concat();
+        Keymap {
+            modes: vec![
+                (
+                    Mode::Normal,
+                    bindings(&[
+                        (Action::Help, &["?"]),
+                        (Action::CommandPalette, &[":"]),
+                        (Action::Quit, &["esc", "q"]),
+                        (Action::Down, &["j", "down"]),
+                        (Action::Up, &["k", "up"]),
+                        (Action::HalfPageDown, &["ctrl-d"]),
+                        (Action::HalfPageUp, &["ctrl-u"]),
+                        (Action::Top, &["g g"]),
+                        (Action::Bottom, &["G"]),
+                        (Action::Edit, &["e"]),
+                        (Action::QuickEdit, &["i"]),
+                        (Action::New, &["n"]),
+                        (Action::NewWithName, &["N"]),
+                        (Action::Delete, &["d"]),
+                        (Action::Search, &["/"]),
+                        (Action::Pin, &["p"]),
+                        (Action::Move, &["m"]),
+                        (Action::SwitchNotebook, &["tab"]),
+                        (Action::SwitchWorkspace, &["w"]),
+                        (Action::TogglePreview, &["v"]),
+                        (Action::ZoomPreview, &["z"]),
+                        (Action::GrowList, &[">"]),
+                        (Action::ShrinkList, &["<"]),
+                        (Action::Undo, &["u"]),
+                        (Action::Redo, &["ctrl-r"]),
+                        // Run from the command palette unless bound in the config
+                        (Action::Rename, &[]),
+                        (Action::Tag, &[]),
+                        (Action::Export, &[]),
+                        (Action::SortByDate, &[]),
+                        (Action::SortByDateDesc, &[]),
+                        (Action::SortByName, &[]),
+                    ]),
+                ),
+                (Mode::Insert, text_input.clone()),
+                (
+                    Mode::DeleteConfirm,
+                    bindings(&[
+                        (Action::Cancel, &["esc", "n"]),
+                        (Action::ConfirmDelete, &["Y"]),
+                    ]),
+                ),
+                (
+                    Mode::Search,
+                    [
+                        bindings(&[
+                            (Action::Cancel, &["esc"]),
+                            (Action::Decide, &["enter"]),
+                            (Action::Down, &["ctrl-j"]),
+                            (Action::Up, &["ctrl-k"]),
+                            (Action::ToggleAllWorkspaces, &["ctrl-a"]),
+                        ]),
+                        // Ctrl-a toggles the search across workspaces
+                        editing(&["home"]),
+                        history,
+                    ]
+                    .concat(),
+                ),
+                (Mode::SelectTemplate, picker.clone()),
+                (Mode::Move, text_input.clone()),
+                (Mode::SelectWorkspace, picker),
+                (
+                    Mode::Help,
+                    bindings(&[
+                        (Action::Cancel, &["esc", "q", "?"]),
+                        (Action::Down, &["j", "down"]),
+                        (Action::Up, &["k", "up"]),
+                    ]),
+                ),
+                (
+                    Mode::Command,
+                    [
+                        bindings(&[
+                            (Action::Cancel, &["esc"]),
+                            (Action::Decide, &["enter"]),
+                            (Action::Down, &["ctrl-j", "down"]),
+                            (Action::Up, &["ctrl-k", "up"]),
+                        ]),
+                        editing(&["home", "ctrl-a"]),
+                    ]
+                    .concat(),
+                ),
+                (Mode::Prompt, text_input),
+                (
+                    Mode::Edit,
+                    bindings(&[
+                        (Action::Cancel, &["esc"]),
+                        (Action::Save, &["ctrl-s"]),
+                        (Action::Undo, &["ctrl-u"]),
+                        (Action::Up, &["up"]),
+                        (Action::Down, &["down"]),
+                        (Action::Left, &["left"]),
+                        (Action::Right, &["right"]),
+                        (Action::LineStart, &["home", "ctrl-a"]),
+                        (Action::LineEnd, &["end", "ctrl-e"]),
+                        (Action::NewLine, &["enter"]),
+                        (Action::DeleteChar, &["backspace"]),
+                        (Action::DeleteNextChar, &["delete"]),
+                    ]),
+                ),
+            ],
+            global: bindings(&[(Action::Suspend, &["ctrl-z"])]),
+        }
+    }
+}
+
+impl Keymap {
+    /// Keys in the config replace the default keys of the action
+    pub fn from_config(config: &KeymapConfig) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        for (mode_name, actions) in config {
+            let bindings = if mode_name == GLOBAL {
+                &mut keymap.global
+            } else {
+                let mode = MODE_NAMES
+                    .iter()
+                    .find(|(_, name)| name == mode_name)
+                    .map(|(mode, _)| *mode)
+                    .ok_or_else(|| anyhow!("Unknown mode in keymap: {}", mode_name))?;
+                keymap.bindings_mut(mode)
+            };
+            for (action_name, keys) in actions {
+                let action = action_name.parse::<Action>()?;
+                let binding = bindings
+                    .iter_mut()
+                    .find(|(available, _)| *available == action)
+                    .ok_or_else(|| {
+                        anyhow!("{} is not available in {} keymap", action_name, mode_name)
+                    })?;
+                binding.1 = keys
+                    .iter()
+                    .map(|keys| parse_sequence(keys))
+                    .collect::<Result<_>>()?;
+            }
+        }
+        keymap.validate()?;
+        Ok(keymap)
+    }
+
+    fn bindings(&self, mode: Mode) -> &Bindings {
+        &self
+            .modes
+            .iter()
+            .find(|(bound, _)| *bound == mode)
+            .unwrap()
+            .1
+    }
+
+    fn bindings_mut(&mut self, mode: Mode) -> &mut Bindings {
+        &mut self
+            .modes
+            .iter_mut()
+            .find(|(bound, _)| *bound == mode)
+            .unwrap()
+            .1
+    }
+
+    fn sequences(&self, mode: Mode) -> impl Iterator<Item = (Action, &Vec<Key>)> {
+        self.bindings(mode)
+            .iter()
+            .chain(self.global.iter())
+            .flat_map(|(action, sequences)| sequences.iter().map(move |keys| (*action, keys)))
+    }
+
+    /// A sequence must not be the beginning of another one, since there is no timeout to tell them apart
+    fn validate(&self) -> Result<()> {
+        for (mode, mode_name) in MODE_NAMES {
+            let sequences = self.sequences(*mode).collect::<Vec<_>>();
+            for (i, (action, keys)) in sequences.iter().enumerate() {
+                for (j, (other_action, other_keys)) in sequences.iter().enumerate() {
+                    if i != j && other_keys.starts_with(keys) {
+                        return Err(anyhow!(
+                            "{} for {} conflicts with {} for {} in {} keymap",
+                            format_sequence(keys),
+                            action.name(),
+                            format_sequence(other_keys),
+                            other_action.name(),
+                            mode_name
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resolve(&self, mode: Mode, keys: &[Key]) -> KeyResolution {
+        let mut is_pending = false;
+        for (action, sequence) in self.sequences(mode) {
+            if sequence.as_slice() == keys {
+                return KeyResolution::Action(action);
+            }
+            if sequence.starts_with(keys) {
+                is_pending = true;
+            }
+        }
+        if is_pending {
+            KeyResolution::Pending
+        } else {
+            KeyResolution::Unbound
+        }
+    }
+
+    /// Every action available in the mode including global ones, whether bound or not
+    pub fn entries(&self, mode: Mode) -> Vec<KeymapEntry> {
+        describe(mode, self.bindings(mode).iter().chain(self.global.iter()))
+    }
+
+    /// e.g. "esc/q: Quit, j/Down: Down". Global bindings are shown in Normal mode.
+    pub fn help(&self, mode: Mode) -> String {
+        let global = if mode == Mode::Normal {
+            self.global.as_slice()
+        } else {
+            &[]
+        };
+        describe(mode, self.bindings(mode).iter().chain(global.iter()))
+            .into_iter()
+            .filter(|entry| !entry.keys.is_empty())
+            .map(|entry| format!("{}: {}", entry.keys, entry.description))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Action with its keys joined by "/" and its description in the mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapEntry {
+    pub action: Action,
+    pub keys: String,
+    pub description: &'static str,
+}
+
+fn describe<'a>(
+    mode: Mode,
+    bindings: impl Iterator<Item = &'a (Action, Vec<Vec<Key>>)>,
+) -> Vec<KeymapEntry> {
+    bindings
+        .map(|(action, sequences)| KeymapEntry {
+            action: *action,
+            keys: sequences
+                .iter()
+                .map(|keys| format_sequence(keys))
+                .collect::<Vec<String>>()
+                .join("/"),
+            description: action.description(mode),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    fn key(s: &str) -> Key {
+        s.parse().unwrap()
+    }
+
+    fn config(mode: &str, action: &str, keys: &[&str]) -> KeymapConfig {
+        let mut actions = BTreeMap::new();
+        actions.insert(action.to_string(), keys.iter().map(|keys| keys.to_string()).collect());
+        let mut config = BTreeMap::new();
+        config.insert(mode.to_string(), actions);
+        config
+    }
+
+    describe "key" {
+        it "ignores shift of characters" {
+            assert_eq!(Key::new(KeyCode::Char('N'), KeyModifiers::SHIFT), key("N"));
+            assert_eq!(key("shift-n"), key("N"));
+        }
+
+        it "parses modifiers and named keys" {
+            assert_eq!(key("ctrl-d"), Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL));
+            assert_eq!(key("Esc"), Key::new(KeyCode::Esc, KeyModifiers::NONE));
+            assert_eq!(key("-"), Key::new(KeyCode::Char('-'), KeyModifiers::NONE));
+            assert!("ctrl-foo".parse::<Key>().is_err());
+        }
+
+        it "parses sequences" {
+            assert_eq!(parse_sequence("gg").unwrap(), vec![key("g"), key("g")]);
+            assert_eq!(parse_sequence("g g").unwrap(), vec![key("g"), key("g")]);
+            assert_eq!(parse_sequence("esc").unwrap(), vec![key("esc")]);
+            assert!(parse_sequence(" ").is_err());
+        }
+    }
+
+    describe "resolve" {
+        before {
+            let keymap = Keymap::default();
+        }
+
+        it "waits for the rest of a sequence" {
+            assert!(matches!(keymap.resolve(Mode::Normal, &[key("g")]), KeyResolution::Pending));
+            assert!(matches!(
+                keymap.resolve(Mode::Normal, &[key("g"), key("g")]),
+                KeyResolution::Action(Action::Top)
+            ));
+        }
+
+        it "includes global bindings" {
+            assert!(matches!(
+                keymap.resolve(Mode::Search, &[key("ctrl-z")]),
+                KeyResolution::Action(Action::Suspend)
+            ));
+            assert!(matches!(keymap.resolve(Mode::Search, &[key("x")]), KeyResolution::Unbound));
+        }
+    }
+
+    describe "from_config" {
+        it "replaces the default keys of the action" {
+            let keymap = Keymap::from_config(&config("normal", "delete", &["d d"])).unwrap();
+            assert!(matches!(keymap.resolve(Mode::Normal, &[key("d")]), KeyResolution::Pending));
+            assert!(matches!(
+                keymap.resolve(Mode::Normal, &[key("d"), key("d")]),
+                KeyResolution::Action(Action::Delete)
+            ));
+        }
+
+        it "rejects conflicting sequences" {
+            assert!(Keymap::from_config(&config("normal", "edit", &["g"])).is_err());
+            assert!(Keymap::from_config(&config("global", "suspend", &["q"])).is_err());
+        }
+
+        it "rejects unknown mode and unavailable action" {
+            assert!(Keymap::from_config(&config("visual", "edit", &["e"])).is_err());
+            assert!(Keymap::from_config(&config("search", "edit", &["e"])).is_err());
+        }
+    }
+
+    describe "help" {
+        it "is generated from the keymap" {
+            let keymap = Keymap::from_config(&config("normal", "edit", &["E"])).unwrap();
+            let help = keymap.help(Mode::Normal);
+            assert!(help.starts_with("?: Help, :: Command palette, esc/q: Quit, j/Down: Down, k/Up: Up"));
+            assert!(!help.contains("Rename"));
+            assert!(help.contains("gg: Top"));
+            assert!(help.contains("E: Edit"));
+            assert!(help.ends_with("^z: Suspend"));
+        }
+
+        it "describes actions by mode" {
+            let keymap = Keymap::default();
+            assert!(keymap.help(Mode::Insert).starts_with("esc: Enter normal mode, Enter: Open editor, Backspace: Delete character, Delete: Delete next character"));
+            assert!(keymap.help(Mode::Insert).contains("Home/^a: Line start"));
+            assert_eq!(keymap.help(Mode::DeleteConfirm), "esc/n: Cancel, Y: Delete");
+        }
+
+        it "lists unbound actions as entries" {
+            let keymap = Keymap::default();
+            let entries = keymap.entries(Mode::Normal);
+            let rename = entries.iter().find(|entry| entry.action == Action::Rename).unwrap();
+            assert_eq!(rename.keys, "");
+            assert_eq!(entries.last().unwrap().action, Action::Suspend);
+        }
+    }
+}
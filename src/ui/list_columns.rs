@@ -0,0 +1,85 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+/// Parts of a kakisute shown in each row of the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    /// Time since creation such as "3h ago"
+    Date,
+    /// File name as it is stored
+    Name,
+    /// Name after the datetime, or the first line of the content for unnamed kakisute
+    Title,
+    Size,
+    Tags,
+}
+
+const LIST_COLUMNS: &[(ListColumn, &str)] = &[
+    (ListColumn::Date, "date"),
+    (ListColumn::Name, "name"),
+    (ListColumn::Title, "title"),
+    (ListColumn::Size, "size"),
+    (ListColumn::Tags, "tags"),
+];
+
+pub const DEFAULT_LIST_COLUMNS: &[ListColumn] = &[
+    ListColumn::Date,
+    ListColumn::Title,
+    ListColumn::Size,
+    ListColumn::Tags,
+];
+
+impl FromStr for ListColumn {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        LIST_COLUMNS
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(column, _)| *column)
+            .ok_or_else(|| anyhow!("Unknown list column: {}", s))
+    }
+}
+
+impl ListColumn {
+    /// Title and name take the width left by the other columns
+    pub fn is_flexible(&self) -> bool {
+        matches!(self, ListColumn::Name | ListColumn::Title)
+    }
+}
+
+/// Columns in the order of the config. The default columns are used if none is given.
+pub fn from_config(config: &[String]) -> Result<Vec<ListColumn>> {
+    if config.is_empty() {
+        return Ok(DEFAULT_LIST_COLUMNS.to_vec());
+    }
+    let mut columns = vec![];
+    for name in config {
+        let column = name.parse::<ListColumn>()?;
+        if columns.contains(&column) {
+            return Err(anyhow!("Duplicated list column: {}", name));
+        }
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "from_config" {
+        it "keeps the order of the config" {
+            let config = vec!["name".to_string(), "date".to_string()];
+            assert_eq!(from_config(&config).unwrap(), vec![ListColumn::Name, ListColumn::Date]);
+            assert_eq!(from_config(&[]).unwrap(), DEFAULT_LIST_COLUMNS.to_vec());
+        }
+
+        it "rejects unknown and duplicated columns" {
+            assert!(from_config(&["color".to_string()]).is_err());
+            assert!(from_config(&["size".to_string(), "size".to_string()]).is_err());
+        }
+    }
+}
@@ -3,62 +3,189 @@ use tui::{
     layout::{Alignment, Rect},
     widgets::Clear,
 };
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    text::Text,
+    style::Style,
+    text::{Span, Spans, Text},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-use super::{app_interactor::Mode, display_data::BlockData};
+use grep::matcher::Matcher;
+use grep::regex::RegexMatcher;
+
+use super::{
+    app_interactor::Mode,
+    display_data::{BlockData, ListRow},
+    list_columns::ListColumn,
+    theme::Theme,
+};
 
 pub const SEARCH_BOX_LENGTH: u16 = 3;
 pub const HELP_BOX_LENGTH: u16 = 3;
+pub const STATUS_BAR_LENGTH: u16 = 1;
+pub const ERROR_MODAL_HEIGHT: u16 = 5;
+pub const INPUT_BOX_HEIGHT: u16 = 3;
+pub const COMMAND_LIST_MAX_HEIGHT: u16 = 12;
 pub const CONTENT_CHUNK_MIN_SIZE: u16 = 3;
 pub const LIST_WIDTH_PERCENT: u16 = 20;
-pub const CONTENT_WIDTH_PERCENT: u16 = 80;
+pub const MIN_LIST_WIDTH_PERCENT: u16 = 10;
+pub const MAX_LIST_WIDTH_PERCENT: u16 = 80;
+/// Terminals narrower than this show the list above the content
+pub const NARROW_WIDTH: u16 = 80;
+pub const STACKED_LIST_HEIGHT_PERCENT: u16 = 40;
 pub const MARGIN: u16 = 1;
+pub const PINNED_MARK: &str = "* ";
+const COLUMN_SEPARATOR: &str = " ";
+const ELLIPSIS: &str = "…";
+/// Title and name columns are not narrowed below this
+const MIN_FLEXIBLE_COLUMN_WIDTH: usize = 8;
+
+/// How the list and the content share the main area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneSettings {
+    pub list_width_percent: u16,
+    pub show_preview: bool,
+    /// The content takes the whole main area
+    pub zoom_preview: bool,
+}
+
+impl Default for PaneSettings {
+    fn default() -> Self {
+        Self {
+            list_width_percent: LIST_WIDTH_PERCENT,
+            show_preview: true,
+            zoom_preview: false,
+        }
+    }
+}
+
+/// Areas of the panes. The mouse handler uses them as well to find what is under the cursor.
+/// Hidden panes are None.
+pub struct MainLayout {
+    pub list: Option<Rect>,
+    pub content: Option<Rect>,
+    /// The list is above the content on narrow terminals
+    pub is_stacked: bool,
+    pub search_box: Option<Rect>,
+    pub help: Rect,
+    pub status_bar: Rect,
+}
 
 /// Build main layout
 /// If need_search_box is true, build layout with search box
-fn build_main_layout<B: Backend>(f: &mut Frame<B>, need_search_box: bool) -> Vec<Rect> {
+pub fn build_main_layout(size: Rect, need_search_box: bool, panes: PaneSettings) -> MainLayout {
+    let mut constraints = vec![Constraint::Min(CONTENT_CHUNK_MIN_SIZE)];
     if need_search_box {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .margin(MARGIN)
-            .constraints(
-                [
-                    Constraint::Min(CONTENT_CHUNK_MIN_SIZE),
-                    Constraint::Length(SEARCH_BOX_LENGTH),
-                    Constraint::Length(HELP_BOX_LENGTH),
-                ]
-                .as_ref(),
-            )
-            .split(f.size())
+        constraints.push(Constraint::Length(SEARCH_BOX_LENGTH));
+    }
+    constraints.push(Constraint::Length(HELP_BOX_LENGTH));
+    constraints.push(Constraint::Length(STATUS_BAR_LENGTH));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(MARGIN)
+        .constraints(constraints)
+        .split(size);
+    let is_stacked = size.width < NARROW_WIDTH;
+    let (list, content) = if panes.zoom_preview {
+        (None, Some(chunks[0]))
+    } else if !panes.show_preview {
+        (Some(chunks[0]), None)
     } else {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .margin(MARGIN)
+        let (direction, list_percent) = if is_stacked {
+            (Direction::Vertical, STACKED_LIST_HEIGHT_PERCENT)
+        } else {
+            (Direction::Horizontal, panes.list_width_percent)
+        };
+        let content_chunk = Layout::default()
+            .direction(direction)
             .constraints(
                 [
-                    Constraint::Min(CONTENT_CHUNK_MIN_SIZE),
-                    Constraint::Length(HELP_BOX_LENGTH),
+                    Constraint::Percentage(list_percent),
+                    Constraint::Percentage(100 - list_percent),
                 ]
                 .as_ref(),
             )
-            .split(f.size())
+            .split(chunks[0]);
+        (Some(content_chunk[0]), Some(content_chunk[1]))
+    };
+    MainLayout {
+        list,
+        content,
+        is_stacked,
+        search_box: if need_search_box {
+            Some(chunks[1])
+        } else {
+            None
+        },
+        help: chunks[chunks.len() - 2],
+        status_bar: chunks[chunks.len() - 1],
     }
 }
 
-fn generate_filename_list<'a>(kakisute_list: BlockData<Vec<&'a str>>, mode: &Mode) -> List<'a> {
+/// Index of the first visible item. The list state is created every frame,
+/// so the list scrolls only as far as needed to show the selected item at the bottom.
+pub fn list_offset(selected: Option<usize>, list_area: Rect) -> usize {
+    // -2 for borders
+    let height = list_area.height.saturating_sub(2).max(1) as usize;
+    selected.map_or(0, |selected| selected.saturating_sub(height - 1))
+}
+
+/// Pinned kakisute are placed at the top of the list, so marking them makes a separate section
+fn generate_filename_list<'a>(
+    kakisute_list: BlockData<Vec<ListRow>>,
+    mode: &Mode,
+    list_width: u16,
+    theme: &Theme,
+) -> List<'a> {
+    let has_pinned = kakisute_list.body.iter().any(|row| row.pinned);
+    let mark_width = if has_pinned { PINNED_MARK.width() } else { 0 };
+    // -2 for borders and -2 for the highlight symbol
+    let available = (list_width as usize).saturating_sub(4 + mark_width);
+    let widths = column_widths(&kakisute_list.body, available);
     let file_names = kakisute_list
         .body
         .iter()
-        .map(|file_name| ListItem::new(file_name.to_string()))
+        .map(|row| {
+            let mut spans = vec![];
+            if row.pinned {
+                spans.push(Span::styled(PINNED_MARK, theme.badge));
+            } else if has_pinned {
+                spans.push(Span::raw(" ".repeat(mark_width)));
+            }
+            let mut prefix = row.prefix.as_str();
+            let last = row.cells.len().saturating_sub(1);
+            for (i, ((column, text), width)) in row.cells.iter().zip(&widths).enumerate() {
+                if i > 0 {
+                    spans.push(Span::raw(COLUMN_SEPARATOR));
+                }
+                // The last column is not padded, so that nothing is clipped needlessly
+                let width = if i == last { usize::MAX } else { *width };
+                match column {
+                    // Notebook is shown in another color so that grouped kakisute are easy to find
+                    column if column.is_flexible() && !prefix.is_empty() => {
+                        let cell = fit(&format!("{}{}", prefix, text), width, false);
+                        match cell.strip_prefix(prefix) {
+                            Some(rest) => {
+                                spans.push(Span::styled(prefix.to_string(), theme.notebook));
+                                spans.push(Span::raw(rest.to_string()));
+                            }
+                            None => spans.push(Span::raw(cell)),
+                        }
+                        prefix = "";
+                    }
+                    ListColumn::Size => spans.push(Span::raw(fit(text, width, true))),
+                    ListColumn::Tags => {
+                        spans.push(Span::styled(fit(text, width, false), theme.badge))
+                    }
+                    _ => spans.push(Span::raw(fit(text, width, false))),
+                }
+            }
+            ListItem::new(Spans::from(spans))
+        })
         .collect::<Vec<ListItem>>();
 
     List::new(file_names)
@@ -67,28 +194,212 @@ fn generate_filename_list<'a>(kakisute_list: BlockData<Vec<&'a str>>, mode: &Mod
                 .title(kakisute_list.title.clone())
                 .borders(Borders::ALL)
                 .border_style(match mode {
-                    Mode::Normal => Style::default().fg(Color::Blue),
-                    _ => Style::default(),
+                    Mode::Normal => theme.focused_border,
+                    _ => theme.border,
                 }),
         )
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+        .highlight_style(theme.highlight)
         .highlight_symbol(">>")
 }
 
-fn generate_kakisute_content<'a>(content: BlockData<String>) -> Paragraph<'a> {
-    Paragraph::new(Text::from(content.body))
-        .wrap(Wrap { trim: false })
-        .block(Block::default().title(content.title).borders(Borders::ALL))
+/// Every column is as wide as its widest cell, and the title and name columns give up
+/// the width which does not fit in the list.
+fn column_widths(rows: &[ListRow], available: usize) -> Vec<usize> {
+    let columns = rows.first().map_or(0, |row| row.cells.len());
+    let mut widths = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .map(|row| {
+                    let (column, text) = &row.cells[i];
+                    let prefix = if column.is_flexible() {
+                        row.prefix.width()
+                    } else {
+                        0
+                    };
+                    prefix + text.width()
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<usize>>();
+    let flexible = rows.first().map_or(vec![], |row| {
+        (0..columns)
+            .filter(|&i| row.cells[i].0.is_flexible())
+            .collect::<Vec<usize>>()
+    });
+    if flexible.is_empty() {
+        return widths;
+    }
+    let fixed = (0..columns)
+        .filter(|i| !flexible.contains(i))
+        .map(|i| widths[i])
+        .sum::<usize>()
+        + COLUMN_SEPARATOR.len() * columns.saturating_sub(1);
+    let share = (available.saturating_sub(fixed) / flexible.len()).max(MIN_FLEXIBLE_COLUMN_WIDTH);
+    for i in flexible {
+        widths[i] = widths[i].min(share);
+    }
+    widths
+}
+
+/// Pads or truncates the text to the display width, marking truncation with an ellipsis
+fn fit(text: &str, width: usize, align_right: bool) -> String {
+    let text_width = text.width();
+    if text_width > width {
+        let mut fitted = String::new();
+        let mut fitted_width = 0;
+        for c in text.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if fitted_width + char_width + ELLIPSIS.width() > width {
+                break;
+            }
+            fitted.push(c);
+            fitted_width += char_width;
+        }
+        if width >= ELLIPSIS.width() {
+            fitted.push_str(ELLIPSIS);
+            fitted_width += ELLIPSIS.width();
+        }
+        return fitted + &" ".repeat(width - fitted_width);
+    }
+    if width == usize::MAX {
+        return text.to_string();
+    }
+    let padding = " ".repeat(width - text_width);
+    if align_right {
+        padding + text
+    } else {
+        text.to_string() + &padding
+    }
+}
+
+fn generate_kakisute_content<'a>(
+    content: BlockData<String>,
+    highlight: &str,
+    scroll: u16,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    Paragraph::new(highlight_matches(
+        content.body,
+        highlight,
+        theme.search_match,
+    ))
+    .wrap(Wrap { trim: false })
+    .scroll((scroll, 0))
+    .block(
+        Block::default()
+            .title(content.title)
+            .borders(Borders::ALL)
+            .border_style(theme.border),
+    )
+}
+
+/// The input is scrolled horizontally to keep the cursor inside the box.
+/// The cursor is the display width before it so that wide characters take two cells.
+fn render_input<B: Backend>(f: &mut Frame<B>, input: Paragraph, area: Rect, cursor: usize) {
+    let scroll = input_scroll(cursor, area.width.saturating_sub(2));
+    f.render_widget(input.scroll((0, scroll as u16)), area);
+    if area.width > 2 && area.height > 2 {
+        f.set_cursor(area.x + 1 + (cursor - scroll) as u16, area.y + 1);
+    }
+}
+
+/// Columns hidden on the left so that the cursor fits in the width
+fn input_scroll(cursor: usize, width: u16) -> usize {
+    (cursor + 1).saturating_sub(width as usize)
+}
+
+/// Lines are not wrapped so that the cursor stays on its line.
+/// The text is scrolled to keep the cursor inside the pane.
+fn render_editor<B: Backend>(
+    f: &mut Frame<B>,
+    content: BlockData<String>,
+    (row, column): (usize, usize),
+    area: Rect,
+    theme: &Theme,
+) {
+    let height = area.height.saturating_sub(2) as usize;
+    let width = area.width.saturating_sub(2) as usize;
+    let scroll_y = (row + 1).saturating_sub(height);
+    let scroll_x = input_scroll(column, width as u16);
+    let editor = Paragraph::new(content.body)
+        .scroll((scroll_y as u16, scroll_x as u16))
+        .block(
+            Block::default()
+                .title(content.title)
+                .borders(Borders::ALL)
+                .border_style(theme.focused_border),
+        );
+    f.render_widget(editor, area);
+    if height > 0 && width > 0 {
+        f.set_cursor(
+            area.x + 1 + (column - scroll_x) as u16,
+            area.y + 1 + (row - scroll_y) as u16,
+        );
+    }
+}
+
+/// Matches are found line by line with the regex of the text the search query matched in contents.
+/// Nothing is highlighted while the query is empty or invalid.
+pub fn highlight_matches<'a>(content: String, query: &str, style: Style) -> Text<'a> {
+    let matcher = match RegexMatcher::new(query) {
+        Ok(matcher) if !query.is_empty() => matcher,
+        _ => return Text::from(content),
+    };
+    let lines = content
+        .lines()
+        .map(|line| {
+            let mut spans = vec![];
+            let mut last = 0;
+            let _ = matcher.find_iter(line.as_bytes(), |m| {
+                if let (Some(before), Some(matched)) =
+                    (line.get(last..m.start()), line.get(m.start()..m.end()))
+                {
+                    if !before.is_empty() {
+                        spans.push(Span::raw(before.to_string()));
+                    }
+                    spans.push(Span::styled(matched.to_string(), style));
+                    last = m.end();
+                }
+                true
+            });
+            if last < line.len() {
+                spans.push(Span::raw(line[last..].to_string()));
+            }
+            Spans::from(spans)
+        })
+        .collect::<Vec<Spans>>();
+    Text::from(lines)
+}
+
+fn generate_help<'a>(help: BlockData<String>, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(Text::from(help.body)).block(
+        Block::default()
+            .title(help.title)
+            .borders(Borders::ALL)
+            .border_style(theme.border),
+    )
+}
+
+fn generate_status_bar<'a>(status_message: String, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(status_message).style(theme.status_bar)
 }
 
-fn generate_help<'a>(help: BlockData<String>) -> Paragraph<'a> {
-    Paragraph::new(Text::from(help.body))
-        .block(Block::default().title(help.title).borders(Borders::ALL))
+fn generate_error_modal<'a>(error_modal: BlockData<String>, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(error_modal.body)
+        .style(theme.error_modal)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(error_modal.title)
+                .title_alignment(Alignment::Center),
+        )
 }
 
-fn generate_input_box<'a>(new_filename: &BlockData<String>) -> Paragraph<'a> {
+fn generate_input_box<'a>(new_filename: &BlockData<String>, theme: &Theme) -> Paragraph<'a> {
     Paragraph::new(new_filename.body.clone())
-        .style(Style::default().fg(Color::Blue))
+        .style(theme.input)
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -97,15 +408,19 @@ fn generate_input_box<'a>(new_filename: &BlockData<String>) -> Paragraph<'a> {
         )
 }
 
-fn generate_search_box<'a>(search_query: &BlockData<String>, mode: &Mode) -> Paragraph<'a> {
-    // Use blue color only if focused
-    let border_color = if mode == &Mode::Search {
-        Color::Blue
+fn generate_search_box<'a>(
+    search_query: &BlockData<String>,
+    mode: &Mode,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    // Use the focused style only if focused
+    let style = if mode == &Mode::Search {
+        theme.focused_border
     } else {
-        Color::White
+        theme.border
     };
     Paragraph::new(search_query.body.clone())
-        .style(Style::default().fg(border_color))
+        .style(style)
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -113,9 +428,9 @@ fn generate_search_box<'a>(search_query: &BlockData<String>, mode: &Mode) -> Par
         )
 }
 
-fn generate_delete_modal<'a>(delete_modal: &BlockData<&'a str>) -> Paragraph<'a> {
+fn generate_delete_modal<'a>(delete_modal: &BlockData<&'a str>, theme: &Theme) -> Paragraph<'a> {
     Paragraph::new(delete_modal.body)
-        .style(Style::default().fg(Color::Red))
+        .style(theme.delete_modal)
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -124,62 +439,200 @@ fn generate_delete_modal<'a>(delete_modal: &BlockData<&'a str>) -> Paragraph<'a>
         )
 }
 
-pub fn render<B: Backend>(f: &mut Frame<B>, display_data: DisplayData) {
-    let chunks = build_main_layout(f, display_data.need_search_box);
-    let content_chunk = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage(LIST_WIDTH_PERCENT),
-                Constraint::Percentage(CONTENT_WIDTH_PERCENT),
-            ]
-            .as_ref(),
+fn generate_picker<'a>(picker: &BlockData<Vec<String>>, theme: &Theme) -> List<'a> {
+    let items = picker
+        .body
+        .iter()
+        .map(|template| ListItem::new(template.clone()))
+        .collect::<Vec<ListItem>>();
+
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(picker.title.clone())
+                .title_alignment(Alignment::Center),
         )
-        .split(chunks[0]);
+        .highlight_style(theme.highlight)
+        .highlight_symbol(">>")
+}
 
-    let filename_list = generate_filename_list(display_data.kakisute_list, &display_data.mode);
-    let mut state = ListState::default();
-    state.select(display_data.index);
-    f.render_stateful_widget(filename_list, content_chunk[0], &mut state);
+pub fn render<B: Backend>(f: &mut Frame<B>, display_data: DisplayData, theme: &Theme) {
+    let layout = build_main_layout(f.size(), display_data.need_search_box, display_data.panes);
 
-    let kakisute_content = generate_kakisute_content(display_data.content);
-    f.render_widget(kakisute_content, content_chunk[1]);
+    if let Some(list_area) = layout.list {
+        let filename_list = generate_filename_list(
+            display_data.kakisute_list,
+            &display_data.mode,
+            list_area.width,
+            theme,
+        );
+        let mut state = ListState::default();
+        state.select(display_data.index);
+        f.render_stateful_widget(filename_list, list_area, &mut state);
+    }
+
+    if let Some(content_area) = layout.content {
+        match display_data.editor_cursor {
+            Some(cursor) => render_editor(f, display_data.content, cursor, content_area, theme),
+            None => {
+                let kakisute_content = generate_kakisute_content(
+                    display_data.content,
+                    &display_data.content_highlight,
+                    display_data.content_scroll,
+                    theme,
+                );
+                f.render_widget(kakisute_content, content_area);
+            }
+        }
+    }
 
-    let help = generate_help(display_data.help);
-    f.render_widget(help, chunks[chunks.len() - 1]);
+    let help = generate_help(display_data.help, theme);
+    f.render_widget(help, layout.help);
 
-    if display_data.need_search_box {
-        let search_box = generate_search_box(&display_data.search_query, &display_data.mode);
-        f.render_widget(search_box, chunks[1]);
+    let status_bar = generate_status_bar(display_data.status_message, theme);
+    f.render_widget(status_bar, layout.status_bar);
 
+    if let Some(search_box_area) = layout.search_box {
+        let search_box = generate_search_box(&display_data.search_query, &display_data.mode, theme);
         // Show the cursor when in search mode
         if display_data.mode == Mode::Search {
-            f.set_cursor(
-                chunks[1].x + display_data.search_query.body.width_cjk() as u16 + 1,
-                chunks[1].y + 1,
-            )
+            render_input(f, search_box, search_box_area, display_data.input_cursor);
+        } else {
+            f.render_widget(search_box, search_box_area);
         }
     }
 
     match display_data.mode {
         Mode::Insert => {
-            let input = generate_input_box(&display_data.new_filename);
+            let input = generate_input_box(&display_data.new_filename, theme);
             let area = centered_rect(50, 3, f.size());
             f.render_widget(Clear, area); //this clears out the background
-            f.render_widget(input, area);
-            f.set_cursor(
-                area.x + display_data.new_filename.body.width_cjk() as u16 + 1,
-                area.y + 1,
-            )
+            render_input(f, input, area, display_data.input_cursor);
         }
         Mode::DeleteConfirm => {
-            let input = generate_delete_modal(&display_data.delete_modal);
+            let input = generate_delete_modal(&display_data.delete_modal, theme);
             let area = centered_rect(50, 3, f.size());
             f.render_widget(Clear, area); //this clears out the background
             f.render_widget(input, area);
         }
+        Mode::Move => {
+            let input = generate_input_box(&display_data.move_destination, theme);
+            let area = centered_rect(50, 3, f.size());
+            f.render_widget(Clear, area); //this clears out the background
+            render_input(f, input, area, display_data.input_cursor);
+        }
+        Mode::SelectTemplate => {
+            render_picker(
+                f,
+                &display_data.template_list,
+                display_data.template_index,
+                theme,
+            );
+        }
+        Mode::SelectWorkspace => {
+            render_picker(
+                f,
+                &display_data.workspace_list,
+                display_data.workspace_index,
+                theme,
+            );
+        }
+        Mode::Help => {
+            render_help_overlay(
+                f,
+                &display_data.help_overlay,
+                display_data.help_scroll,
+                theme,
+            );
+        }
+        Mode::Command => {
+            render_command_palette(
+                f,
+                &display_data.command_query,
+                &display_data.command_list,
+                display_data.command_index,
+                display_data.input_cursor,
+                theme,
+            );
+        }
+        Mode::Prompt => {
+            let input = generate_input_box(&display_data.prompt_input, theme);
+            let area = centered_rect(50, INPUT_BOX_HEIGHT, f.size());
+            f.render_widget(Clear, area); //this clears out the background
+            render_input(f, input, area, display_data.input_cursor);
+        }
         _ => {}
     }
+
+    // Errors are shown above everything else
+    if let Some(error_modal) = display_data.error_modal {
+        let area = centered_rect(60, ERROR_MODAL_HEIGHT.min(f.size().height), f.size());
+        f.render_widget(Clear, area); //this clears out the background
+        f.render_widget(generate_error_modal(error_modal, theme), area);
+    }
+}
+
+fn render_picker<B: Backend>(
+    f: &mut Frame<B>,
+    picker: &BlockData<Vec<String>>,
+    index: Option<usize>,
+    theme: &Theme,
+) {
+    let list = generate_picker(picker, theme);
+    // +2 for borders
+    let height = (picker.body.len() as u16 + 2).min(f.size().height);
+    let area = centered_rect(50, height, f.size());
+    let mut state = ListState::default();
+    state.select(index);
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_help_overlay<B: Backend>(
+    f: &mut Frame<B>,
+    help: &BlockData<Vec<String>>,
+    scroll: u16,
+    theme: &Theme,
+) {
+    let paragraph = Paragraph::new(help.body.join("\n"))
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(help.title.clone())
+                .title_alignment(Alignment::Center)
+                .border_style(theme.border),
+        );
+    // +2 for borders
+    let height = (help.body.len() as u16 + 2).min(f.size().height);
+    let area = centered_rect(60, height, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(paragraph, area);
+}
+
+/// Input box with the matching commands below
+fn render_command_palette<B: Backend>(
+    f: &mut Frame<B>,
+    query: &BlockData<String>,
+    commands: &BlockData<Vec<String>>,
+    index: Option<usize>,
+    cursor: usize,
+    theme: &Theme,
+) {
+    // +2 for borders
+    let list_height = (commands.body.len() as u16 + 2).min(COMMAND_LIST_MAX_HEIGHT);
+    let height = (INPUT_BOX_HEIGHT + list_height).min(f.size().height);
+    let area = centered_rect(50, height, f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(INPUT_BOX_HEIGHT), Constraint::Min(0)].as_ref())
+        .split(area);
+    f.render_widget(Clear, area); //this clears out the background
+    let mut state = ListState::default();
+    state.select(index);
+    f.render_stateful_widget(generate_picker(commands, theme), chunks[1], &mut state);
+    render_input(f, generate_input_box(query, theme), chunks[0], cursor);
 }
 
 fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
@@ -187,9 +640,10 @@ fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Percentage(50),
+                // Leftover height is split so that tall popups stay inside the screen
+                Constraint::Length(r.height.saturating_sub(height) / 2),
                 Constraint::Length(height),
-                Constraint::Percentage(50),
+                Constraint::Min(0),
             ]
             .as_ref(),
         )
@@ -207,3 +661,114 @@ fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
         )
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "build_main_layout" {
+        it "puts the list beside the content on wide terminals" {
+            let layout = build_main_layout(Rect::new(0, 0, 100, 30), false, PaneSettings::default());
+            let (list, content) = (layout.list.unwrap(), layout.content.unwrap());
+            assert!(!layout.is_stacked);
+            assert_eq!(list.y, content.y);
+            assert_eq!(list.width, 19);
+        }
+
+        it "stacks the panes on narrow terminals" {
+            let layout = build_main_layout(Rect::new(0, 0, 60, 30), true, PaneSettings::default());
+            let (list, content) = (layout.list.unwrap(), layout.content.unwrap());
+            assert!(layout.is_stacked);
+            assert_eq!(list.x, content.x);
+            assert!(list.y < content.y);
+            assert!(layout.search_box.is_some());
+        }
+
+        it "hides panes" {
+            let zoomed = PaneSettings { zoom_preview: true, ..PaneSettings::default() };
+            let layout = build_main_layout(Rect::new(0, 0, 100, 30), false, zoomed);
+            assert!(layout.list.is_none());
+            assert_eq!(layout.content.unwrap().width, 98);
+
+            let hidden = PaneSettings { show_preview: false, ..PaneSettings::default() };
+            let layout = build_main_layout(Rect::new(0, 0, 100, 30), false, hidden);
+            assert!(layout.content.is_none());
+        }
+    }
+
+    describe "column_widths" {
+        before {
+            let row = |title: &str, size: &str| ListRow {
+                pinned: false,
+                prefix: String::new(),
+                cells: vec![
+                    (ListColumn::Date, "3h ago".to_string()),
+                    (ListColumn::Title, title.to_string()),
+                    (ListColumn::Size, size.to_string()),
+                ],
+            };
+            let rows = vec![row("short", "512B"), row("a very long title", "1.5K")];
+        }
+
+        it "fits the widest cells" {
+            assert_eq!(column_widths(&rows, 100), vec![6, 17, 4]);
+        }
+
+        it "narrows the title to the available width" {
+            assert_eq!(column_widths(&rows, 22), vec![6, 10, 4]);
+            assert_eq!(column_widths(&rows, 0), vec![6, MIN_FLEXIBLE_COLUMN_WIDTH, 4]);
+        }
+    }
+
+    describe "fit" {
+        it "pads and truncates to the width" {
+            assert_eq!(fit("abc", 5, false), "abc  ");
+            assert_eq!(fit("abc", 5, true), "  abc");
+            assert_eq!(fit("abcdef", 4, false), "abc…");
+            assert_eq!(fit("日本語", 4, false), "日… ");
+            assert_eq!(fit("abc", usize::MAX, false), "abc");
+        }
+    }
+
+    describe "highlight_matches" {
+        it "styles every match of the query" {
+            let style = Theme::default().search_match;
+            let text = highlight_matches("foo bar foo\nbaz".to_string(), "fo+", style);
+            assert_eq!(
+                text.lines[0].0,
+                vec![
+                    Span::styled("foo", style),
+                    Span::raw(" bar "),
+                    Span::styled("foo", style),
+                ]
+            );
+            assert_eq!(text.lines[1].0, vec![Span::raw("baz")]);
+        }
+
+        it "highlights nothing for empty or invalid queries" {
+            let style = Theme::default().search_match;
+            assert_eq!(highlight_matches("foo".to_string(), "", style), Text::from("foo"));
+            assert_eq!(highlight_matches("foo(".to_string(), "(", style), Text::from("foo("));
+        }
+    }
+
+    describe "list_offset" {
+        it "scrolls only as far as the selected item" {
+            let list_area = Rect::new(0, 0, 10, 7);
+            assert_eq!(list_offset(Some(4), list_area), 0);
+            assert_eq!(list_offset(Some(5), list_area), 1);
+            assert_eq!(list_offset(None, list_area), 0);
+            assert_eq!(list_offset(Some(3), Rect::new(0, 0, 10, 1)), 3);
+        }
+    }
+
+    describe "input_scroll" {
+        it "keeps the cursor inside the box" {
+            assert_eq!(input_scroll(9, 10), 0);
+            assert_eq!(input_scroll(10, 10), 1);
+            assert_eq!(input_scroll(24, 10), 15);
+            assert_eq!(input_scroll(3, 0), 4);
+        }
+    }
+}
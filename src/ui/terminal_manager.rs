@@ -1,41 +1,121 @@
 use crate::ui::display_data::DisplayData;
 
 use std::io;
+use std::panic;
+use std::sync::Once;
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    cursor::Show,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use tui::{backend::Backend, Terminal};
+use tui::{backend::Backend, layout::Rect, Terminal};
 
 use anyhow::Result;
 
 use super::renderer::render;
+use super::theme::Theme;
 
 pub struct TerminalManager<B: Backend> {
     terminal: Terminal<B>,
+    /// Entering and exiting are skipped when already done, so that the app screen can be
+    /// restored after an error without knowing where it happened
+    is_in_app_screen: bool,
+    theme: Theme,
 }
 
+static INSTALL_RESTORE_HOOKS: Once = Once::new();
+
 impl<B: Backend> TerminalManager<B> {
-    pub fn new(terminal: Terminal<B>) -> Self {
-        Self { terminal }
+    pub fn new(terminal: Terminal<B>, theme: Theme) -> Self {
+        INSTALL_RESTORE_HOOKS.call_once(install_restore_hooks);
+        Self {
+            terminal,
+            is_in_app_screen: false,
+            theme,
+        }
+    }
+}
+
+/// Errors are ignored because this is the last resort and the terminal may be gone
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = crossterm::execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        Show
+    );
+}
+
+/// Restore the terminal before the panic message is printed or the process is killed
+fn install_restore_hooks() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::{SIGHUP, SIGTERM};
+        use signal_hook::iterator::Signals;
+
+        if let Ok(mut signals) = Signals::new([SIGTERM, SIGHUP]) {
+            std::thread::spawn(move || {
+                if let Some(signal) = signals.forever().next() {
+                    restore_terminal();
+                    // Exiting here skips the destructors which empty the trash
+                    crate::repository::remove_session_trash();
+                    std::process::exit(128 + signal);
+                }
+            });
+        }
+    }
+}
+
+impl<B: Backend> Drop for TerminalManager<B> {
+    fn drop(&mut self) {
+        if self.is_in_app_screen {
+            restore_terminal();
+        }
     }
 }
 
 impl<B: Backend> TerminalManage for TerminalManager<B> {
     fn enter_app_screen(&mut self) -> Result<()> {
+        if self.is_in_app_screen {
+            return Ok(());
+        }
         enable_raw_mode()?;
-        crossterm::execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        crossterm::execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        self.is_in_app_screen = true;
         Ok(())
     }
 
     fn draw_frame(&mut self, display_data: DisplayData) -> Result<()> {
-        self.terminal.draw(|f| render(f, display_data))?;
+        let theme = &self.theme;
+        self.terminal.draw(|f| render(f, display_data, theme))?;
         Ok(())
     }
     fn exit_app_screen(&mut self) -> Result<()> {
+        if !self.is_in_app_screen {
+            return Ok(());
+        }
         disable_raw_mode()?;
-        crossterm::execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
+        self.is_in_app_screen = false;
         Ok(())
     }
 
@@ -44,8 +124,21 @@ impl<B: Backend> TerminalManage for TerminalManager<B> {
         Ok(())
     }
 
-    fn get_terminal_height(&self) -> Result<u16> {
-        Ok(self.terminal.size().unwrap().height)
+    fn get_terminal_size(&self) -> Result<Rect> {
+        Ok(self.terminal.size()?)
+    }
+
+    /// Raw mode delivers Ctrl-Z as a key, so stop the process by ourselves after leaving the app screen
+    fn suspend(&mut self) -> Result<()> {
+        #[cfg(unix)]
+        {
+            self.exit_app_screen()?;
+            // Returns when the process is resumed by SIGCONT, e.g. `fg`
+            signal_hook::low_level::raise(signal_hook::consts::SIGTSTP)?;
+            self.enter_app_screen()?;
+            self.clear_app_screen()?;
+        }
+        Ok(())
     }
 }
 
@@ -54,5 +147,6 @@ pub trait TerminalManage {
     fn draw_frame(&mut self, display_data: DisplayData) -> Result<()>;
     fn exit_app_screen(&mut self) -> Result<()>;
     fn clear_app_screen(&mut self) -> Result<()>;
-    fn get_terminal_height(&self) -> Result<u16>;
+    fn get_terminal_size(&self) -> Result<Rect>;
+    fn suspend(&mut self) -> Result<()>;
 }
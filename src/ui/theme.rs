@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use anyhow::{anyhow, Result};
+use tui::style::{Color, Modifier, Style};
+
+/// Disables colors when set to a non-empty value. See https://no-color.org
+const NO_COLOR_ENV: &str = "NO_COLOR";
+/// Key in the theme config selecting the built-in theme to start from
+const NAME_KEY: &str = "name";
+
+pub const THEME_NAMES: [&str; 4] = ["dark", "light", "high-contrast", "no-color"];
+
+/// Config entry name to the style it overrides
+pub type ThemeConfig = BTreeMap<String, String>;
+
+/// Styles of every colored part of the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub border: Style,
+    /// Border of the pane receiving keys
+    pub focused_border: Style,
+    /// Selected item of lists and pickers
+    pub highlight: Style,
+    /// Badges such as the pinned mark
+    pub badge: Style,
+    pub notebook: Style,
+    pub search_match: Style,
+    pub input: Style,
+    pub status_bar: Style,
+    pub delete_modal: Style,
+    pub error_modal: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            border: Style::default(),
+            focused_border: Style::default().fg(Color::Blue),
+            highlight: Style::default().bg(Color::Blue).fg(Color::Black),
+            badge: Style::default().fg(Color::Yellow),
+            notebook: Style::default().fg(Color::Cyan),
+            search_match: Style::default().bg(Color::Yellow).fg(Color::Black),
+            input: Style::default().fg(Color::Blue),
+            status_bar: Style::default().fg(Color::Green),
+            delete_modal: Style::default().fg(Color::Red),
+            error_modal: Style::default().fg(Color::Red),
+        }
+    }
+
+    /// Avoids yellow and light colors, which are hard to read on a white background
+    pub fn light() -> Self {
+        Theme {
+            border: Style::default(),
+            focused_border: Style::default().fg(Color::Blue),
+            highlight: Style::default().bg(Color::Blue).fg(Color::White),
+            badge: Style::default().fg(Color::Magenta),
+            notebook: Style::default().fg(Color::Blue),
+            search_match: Style::default().bg(Color::LightYellow).fg(Color::Black),
+            input: Style::default().fg(Color::Blue),
+            status_bar: Style::default().fg(Color::Green),
+            delete_modal: Style::default().fg(Color::Red),
+            error_modal: Style::default().fg(Color::Red),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            border: Style::default().fg(Color::White),
+            focused_border: bold.fg(Color::LightYellow),
+            highlight: bold.bg(Color::White).fg(Color::Black),
+            badge: bold.fg(Color::LightYellow),
+            notebook: bold.fg(Color::LightCyan),
+            search_match: bold.bg(Color::LightYellow).fg(Color::Black),
+            input: bold.fg(Color::White),
+            status_bar: bold.fg(Color::LightGreen),
+            delete_modal: bold.fg(Color::LightRed),
+            error_modal: bold.fg(Color::LightRed),
+        }
+    }
+
+    /// Only modifiers are used so that the highlight and matches are still visible
+    pub fn no_color() -> Self {
+        let plain = Style::default();
+        Theme {
+            border: plain,
+            focused_border: plain.add_modifier(Modifier::BOLD),
+            highlight: plain.add_modifier(Modifier::REVERSED),
+            badge: plain.add_modifier(Modifier::BOLD),
+            notebook: plain,
+            search_match: plain.add_modifier(Modifier::UNDERLINED),
+            input: plain,
+            status_bar: plain,
+            delete_modal: plain.add_modifier(Modifier::BOLD),
+            error_modal: plain.add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "high-contrast" => Ok(Theme::high_contrast()),
+            "no-color" => Ok(Theme::no_color()),
+            _ => Err(anyhow!(
+                "Unknown theme: {} (available: {})",
+                name,
+                THEME_NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Built-in theme selected by `name` with the other entries overriding its styles.
+    /// NO_COLOR wins over the config.
+    pub fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let no_color = env::var(NO_COLOR_ENV).is_ok_and(|value| !value.is_empty());
+        Theme::from_config_with(config, no_color)
+    }
+
+    fn from_config_with(config: &ThemeConfig, no_color: bool) -> Result<Self> {
+        let mut theme = Theme::by_name(config.get(NAME_KEY).map_or("dark", |name| name))?;
+        for (element, style) in config.iter().filter(|(key, _)| *key != NAME_KEY) {
+            *theme.style_mut(element)? = parse_style(style)?;
+        }
+        if no_color {
+            // Overrides are still validated so that a broken config is noticed
+            return Ok(Theme::no_color());
+        }
+        Ok(theme)
+    }
+
+    fn style_mut(&mut self, element: &str) -> Result<&mut Style> {
+        Ok(match element {
+            "border" => &mut self.border,
+            "focused-border" => &mut self.focused_border,
+            "highlight" => &mut self.highlight,
+            "badge" => &mut self.badge,
+            "notebook" => &mut self.notebook,
+            "search-match" => &mut self.search_match,
+            "input" => &mut self.input,
+            "status-bar" => &mut self.status_bar,
+            "delete-modal" => &mut self.delete_modal,
+            "error-modal" => &mut self.error_modal,
+            _ => return Err(anyhow!("Unknown theme element: {}", element)),
+        })
+    }
+}
+
+/// Parses styles like `black on cyan bold`: an optional foreground, `on` and a background, and modifiers.
+/// Colors are names, `#rrggbb` or a number of the 256 color palette.
+pub fn parse_style(style: &str) -> Result<Style> {
+    let mut parsed = Style::default();
+    let mut words = style.split_whitespace();
+    while let Some(word) = words.next() {
+        parsed = match word {
+            "on" => {
+                let color = words
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing background color in style: {}", style))?;
+                parsed.bg(parse_color(color)?)
+            }
+            "bold" => parsed.add_modifier(Modifier::BOLD),
+            "dim" => parsed.add_modifier(Modifier::DIM),
+            "italic" => parsed.add_modifier(Modifier::ITALIC),
+            "underlined" => parsed.add_modifier(Modifier::UNDERLINED),
+            "reversed" => parsed.add_modifier(Modifier::REVERSED),
+            color => parsed.fg(parse_color(color)?),
+        };
+    }
+    Ok(parsed)
+}
+
+fn parse_color(color: &str) -> Result<Color> {
+    let named = match color.to_lowercase().as_str() {
+        "reset" | "default" => Some(Color::Reset),
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "gray" => Some(Color::Gray),
+        "dark-gray" => Some(Color::DarkGray),
+        "light-red" => Some(Color::LightRed),
+        "light-green" => Some(Color::LightGreen),
+        "light-yellow" => Some(Color::LightYellow),
+        "light-blue" => Some(Color::LightBlue),
+        "light-magenta" => Some(Color::LightMagenta),
+        "light-cyan" => Some(Color::LightCyan),
+        "white" => Some(Color::White),
+        _ => None,
+    };
+    if let Some(named) = named {
+        return Ok(named);
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() == 6 {
+            if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                return Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+            }
+        }
+    }
+    color
+        .parse::<u8>()
+        .map(Color::Indexed)
+        .map_err(|_| anyhow!("Unknown color: {}", color))
+}
+
+#[cfg(test)]
+use speculate::speculate;
+
+#[cfg(test)]
+speculate! {
+    describe "parse_style" {
+        it "parses colors and modifiers" {
+            assert_eq!(
+                parse_style("black on cyan bold").unwrap(),
+                Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+            );
+            assert_eq!(parse_style("#ff8000").unwrap(), Style::default().fg(Color::Rgb(255, 128, 0)));
+            assert_eq!(parse_style("on 236").unwrap(), Style::default().bg(Color::Indexed(236)));
+            assert_eq!(parse_style("").unwrap(), Style::default());
+        }
+
+        it "rejects unknown words" {
+            assert!(parse_style("purple").is_err());
+            assert!(parse_style("red on").is_err());
+            assert!(parse_style("#fff").is_err());
+        }
+    }
+
+    describe "from_config" {
+        it "overrides the selected theme" {
+            let config = ThemeConfig::from([
+                ("name".to_string(), "light".to_string()),
+                ("highlight".to_string(), "reversed".to_string()),
+            ]);
+            let theme = Theme::from_config_with(&config, false).unwrap();
+            assert_eq!(theme.highlight, Style::default().add_modifier(Modifier::REVERSED));
+            assert_eq!(theme.badge, Theme::light().badge);
+            assert_eq!(Theme::from_config_with(&ThemeConfig::new(), false).unwrap(), Theme::dark());
+        }
+
+        it "uses no colors when NO_COLOR is set" {
+            let config = ThemeConfig::from([("name".to_string(), "light".to_string())]);
+            assert_eq!(Theme::from_config_with(&config, true).unwrap(), Theme::no_color());
+        }
+
+        it "rejects unknown names and elements" {
+            let config = ThemeConfig::from([("name".to_string(), "solarized".to_string())]);
+            assert!(Theme::from_config_with(&config, false).is_err());
+            let config = ThemeConfig::from([("title".to_string(), "red".to_string())]);
+            assert!(Theme::from_config_with(&config, true).is_err());
+        }
+    }
+}
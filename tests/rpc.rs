@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+#[path = "../src/test_dir.rs"]
+mod test_dir;
+use test_dir::TestDir;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct RpcProcess {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+}
+
+impl RpcProcess {
+    fn spawn(data_dir: &Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_kakisute"))
+            .arg("--data_dir")
+            .arg(data_dir)
+            .arg("rpc")
+            .env("KAKISUTE_CONFIG", data_dir.join("config.toml"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let message = serde_json::from_str(&line.unwrap()).unwrap();
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        RpcProcess {
+            child,
+            stdin,
+            messages,
+            next_id: 1,
+        }
+    }
+
+    fn send_line(&mut self, line: &str) {
+        writeln!(self.stdin, "{}", line).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// Notifications received meanwhile are skipped
+    fn call(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send_line(&request.to_string());
+        self.receive(|message| message["id"] == json!(id))
+    }
+
+    fn receive(&self, matches: impl Fn(&Value) -> bool) -> Value {
+        loop {
+            let message = self.messages.recv_timeout(TIMEOUT).unwrap();
+            if matches(&message) {
+                return message;
+            }
+        }
+    }
+}
+
+impl Drop for RpcProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn setup(name: &str) -> TestDir {
+    let data_dir = TestDir::new(&format!("rpc_{}", name));
+    fs::write(data_dir.join("2022_01_10_16_30_15.sql"), "select 1").unwrap();
+    data_dir
+}
+
+#[test]
+fn test_crud_and_search() {
+    let data_dir = setup("crud");
+    let mut rpc = RpcProcess::spawn(&data_dir);
+
+    let response = rpc.call("list", json!({}));
+    assert_eq!(
+        response["result"],
+        json!([{ "file_name": "2022_01_10_16_30_15.sql", "pinned": false }])
+    );
+
+    let response = rpc.call("create", json!({ "name": "memo.md", "content": "# memo" }));
+    let file_name = response["result"]["file_name"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(file_name.ends_with("_memo.md"));
+
+    let response = rpc.call("get", json!({ "file_name": file_name }));
+    assert_eq!(response["result"]["content"], json!("# memo"));
+
+    rpc.call(
+        "update",
+        json!({ "file_name": file_name, "content": "select 2" }),
+    );
+    let response = rpc.call("search", json!({ "query": "\"select 2\"" }));
+    assert_eq!(response["result"], json!([file_name]));
+
+    let response = rpc.call("toggle_pin", json!({ "file_name": file_name }));
+    assert_eq!(response["result"]["pinned"], json!(true));
+
+    rpc.call("delete", json!({ "file_name": file_name }));
+    let response = rpc.call("list", json!({}));
+    assert_eq!(response["result"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_errors() {
+    let data_dir = setup("errors");
+    let mut rpc = RpcProcess::spawn(&data_dir);
+
+    rpc.send_line("not json");
+    let response = rpc.receive(|message| message.get("error").is_some());
+    assert_eq!(response["error"]["code"], json!(-32700));
+
+    let response = rpc.call("unknown", json!({}));
+    assert_eq!(response["error"]["code"], json!(-32601));
+
+    let response = rpc.call("get", json!({}));
+    assert_eq!(response["error"]["code"], json!(-32602));
+
+    let response = rpc.call("get", json!({ "file_name": "none.txt" }));
+    assert_eq!(response["error"]["code"], json!(-32000));
+}
+
+#[test]
+fn test_change_notification() {
+    let data_dir = setup("notification");
+    let mut rpc = RpcProcess::spawn(&data_dir);
+    // The watcher is running once the first response arrives
+    rpc.call("list", json!({}));
+
+    fs::write(
+        data_dir.join("2022_01_11_09_00_00.txt"),
+        "from another shell",
+    )
+    .unwrap();
+    let notification = rpc.receive(|message| message["method"] == json!("changed"));
+    assert_eq!(
+        notification["params"]["file_names"],
+        json!(["2022_01_11_09_00_00.txt"])
+    );
+
+    let response = rpc.call("list", json!({}));
+    assert_eq!(response["result"].as_array().unwrap().len(), 2);
+}